@@ -0,0 +1,54 @@
+use carrlink::messages::{decode_acknowledgement, decode_status, decode_version};
+use carrlink::protocol::{
+    compute_checksum, decode_uint32, encode_uint32, make_set_word_request, WordAddress,
+};
+use carrlink::ChecksumValidation;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const TRACK_STATUS: &[u8] = b"?:??<:????050021";
+const LAP_STATUS: &[u8] = b"?100102>0417";
+const VERSION: &[u8] = b"053372";
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, frame) in [("track_status", TRACK_STATUS), ("lap_status", LAP_STATUS)] {
+        group.throughput(Throughput::Bytes(frame.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| decode_status(black_box(frame), ChecksumValidation::Strict))
+        });
+    }
+
+    group.throughput(Throughput::Bytes(VERSION.len() as u64));
+    group.bench_function("version", |b| {
+        b.iter(|| decode_version(black_box(VERSION), ChecksumValidation::Strict))
+    });
+
+    let request = make_set_word_request(WordAddress::Speed(1), 8);
+    group.throughput(Throughput::Bytes(1));
+    group.bench_function("acknowledgement", |b| {
+        b.iter(|| decode_acknowledgement(black_box(&request), b"J", ChecksumValidation::Strict))
+    });
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.bench_function("set_word_request", |b| {
+        b.iter(|| make_set_word_request(black_box(WordAddress::Fuel(3)), black_box(12)))
+    });
+    group.bench_function("uint32", |b| b.iter(|| encode_uint32(black_box(123_456))));
+    group.finish();
+}
+
+fn primitives(c: &mut Criterion) {
+    let encoded = encode_uint32(123_456);
+    c.bench_function("decode_uint32", |b| {
+        b.iter(|| decode_uint32(black_box(&encoded)))
+    });
+    c.bench_function("compute_checksum", |b| {
+        b.iter(|| compute_checksum(black_box(&TRACK_STATUS[1..TRACK_STATUS.len() - 1])))
+    });
+}
+
+criterion_group!(benches, decode, encode, primitives);
+criterion_main!(benches);
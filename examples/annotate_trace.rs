@@ -0,0 +1,39 @@
+//! Prints traces with the decoded outcome of every response, which helps annotating new captures
+//! for the conformance corpus. The corpus itself is verified by `cargo test --test conformance`.
+//!
+//! Usage: `annotate_trace <trace>...`
+
+use carrlink::conformance::{describe, Trace};
+use carrlink::ChecksumValidation;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: annotate_trace <trace>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    for path in &paths {
+        let trace = match Trace::load(path) {
+            Ok(trace) => trace,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                failed = true;
+                continue;
+            }
+        };
+
+        for exchange in &trace.exchanges {
+            let actual = describe(&exchange.decode(ChecksumValidation::Strict));
+            println!("{}:{}: = {}", path, exchange.line, actual);
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
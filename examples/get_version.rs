@@ -1,24 +1,10 @@
 use std::{io, time::Duration};
 
-use btleplug::{
-    api::{Central as _, Manager as _},
-    platform::{Adapter, Manager},
-};
-use carrlink::{BackendBLE, ControlUnit};
-use tokio;
-
-async fn find_adapter() -> btleplug::Result<Adapter> {
-    let manager = Manager::new().await?;
-    let adapter_list = manager.adapters().await?;
-    match adapter_list.first() {
-        Some(adapter) => Ok(adapter.to_owned()),
-        None => Err(btleplug::Error::DeviceNotFound),
-    }
-}
+use carrlink::prelude::*;
 
-async fn find_control_unit(adapter: Adapter) -> carrlink::Result<ControlUnit<BackendBLE>> {
+async fn find_control_unit(adapter: &Adapter) -> carrlink::Result<ControlUnit<BackendBLE>> {
     loop {
-        match carrlink::discover_first_ble(&adapter, Duration::from_secs(5)).await? {
+        match discover_first_ble(adapter, Duration::from_secs(5)).await? {
             Some(control_unit) => return Ok(control_unit),
             None => println!("No control unit found"),
         };
@@ -29,14 +15,14 @@ async fn find_control_unit(adapter: Adapter) -> carrlink::Result<ControlUnit<Bac
 async fn main() -> io::Result<()> {
     println!("Search adapter ...");
 
-    let adapter = find_adapter().await.unwrap();
+    let adapter = first_adapter().await.unwrap();
 
     println!(
         "Search control unit on adapter {} ...",
         adapter.adapter_info().await.unwrap()
     );
 
-    let mut control_unit = find_control_unit(adapter).await.unwrap();
+    let mut control_unit = find_control_unit(&adapter).await.unwrap();
 
     println!("Connect to control unit");
     control_unit.connect().await.unwrap();
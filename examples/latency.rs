@@ -0,0 +1,146 @@
+//! Measures the round trip times of status requests, e.g. `latency remote 127.0.0.1:6800 500`.
+//!
+//! The backend is one of `simulated`, `remote <address>` and `ble`, depending on the enabled
+//! features, followed by an optional number of requests.
+
+use std::env;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use carrlink::{Backend, BrakeLevel, ControlUnit, ControllerId, RequestBatch, SpeedLevel};
+
+const DEFAULT_REQUEST_COUNT: usize = 200;
+
+async fn measure<T: Backend>(
+    control_unit: &mut ControlUnit<T>,
+    count: usize,
+) -> carrlink::Result<Vec<Duration>> {
+    let mut round_trips = Vec::with_capacity(count);
+    for _ in 0..count {
+        let started_at = Instant::now();
+        control_unit.get_status().await?;
+        round_trips.push(started_at.elapsed());
+    }
+    Ok(round_trips)
+}
+
+async fn measure_batch<T: Backend>(
+    control_unit: &mut ControlUnit<T>,
+) -> carrlink::Result<Duration> {
+    let mut batch = RequestBatch::new();
+    for player in ControllerId::ALL {
+        batch
+            .set_speed_level(player, SpeedLevel::MAX)
+            .set_brake_level(player, BrakeLevel::MAX);
+    }
+
+    let started_at = Instant::now();
+    control_unit.send_batch(&batch).await?;
+    Ok(started_at.elapsed())
+}
+
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    sorted[(sorted.len() - 1) * percent / 100]
+}
+
+fn report(name: &str, mut round_trips: Vec<Duration>) {
+    if round_trips.is_empty() {
+        return;
+    }
+
+    round_trips.sort();
+    let mean = round_trips.iter().sum::<Duration>() / round_trips.len() as u32;
+    println!(
+        "{:<8} n={} min={:?} mean={:?} p50={:?} p95={:?} p99={:?} max={:?}",
+        name,
+        round_trips.len(),
+        round_trips[0],
+        mean,
+        percentile(&round_trips, 50),
+        percentile(&round_trips, 95),
+        percentile(&round_trips, 99),
+        round_trips[round_trips.len() - 1]
+    );
+}
+
+async fn run<T: Backend>(
+    name: &str,
+    mut control_unit: ControlUnit<T>,
+    count: usize,
+) -> carrlink::Result<()> {
+    control_unit.connect().await?;
+
+    report(name, measure(&mut control_unit, count).await?);
+    println!(
+        "{:<8} batch of {} word writes took {:?}",
+        name,
+        carrlink::MAX_CONTROLLER_COUNT * 2,
+        measure_batch(&mut control_unit).await?
+    );
+
+    control_unit.disconnect().await
+}
+
+#[cfg(feature = "ble")]
+async fn run_ble(count: usize) -> carrlink::Result<()> {
+    use btleplug::api::Manager as _;
+    use btleplug::platform::Manager;
+
+    let manager = Manager::new().await?;
+    let adapter = manager
+        .adapters()
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(carrlink::Error::DeviceNotFound)?;
+    let control_unit = carrlink::discover_first_ble(&adapter, Duration::from_secs(10))
+        .await?
+        .ok_or(carrlink::Error::DeviceNotFound)?;
+    run("ble", control_unit, count).await
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (backend, rest) = match args.split_first() {
+        Some((backend, rest)) => (backend.as_str(), rest),
+        None => ("simulated", &[][..]),
+    };
+    let count_arg = |index: usize| {
+        rest.get(index)
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(DEFAULT_REQUEST_COUNT)
+    };
+
+    let result = match backend {
+        #[cfg(feature = "simulator")]
+        "simulated" => {
+            let backend = carrlink::SimulatedBackend::new(8);
+            run("simulated", ControlUnit::new(backend), count_arg(0)).await
+        }
+        #[cfg(feature = "remote")]
+        "remote" => match rest.first() {
+            Some(address) => {
+                let backend = carrlink::RemoteBackend::new(address);
+                run("remote", ControlUnit::new(backend), count_arg(1)).await
+            }
+            None => Err(carrlink::Error::InvalidArgument(
+                "missing address of the remote backend".to_owned(),
+            )),
+        },
+        #[cfg(feature = "ble")]
+        "ble" => run_ble(count_arg(0)).await,
+        backend => Err(carrlink::Error::InvalidArgument(format!(
+            "backend {} is unknown or its feature is not enabled",
+            backend
+        ))),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,23 +1,10 @@
 use std::{io, time::Duration};
 
-use btleplug::{
-    api::{Central as _, Manager as _},
-    platform::{Adapter, Manager},
-};
-use carrlink::{BackendBLE, ControlUnit};
-
-async fn find_adapter() -> btleplug::Result<Adapter> {
-    let manager = Manager::new().await?;
-    let adapter_list = manager.adapters().await?;
-    match adapter_list.first() {
-        Some(adapter) => Ok(adapter.to_owned()),
-        None => Err(btleplug::Error::DeviceNotFound),
-    }
-}
+use carrlink::prelude::*;
 
-async fn find_control_unit(adapter: Adapter) -> carrlink::Result<ControlUnit<BackendBLE>> {
+async fn find_control_unit(adapter: &Adapter) -> carrlink::Result<ControlUnit<BackendBLE>> {
     loop {
-        match carrlink::discover_first_ble(&adapter, Duration::from_secs(5)).await? {
+        match discover_first_ble(adapter, Duration::from_secs(5)).await? {
             Some(control_unit) => return Ok(control_unit),
             None => println!("No control unit found"),
         };
@@ -33,21 +20,25 @@ async fn main() -> io::Result<()> {
     }
 
     println!("Search adapter ...");
-    let adapter = find_adapter().await.unwrap();
+    let adapter = first_adapter().await.unwrap();
 
     println!(
         "Search control unit on adapter {} ...",
         adapter.adapter_info().await.unwrap()
     );
 
-    let mut control_unit = find_control_unit(adapter).await.unwrap();
+    let mut control_unit = find_control_unit(&adapter).await.unwrap();
 
     println!("Connect to control unit");
     control_unit.connect().await.unwrap();
 
-    let player = args[1].parse::<usize>().unwrap();
-    let level = args[2].parse::<usize>().unwrap();
-    println!("Set speed of player #{} to {}", player, level);
+    let player = ControllerId::try_from(args[1].parse::<usize>().unwrap()).unwrap();
+    let level = SpeedLevel::try_from(args[2].parse::<u8>().unwrap()).unwrap();
+    println!(
+        "Set speed of player #{} to {}",
+        player.index(),
+        level.value()
+    );
     control_unit.set_speed_level(player, level).await.unwrap();
 
     println!("Disconnect from control unit");
@@ -0,0 +1,69 @@
+/*
+ * C API of carrlink, a library for interfacing with the Carrera control unit.
+ *
+ * Every function which can fail returns CARRLINK_OK on success and the stable
+ * error code of carrlink otherwise, e.g. 3 if not connected or 6 on a timeout.
+ * Handles must not be used from several threads at the same time.
+ */
+
+#ifndef CARRLINK_H
+#define CARRLINK_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CARRLINK_OK 0
+
+#define CARRLINK_STATUS_NO_DATA 0
+#define CARRLINK_STATUS_LAP 1
+#define CARRLINK_STATUS_TRACK 2
+
+typedef struct CarrlinkControlUnit CarrlinkControlUnit;
+
+typedef struct CarrlinkStatus {
+    uint32_t kind;
+    /* lap status */
+    uint32_t controller;
+    uint32_t sector;
+    uint32_t time_ms;
+    /* track status */
+    uint8_t fuel_levels[8];
+    uint8_t refueling_mask;
+    uint8_t start_signal;
+    uint8_t mode;
+    uint8_t controller_count;
+} CarrlinkStatus;
+
+typedef struct CarrlinkLap {
+    uint32_t controller;
+    uint32_t number;
+    uint32_t lap_time_ms;
+    uint32_t timestamp_ms;
+    bool is_valid;
+} CarrlinkLap;
+
+typedef void (*CarrlinkLapCallback)(void *user_data, const CarrlinkLap *lap);
+
+uint32_t carrlink_discover(uint32_t timeout_ms, CarrlinkControlUnit **result);
+void carrlink_free(CarrlinkControlUnit *handle);
+
+uint32_t carrlink_connect(CarrlinkControlUnit *handle);
+uint32_t carrlink_disconnect(CarrlinkControlUnit *handle);
+
+uint32_t carrlink_set_lap_callback(CarrlinkControlUnit *handle, CarrlinkLapCallback callback, void *user_data);
+uint32_t carrlink_poll_status(CarrlinkControlUnit *handle, CarrlinkStatus *result);
+
+uint32_t carrlink_start(CarrlinkControlUnit *handle);
+uint32_t carrlink_set_speed(CarrlinkControlUnit *handle, uint32_t controller, uint8_t level);
+uint32_t carrlink_set_brake(CarrlinkControlUnit *handle, uint32_t controller, uint8_t level);
+uint32_t carrlink_set_fuel(CarrlinkControlUnit *handle, uint32_t controller, uint8_t level);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,304 @@
+//! C API of carrlink.
+//!
+//! The API is declared in `include/carrlink.h`. Control units are represented by opaque handles
+//! which are created by [`carrlink_discover`] and released by [`carrlink_free`]. Every function
+//! which can fail returns zero on success and the stable code of the `carrlink::Error` otherwise.
+
+use btleplug::api::Manager as _;
+use btleplug::platform::Manager;
+use carrlink::{
+    BackendBLE, BrakeLevel, ControlUnit, ControllerId, FuelLevel, RaceSession, SpeedLevel, Status,
+};
+use std::ffi::c_void;
+use std::time::Duration;
+
+/// Code which is returned on success.
+const CARRLINK_OK: u32 = 0;
+
+/// Code which is returned for a null handle or output pointer, same as `Error::InvalidArgument`.
+const CARRLINK_INVALID_ARGUMENT: u32 = 5;
+
+/// Callback which is invoked for every lap completed while polling.
+pub type CarrlinkLapCallback = extern "C" fn(user_data: *mut c_void, lap: *const CarrlinkLap);
+
+/// Opaque handle of a control unit.
+pub struct CarrlinkControlUnit {
+    runtime: tokio::runtime::Runtime,
+    control_unit: ControlUnit<BackendBLE>,
+    session: RaceSession,
+    lap_callback: Option<(CarrlinkLapCallback, *mut c_void)>,
+}
+
+/// Kind of a [`CarrlinkStatus`].
+pub const CARRLINK_STATUS_NO_DATA: u32 = 0;
+pub const CARRLINK_STATUS_LAP: u32 = 1;
+pub const CARRLINK_STATUS_TRACK: u32 = 2;
+
+/// Status of the control unit, the fields which do not belong to its kind are zero.
+#[repr(C)]
+#[derive(Default)]
+pub struct CarrlinkStatus {
+    pub kind: u32,
+    pub controller: u32,
+    pub sector: u32,
+    pub time_ms: u32,
+    pub fuel_levels: [u8; 8],
+    pub refueling_mask: u8,
+    pub start_signal: u8,
+    pub mode: u8,
+    pub controller_count: u8,
+}
+
+/// Lap which was completed by a controller.
+#[repr(C)]
+pub struct CarrlinkLap {
+    pub controller: u32,
+    pub number: u32,
+    pub lap_time_ms: u32,
+    pub timestamp_ms: u32,
+    pub is_valid: bool,
+}
+
+fn code_of(result: carrlink::Result<()>) -> u32 {
+    match result {
+        Ok(()) => CARRLINK_OK,
+        Err(error) => error.code(),
+    }
+}
+
+/// Runs the operation on the control unit of the handle.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+unsafe fn with_handle<F>(handle: *mut CarrlinkControlUnit, operation: F) -> u32
+where
+    F: FnOnce(&mut CarrlinkControlUnit) -> carrlink::Result<()>,
+{
+    // SAFETY: guaranteed by the caller
+    match unsafe { handle.as_mut() } {
+        Some(handle) => code_of(operation(handle)),
+        None => CARRLINK_INVALID_ARGUMENT,
+    }
+}
+
+async fn discover(timeout: Duration) -> carrlink::Result<Option<ControlUnit<BackendBLE>>> {
+    let manager = Manager::new().await?;
+    let adapters = manager.adapters().await?;
+    match adapters.first() {
+        Some(adapter) => carrlink::discover_first_ble(adapter, timeout).await,
+        None => Err(carrlink::Error::DeviceNotFound),
+    }
+}
+
+/// Searches for a control unit with the first bluetooth adapter and stores its handle in `result`.
+///
+/// # Safety
+/// The result must point to writable memory for a handle.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_discover(
+    timeout_ms: u32,
+    result: *mut *mut CarrlinkControlUnit,
+) -> u32 {
+    if result.is_null() {
+        return CARRLINK_INVALID_ARGUMENT;
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(error) => return carrlink::Error::Other(Box::new(error)).code(),
+    };
+
+    let control_unit = match runtime.block_on(discover(Duration::from_millis(timeout_ms as u64))) {
+        Ok(Some(control_unit)) => control_unit,
+        Ok(None) => return carrlink::Error::DeviceNotFound.code(),
+        Err(error) => return error.code(),
+    };
+
+    let handle = Box::new(CarrlinkControlUnit {
+        runtime,
+        control_unit,
+        session: RaceSession::new(),
+        lap_callback: None,
+    });
+    // SAFETY: checked for null above
+    unsafe { *result = Box::into_raw(handle) };
+    CARRLINK_OK
+}
+
+/// Releases the handle, it must not be used afterwards.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_free(handle: *mut CarrlinkControlUnit) {
+    if !handle.is_null() {
+        // SAFETY: the handle was created by carrlink_discover via Box::into_raw
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Connects to the control unit.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_connect(handle: *mut CarrlinkControlUnit) -> u32 {
+    with_handle(handle, |handle| {
+        handle.runtime.block_on(handle.control_unit.connect())
+    })
+}
+
+/// Disconnects from the control unit.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_disconnect(handle: *mut CarrlinkControlUnit) -> u32 {
+    with_handle(handle, |handle| {
+        handle.runtime.block_on(handle.control_unit.disconnect())
+    })
+}
+
+/// Registers the callback which is invoked for every lap completed while polling.
+/// Passing a null callback removes the registered one.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_set_lap_callback(
+    handle: *mut CarrlinkControlUnit,
+    callback: Option<CarrlinkLapCallback>,
+    user_data: *mut c_void,
+) -> u32 {
+    with_handle(handle, |handle| {
+        handle.lap_callback = callback.map(|callback| (callback, user_data));
+        Ok(())
+    })
+}
+
+/// Polls the next status of the control unit and stores it in `result`.
+/// Lap statuses are tracked by a session, which invokes the lap callback for completed laps.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+/// The result must point to writable memory for a status.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_poll_status(
+    handle: *mut CarrlinkControlUnit,
+    result: *mut CarrlinkStatus,
+) -> u32 {
+    if result.is_null() {
+        return CARRLINK_INVALID_ARGUMENT;
+    }
+
+    with_handle(handle, |handle| {
+        let status = handle.runtime.block_on(handle.control_unit.get_status())?;
+
+        if let (Some(lap), Some((callback, user_data))) =
+            (handle.session.update(&status), handle.lap_callback)
+        {
+            let lap = CarrlinkLap {
+                controller: lap.controller.index() as u32,
+                number: lap.number as u32,
+                lap_time_ms: lap.lap_time.as_millis() as u32,
+                timestamp_ms: lap.time.raw(),
+                is_valid: lap.is_valid,
+            };
+            callback(user_data, &lap);
+        }
+
+        let mut converted = CarrlinkStatus::default();
+        match status {
+            Status::Lap(status) => {
+                converted.kind = CARRLINK_STATUS_LAP;
+                converted.controller = status.controller.index() as u32;
+                converted.sector = status.sector as u32;
+                converted.time_ms = status.time.raw();
+            }
+            Status::Track(status) => {
+                converted.kind = CARRLINK_STATUS_TRACK;
+                converted.fuel_levels = status.fuel_levels.map(|level| level.value());
+                converted.refueling_mask = status
+                    .is_refueling
+                    .iter()
+                    .enumerate()
+                    .fold(0, |mask, (index, is_refueling)| {
+                        mask | ((*is_refueling as u8) << index)
+                    });
+                converted.start_signal = status.start_signal as u8;
+                converted.mode = status.mode.bits();
+                converted.controller_count = status.controller_count as u8;
+            }
+            _ => converted.kind = CARRLINK_STATUS_NO_DATA,
+        }
+
+        // SAFETY: checked for null above
+        unsafe { *result = converted };
+        Ok(())
+    })
+}
+
+/// Starts the race countdown.
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_start(handle: *mut CarrlinkControlUnit) -> u32 {
+    with_handle(handle, |handle| {
+        handle.runtime.block_on(handle.control_unit.start())
+    })
+}
+
+/// Sets the speed level of the given controller to a value in [0, 15].
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_set_speed(
+    handle: *mut CarrlinkControlUnit,
+    controller: u32,
+    level: u8,
+) -> u32 {
+    with_handle(handle, |handle| {
+        let controller = ControllerId::try_from(controller as usize)?;
+        let level = SpeedLevel::try_from(level)?;
+        let request = handle.control_unit.set_speed_level(controller, level);
+        handle.runtime.block_on(request)
+    })
+}
+
+/// Sets the brake level of the given controller to a value in [0, 15].
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_set_brake(
+    handle: *mut CarrlinkControlUnit,
+    controller: u32,
+    level: u8,
+) -> u32 {
+    with_handle(handle, |handle| {
+        let controller = ControllerId::try_from(controller as usize)?;
+        let level = BrakeLevel::try_from(level)?;
+        let request = handle.control_unit.set_brake_level(controller, level);
+        handle.runtime.block_on(request)
+    })
+}
+
+/// Sets the fuel level of the given controller to a value in [0, 15].
+///
+/// # Safety
+/// The handle must be null or created by [`carrlink_discover`] and not be used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn carrlink_set_fuel(
+    handle: *mut CarrlinkControlUnit,
+    controller: u32,
+    level: u8,
+) -> u32 {
+    with_handle(handle, |handle| {
+        let controller = ControllerId::try_from(controller as usize)?;
+        let level = FuelLevel::try_from(level)?;
+        let request = handle.control_unit.set_fuel_level(controller, level);
+        handle.runtime.block_on(request)
+    })
+}
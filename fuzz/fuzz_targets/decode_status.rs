@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = carrlink::fuzzing::decode_status(data, carrlink::ChecksumValidation::Strict);
+});
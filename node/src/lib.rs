@@ -0,0 +1,275 @@
+//! Node.js bindings of carrlink.
+//!
+//! The bindings are built with napi-rs into a native addon which can be loaded by Node.js and
+//! Electron. Control units are discovered with `discover` and report their statuses as stream of
+//! events to the callback registered with `ControlUnit.subscribe`.
+
+use btleplug::api::Manager as _;
+use btleplug::platform::Manager;
+use carrlink::{
+    BackendBLE, BrakeLevel, ControllerId, FuelLevel, RaceSession, SpeedLevel, StartDetector,
+    StartEvent, Status,
+};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::JsFunction;
+use napi_derive::napi;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Interval between two status requests if none is given to `subscribe`.
+const DEFAULT_POLL_INTERVAL_MS: u32 = 20;
+
+/// Converts the error into a JavaScript error whose message starts with the name of the error.
+fn to_js(error: carrlink::Error) -> Error {
+    Error::new(
+        napi::Status::GenericFailure,
+        format!("[{}] {}", error.name(), error),
+    )
+}
+
+/// Lap which was completed by a controller.
+#[napi(object)]
+pub struct Lap {
+    pub controller: u32,
+    pub number: u32,
+    pub lap_time_ms: u32,
+    pub timestamp_ms: u32,
+    pub is_valid: bool,
+}
+
+/// Status of the track as reported by the control unit.
+#[napi(object)]
+pub struct TrackStatus {
+    pub fuel_levels: Vec<u32>,
+    pub is_refueling: Vec<bool>,
+    pub start_signal: u32,
+    pub mode: u32,
+    pub controller_count: u32,
+}
+
+/// Event which is passed to the callback registered with `ControlUnit.subscribe`.
+/// The kind is one of `lap`, `track`, `start` or `error` and determines the populated field.
+#[napi(object)]
+pub struct RaceEvent {
+    pub kind: String,
+    pub lap: Option<Lap>,
+    pub track: Option<TrackStatus>,
+    pub error: Option<String>,
+}
+
+impl RaceEvent {
+    fn new(kind: &str) -> RaceEvent {
+        RaceEvent {
+            kind: kind.to_owned(),
+            lap: None,
+            track: None,
+            error: None,
+        }
+    }
+}
+
+impl From<&carrlink::Lap> for Lap {
+    fn from(lap: &carrlink::Lap) -> Self {
+        Lap {
+            controller: lap.controller.index() as u32,
+            number: lap.number as u32,
+            lap_time_ms: lap.lap_time.as_millis() as u32,
+            timestamp_ms: lap.time.as_millis(),
+            is_valid: lap.is_valid,
+        }
+    }
+}
+
+impl From<&carrlink::TrackStatus> for TrackStatus {
+    fn from(status: &carrlink::TrackStatus) -> Self {
+        TrackStatus {
+            fuel_levels: status
+                .fuel_levels
+                .iter()
+                .map(|level| level.value() as u32)
+                .collect(),
+            is_refueling: status.is_refueling.to_vec(),
+            start_signal: status.start_signal as u32,
+            mode: status.mode.bits() as u32,
+            controller_count: status.controller_count as u32,
+        }
+    }
+}
+
+type EventCallback = ThreadsafeFunction<RaceEvent, ErrorStrategy::Fatal>;
+
+/// Polls the control unit and passes the derived events to the callback until aborted.
+async fn poll(
+    control_unit: Arc<Mutex<carrlink::ControlUnit<BackendBLE>>>,
+    callback: EventCallback,
+    interval: Duration,
+) {
+    let mut session = RaceSession::new();
+    let mut start_detector = StartDetector::new();
+
+    loop {
+        let status = control_unit.lock().await.get_status().await;
+        match status {
+            Ok(status) => {
+                if let Some(StartEvent::RaceStarted) = start_detector.update_status(&status) {
+                    session.restart_timing();
+                    callback.call(
+                        RaceEvent::new("start"),
+                        ThreadsafeFunctionCallMode::NonBlocking,
+                    );
+                }
+                if let Status::Track(track) = &status {
+                    let mut event = RaceEvent::new("track");
+                    event.track = Some(track.into());
+                    callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+                if let Some(lap) = session.update(&status) {
+                    let mut event = RaceEvent::new("lap");
+                    event.lap = Some((&lap).into());
+                    callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            }
+            Err(error) => {
+                let fatal = !error.is_transient();
+                let mut event = RaceEvent::new("error");
+                event.error = Some(to_js(error).reason);
+                callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+                if fatal {
+                    return;
+                }
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Control unit which is connected via bluetooth.
+#[napi]
+pub struct ControlUnit {
+    inner: Arc<Mutex<carrlink::ControlUnit<BackendBLE>>>,
+    poller: Option<JoinHandle<()>>,
+}
+
+#[napi]
+impl ControlUnit {
+    /// Connects to the control unit.
+    #[napi]
+    pub async fn connect(&self) -> Result<()> {
+        self.inner.lock().await.connect().await.map_err(to_js)
+    }
+
+    /// Disconnects from the control unit.
+    #[napi]
+    pub async fn disconnect(&self) -> Result<()> {
+        self.inner.lock().await.disconnect().await.map_err(to_js)
+    }
+
+    /// Determines if the control unit is connected.
+    #[napi]
+    pub async fn is_connected(&self) -> Result<bool> {
+        self.inner.lock().await.is_connected().await.map_err(to_js)
+    }
+
+    /// Returns the firmware version of the control unit.
+    #[napi]
+    pub async fn get_version(&self) -> Result<String> {
+        self.inner.lock().await.get_version().await.map_err(to_js)
+    }
+
+    /// Starts the race, i.e. presses the start button of the control unit.
+    #[napi]
+    pub async fn start(&self) -> Result<()> {
+        self.inner.lock().await.start().await.map_err(to_js)
+    }
+
+    /// Sets the speed level of the controller.
+    #[napi]
+    pub async fn set_speed(&self, controller: u32, level: u32) -> Result<()> {
+        let controller = ControllerId::try_from(controller as usize).map_err(to_js)?;
+        let level = SpeedLevel::try_from(level.min(u8::MAX as u32) as u8).map_err(to_js)?;
+        let mut control_unit = self.inner.lock().await;
+        control_unit
+            .set_speed_level(controller, level)
+            .await
+            .map_err(to_js)
+    }
+
+    /// Sets the brake level of the controller.
+    #[napi]
+    pub async fn set_brake(&self, controller: u32, level: u32) -> Result<()> {
+        let controller = ControllerId::try_from(controller as usize).map_err(to_js)?;
+        let level = BrakeLevel::try_from(level.min(u8::MAX as u32) as u8).map_err(to_js)?;
+        let mut control_unit = self.inner.lock().await;
+        control_unit
+            .set_brake_level(controller, level)
+            .await
+            .map_err(to_js)
+    }
+
+    /// Sets the fuel level of the controller.
+    #[napi]
+    pub async fn set_fuel(&self, controller: u32, level: u32) -> Result<()> {
+        let level = FuelLevel::try_from(level.min(u8::MAX as u32) as u8).map_err(to_js)?;
+        let controller = ControllerId::try_from(controller as usize).map_err(to_js)?;
+        let mut control_unit = self.inner.lock().await;
+        control_unit
+            .set_fuel_level(controller, level)
+            .await
+            .map_err(to_js)
+    }
+
+    /// Starts polling the control unit and passes every derived event to the callback.
+    /// A previous subscription is replaced. Polling stops on errors which are not transient.
+    #[napi(ts_args_type = "callback: (event: RaceEvent) => void, intervalMs?: number")]
+    pub fn subscribe(&mut self, callback: JsFunction, interval_ms: Option<u32>) -> Result<()> {
+        let callback: EventCallback = callback
+            .create_threadsafe_function(0, |context: ThreadSafeCallContext<RaceEvent>| {
+                Ok(vec![context.value])
+            })?;
+        let interval =
+            Duration::from_millis(interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS) as u64);
+
+        self.unsubscribe();
+        self.poller = Some(spawn(poll(self.inner.clone(), callback, interval)));
+        Ok(())
+    }
+
+    /// Stops polling the control unit.
+    #[napi]
+    pub fn unsubscribe(&mut self) {
+        if let Some(poller) = self.poller.take() {
+            poller.abort();
+        }
+    }
+}
+
+impl Drop for ControlUnit {
+    fn drop(&mut self) {
+        self.unsubscribe();
+    }
+}
+
+/// Searches for a control unit with the first bluetooth adapter.
+/// Resolves to null if no control unit was found within the timeout.
+#[napi]
+pub async fn discover(timeout_ms: u32) -> Result<Option<ControlUnit>> {
+    let manager = Manager::new().await.map_err(|e| to_js(e.into()))?;
+    let adapters = manager.adapters().await.map_err(|e| to_js(e.into()))?;
+    let adapter = adapters
+        .first()
+        .ok_or_else(|| to_js(carrlink::Error::DeviceNotFound))?;
+
+    let control_unit =
+        carrlink::discover_first_ble(adapter, Duration::from_millis(timeout_ms as u64))
+            .await
+            .map_err(to_js)?;
+    Ok(control_unit.map(|control_unit| ControlUnit {
+        inner: Arc::new(Mutex::new(control_unit)),
+        poller: None,
+    }))
+}
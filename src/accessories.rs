@@ -0,0 +1,61 @@
+//! Module which describes the accessories connected to the control unit.
+
+use crate::{TrackMode, TrackStatus};
+
+/// Whether an accessory is connected to the control unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AccessoryState {
+    /// The control unit reports the accessory as connected.
+    Connected,
+
+    /// The control unit reports the accessory as not connected.
+    Disconnected,
+
+    /// The control unit does not report whether the accessory is connected.
+    Unknown,
+}
+
+impl AccessoryState {
+    fn from_mode(mode: TrackMode, flag: TrackMode) -> AccessoryState {
+        match mode.contains(flag) {
+            true => AccessoryState::Connected,
+            false => AccessoryState::Disconnected,
+        }
+    }
+}
+
+/// Report of the accessories which are connected to the control unit.
+///
+/// Only the pit lane and the lap counter adapter are reported by the mode of the track status.
+/// The position tower and the startlight only receive data from the control unit and give no
+/// feedback, so they are always [`Unknown`](AccessoryState::Unknown).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Accessories {
+    /// Pit lane adapter, which refuels the cars.
+    pub pit_lane: AccessoryState,
+
+    /// Lap counter adapter.
+    pub lap_counter: AccessoryState,
+
+    /// Position tower, which displays positions and the current lap.
+    pub position_tower: AccessoryState,
+
+    /// Startlight, which displays the countdown.
+    pub start_light: AccessoryState,
+}
+
+impl Accessories {
+    /// Creates the report from the mode of the given track status.
+    pub fn from_track_status(status: &TrackStatus) -> Accessories {
+        Accessories {
+            pit_lane: AccessoryState::from_mode(status.mode, TrackMode::PIT_LANE),
+            lap_counter: AccessoryState::from_mode(status.mode, TrackMode::LAP_COUNTER),
+            position_tower: AccessoryState::Unknown,
+            start_light: AccessoryState::Unknown,
+        }
+    }
+}
@@ -1,12 +1,48 @@
 //! Module which defines backend trait which allows to use different
 //! communication channels with control units.
 
+use crate::{DecodeError, Error, Frame};
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Bound which requires backends to be sendable between threads, except in browsers,
+/// where the futures of the Web Bluetooth API can not be sent.
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(hidden)]
+pub trait MaybeSend: Send {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+#[cfg(target_arch = "wasm32")]
+#[doc(hidden)]
+pub trait MaybeSend {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// Bound which requires backends to be shareable between threads, except in browsers.
+/// Sending the future of [`Backend::is_connected`] requires it anyway, as it borrows the backend.
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(hidden)]
+pub trait MaybeSync: Sync {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Sync> MaybeSync for T {}
+
+#[cfg(target_arch = "wasm32")]
+#[doc(hidden)]
+pub trait MaybeSync {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSync for T {}
 
 /// Backend which determines the communication channel with a control unit.
-#[async_trait]
-pub trait Backend {
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Backend: MaybeSend + MaybeSync {
     /// Establishes a connection with the control unit.
     async fn connect(&mut self) -> crate::Result<()>;
 
@@ -17,5 +53,178 @@ pub trait Backend {
     async fn is_connected(&self) -> crate::Result<bool>;
 
     /// Sends a request with the given timeout to the control unit and waits for a response.
-    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Vec<u8>>;
+    /// Responses which exceed the capacity of a [`Frame`] are no valid responses of a control unit.
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame>;
+
+    /// Sends multiple requests with the given timeout to the control unit and waits for all responses.
+    /// The responses are returned in the order of the requests.
+    /// Backends which are able to pipeline requests should override the default implementation,
+    /// which sends one request after another. Pipelining backends should bound the whole batch
+    /// by [`batch_timeout`].
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        let mut responses = Vec::with_capacity(requests.len());
+        for request in requests {
+            responses.push(self.request(request, timeout).await?);
+        }
+        Ok(responses)
+    }
+
+    /// Drops responses which arrived after their request failed, so they are not mistaken for
+    /// the response of the next request. Backends which match responses to their requests need
+    /// not override the default implementation, which does nothing.
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// Boxed backends, e.g. a `Box<dyn Backend>` which is selected at runtime, are backends as well.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<T: Backend + ?Sized> Backend for Box<T> {
+    async fn connect(&mut self) -> crate::Result<()> {
+        (**self).connect().await
+    }
+
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        (**self).disconnect().await
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        (**self).is_connected().await
+    }
+
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        (**self).request(data, timeout).await
+    }
+
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        (**self).request_batch(requests, timeout).await
+    }
+
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        (**self).discard_stale_responses().await
+    }
+}
+
+/// Borrowed backends are backends as well, so a control unit can use a backend it does not own.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<T: Backend + ?Sized> Backend for &mut T {
+    async fn connect(&mut self) -> crate::Result<()> {
+        (**self).connect().await
+    }
+
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        (**self).disconnect().await
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        (**self).is_connected().await
+    }
+
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        (**self).request(data, timeout).await
+    }
+
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        (**self).request_batch(requests, timeout).await
+    }
+
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        (**self).discard_stale_responses().await
+    }
+}
+
+/// Shared backends are backends as well, every call locks the backend until it completed.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<T: Backend> Backend for Arc<Mutex<T>> {
+    async fn connect(&mut self) -> crate::Result<()> {
+        self.lock().await.connect().await
+    }
+
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        self.lock().await.disconnect().await
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        self.lock().await.is_connected().await
+    }
+
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        self.lock().await.request(data, timeout).await
+    }
+
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        self.lock().await.request_batch(requests, timeout).await
+    }
+
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        self.lock().await.discard_stale_responses().await
+    }
+}
+
+/// Copies a response into a frame, responses exceeding its capacity are rejected.
+#[cfg_attr(
+    not(any(
+        feature = "remote",
+        feature = "simulator",
+        all(feature = "ble", not(target_arch = "wasm32")),
+        all(feature = "web-bluetooth", target_arch = "wasm32")
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn to_frame(response: &[u8]) -> crate::Result<Frame> {
+    Frame::try_from(response)
+        .map_err(|_| Error::invalid_response(response, DecodeError::WrongLength))
+}
+
+/// Returns the time a pipelined batch of the given number of requests may take, which is the
+/// timeout of every request plus one for the transport.
+#[cfg_attr(
+    not(any(feature = "remote", all(feature = "ble", not(target_arch = "wasm32")))),
+    allow(dead_code)
+)]
+pub(crate) fn batch_timeout(timeout: Duration, request_count: usize) -> Duration {
+    let request_count = u32::try_from(request_count).unwrap_or(u32::MAX);
+    timeout.saturating_mul(request_count.saturating_add(1))
+}
+
+/// Brings a response received via bluetooth into the common format of all backends.
+#[cfg_attr(
+    not(any(
+        all(feature = "ble", not(target_arch = "wasm32")),
+        all(feature = "web-bluetooth", target_arch = "wasm32")
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn normalize_ble_response(request: &[u8], response: &[u8]) -> crate::Result<Frame> {
+    // BLE data is mostly tailored by a $ and they miss the command character
+    // bring this data buffer into a common format while copying it into the frame
+    let (Some((b'$', payload)), Some(command)) = (response.split_last(), request.first()) else {
+        return to_frame(response);
+    };
+
+    let mut frame = Frame::new();
+    frame.push(*command);
+    frame
+        .try_extend_from_slice(payload)
+        .map_err(|_| Error::invalid_response(response, DecodeError::WrongLength))?;
+    Ok(frame)
 }
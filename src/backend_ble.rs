@@ -1,18 +1,21 @@
 //! Module which implements a bluetooth low energy backend with routines for
 //! connecting, disconnecting and sending requests.
 
+use std::pin::Pin;
+use std::sync::Mutex;
 use std::time::Duration;
 
-use crate::{Backend, ControlUnit, Error};
+use crate::backend::{batch_timeout, normalize_ble_response};
+use crate::messages::{STATUS_REQUEST, VERSION_REQUEST};
+use crate::{Backend, Error, Frame, Operation, PeripheralIdentity};
 use async_trait::async_trait;
-use btleplug::api::{
-    Central as _, CentralEvent, Characteristic, Peripheral as _, ScanFilter, WriteType,
-};
-use btleplug::platform::{Adapter, Peripheral};
-use futures::stream::StreamExt;
+use btleplug::api::{Characteristic, Peripheral as _, ValueNotification, WriteType};
+use btleplug::platform::Peripheral;
+use futures::stream::{Stream, StreamExt};
+use futures::FutureExt;
 use uuid::{uuid, Uuid};
 
-const SERVICE_UUID: Uuid = uuid!("39df7777-b1b4-b90b-57f1-7144ae4e4a6a");
+pub(crate) const SERVICE_UUID: Uuid = uuid!("39df7777-b1b4-b90b-57f1-7144ae4e4a6a");
 const NOTIFY_UUID: Uuid = uuid!("39df9999-b1b4-b90b-57f1-7144ae4e4a6a");
 const OUTPUT_UUID: Uuid = uuid!("39df8888-b1b4-b90b-57f1-7144ae4e4a6a");
 
@@ -24,26 +27,78 @@ impl From<btleplug::Error> for crate::Error {
             btleplug::Error::NotConnected => crate::Error::NotConnected,
             btleplug::Error::Other(contained) => crate::Error::Other(contained),
             btleplug::Error::NotSupported(msg) => crate::Error::NotSupported(msg),
-            btleplug::Error::TimedOut(_) => crate::Error::TimedOut,
+            btleplug::Error::TimedOut(timeout) => crate::Error::TimedOut {
+                operation: Operation::Request,
+                timeout,
+            },
             btleplug::Error::RuntimeError(msg) => crate::Error::RuntimeError(msg),
-            _ => crate::Error::Other(Box::new(value)),
+            _ => crate::Error::Bluetooth(value),
         }
     }
 }
 
+type NotificationStream = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+
 struct EndpointsBLE {
     notify_char: Characteristic,
     output_char: Characteristic,
+    // the stream is kept for the whole connection instead of subscribing for every request,
+    // it is only accessed mutably, the mutex merely makes the backend shareable between threads
+    notifications: Mutex<NotificationStream>,
+}
+
+impl EndpointsBLE {
+    fn notifications(&mut self) -> &mut NotificationStream {
+        match self.notifications.get_mut() {
+            Ok(notifications) => notifications,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Drops the responses of requests which timed out earlier.
+    fn discard_notifications(&mut self) {
+        while let Some(Some(_)) = self.notifications().next().now_or_never() {}
+    }
+}
+
+/// Category of a request which determines the write type it is sent with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestCategory {
+    /// Status and firmware version requests, which are sent repeatedly while polling.
+    Poll,
+
+    /// Requests which change the state of the control unit, e.g. button presses or word writes.
+    Command,
+}
+
+impl RequestCategory {
+    /// Determines the category of the given request.
+    pub fn of(request: &[u8]) -> RequestCategory {
+        if request == STATUS_REQUEST || request == VERSION_REQUEST {
+            RequestCategory::Poll
+        } else {
+            RequestCategory::Command
+        }
+    }
 }
 
 /// Backend which manages a bluetooth connection with the control unit.
+///
+/// All requests are written without response by default. Writes with response are acknowledged
+/// by the control unit on the link layer, which makes them more reliable but slower, so they
+/// are best used for critical commands like the race start while polling stays fast.
 pub struct BackendBLE {
     peripheral: Peripheral,
     endpoints: Option<EndpointsBLE>,
+    poll_write_type: WriteType,
+    command_write_type: WriteType,
 }
 
-fn as_timeout_error<E>(_: E) -> Error {
-    Error::TimedOut
+fn request_timed_out(timeout: Duration) -> Error {
+    Error::TimedOut {
+        operation: Operation::Request,
+        timeout,
+    }
 }
 
 impl BackendBLE {
@@ -51,6 +106,36 @@ impl BackendBLE {
         BackendBLE {
             peripheral,
             endpoints: None,
+            poll_write_type: WriteType::WithoutResponse,
+            command_write_type: WriteType::WithoutResponse,
+        }
+    }
+
+    /// Returns the identity of the peripheral, which can be persisted to reconnect quickly
+    /// with [`reconnect_ble`](crate::reconnect_ble).
+    pub fn identity(&self) -> PeripheralIdentity {
+        PeripheralIdentity::of(&self.peripheral)
+    }
+
+    /// Sets the write type which is used for requests of the given category.
+    pub fn with_write_type(mut self, category: RequestCategory, write_type: WriteType) -> Self {
+        self.set_write_type(category, write_type);
+        self
+    }
+
+    /// Sets the write type which is used for requests of the given category.
+    pub fn set_write_type(&mut self, category: RequestCategory, write_type: WriteType) {
+        match category {
+            RequestCategory::Poll => self.poll_write_type = write_type,
+            RequestCategory::Command => self.command_write_type = write_type,
+        }
+    }
+
+    /// Returns the write type which is used for requests of the given category.
+    pub fn write_type(&self, category: RequestCategory) -> WriteType {
+        match category {
+            RequestCategory::Poll => self.poll_write_type,
+            RequestCategory::Command => self.command_write_type,
         }
     }
 
@@ -90,19 +175,20 @@ impl BackendBLE {
         }?;
 
         self.peripheral.subscribe(&notify_char).await?;
+        let notifications = self.peripheral.notifications().await?;
 
         self.endpoints = Some(EndpointsBLE {
             output_char,
             notify_char,
+            notifications: Mutex::new(notifications),
         });
 
         Ok(())
     }
 
     async fn disconnect_internal(&mut self) -> btleplug::Result<()> {
-        match &self.endpoints {
-            Some(endpoints) => self.peripheral.unsubscribe(&endpoints.notify_char).await?,
-            None => (),
+        if let Some(endpoints) = &self.endpoints {
+            self.peripheral.unsubscribe(&endpoints.notify_char).await?;
         }
 
         self.endpoints = None;
@@ -114,30 +200,45 @@ impl BackendBLE {
         Ok(())
     }
 
-    async fn request_internal(&mut self, data: &[u8]) -> btleplug::Result<Vec<u8>> {
-        match &self.endpoints {
-            None => Err(btleplug::Error::NotConnected),
-            Some(endpoints) => {
-                self.peripheral
-                    .write(&endpoints.output_char, data, WriteType::WithoutResponse)
-                    .await?;
-                let mut notify_stream = self.peripheral.notifications().await?.take(1);
-                match notify_stream.next().await {
-                    Some(in_data) => {
-                        let mut result = in_data.value;
-                        // BLE data is mostly tailored by a $ and they miss the command character
-                        // bring this data buffer into a common format
-                        if !result.is_empty() && *result.last().unwrap() == b'$' {
-                            result.truncate(result.len() - 1);
-                            result.splice(0..0, [*data.first().unwrap()]);
-                        }
-
-                        Ok(result)
-                    }
-                    None => Err(btleplug::Error::RuntimeError("no response".to_owned())),
-                }
+    async fn request_internal(&mut self, data: &[u8]) -> crate::Result<Frame> {
+        let write_type = self.write_type(RequestCategory::of(data));
+        let endpoints = self.endpoints.as_mut().ok_or(Error::NotConnected)?;
+
+        endpoints.discard_notifications();
+
+        self.peripheral
+            .write(&endpoints.output_char, data, write_type)
+            .await?;
+        match endpoints.notifications().next().await {
+            Some(in_data) => normalize_ble_response(data, &in_data.value),
+            None => Err(Error::RuntimeError("no response".to_owned())),
+        }
+    }
+
+    async fn request_batch_internal(&mut self, requests: &[&[u8]]) -> crate::Result<Vec<Frame>> {
+        let write_types: Vec<WriteType> = requests
+            .iter()
+            .map(|request| self.write_type(RequestCategory::of(request)))
+            .collect();
+        let endpoints = self.endpoints.as_mut().ok_or(Error::NotConnected)?;
+        endpoints.discard_notifications();
+
+        for (request, write_type) in requests.iter().zip(write_types) {
+            self.peripheral
+                .write(&endpoints.output_char, request, write_type)
+                .await?;
+        }
+
+        // the control unit answers the requests in order
+        let mut responses = Vec::with_capacity(requests.len());
+        for request in requests {
+            match endpoints.notifications().next().await {
+                Some(in_data) => responses.push(normalize_ble_response(request, &in_data.value)?),
+                None => return Err(Error::RuntimeError("no response".to_owned())),
             }
         }
+
+        Ok(responses)
     }
 }
 
@@ -145,8 +246,9 @@ impl BackendBLE {
 impl Backend for BackendBLE {
     /// Connects the backend with the configured peripheral.
     async fn connect(&mut self) -> crate::Result<()> {
-        self.connect_internal().await?;
-        Ok(())
+        self.connect_internal()
+            .await
+            .map_err(|error| Error::from(error).during(Operation::Connect))
     }
 
     /// Disconnects the backend from the configured peripheral.
@@ -155,64 +257,29 @@ impl Backend for BackendBLE {
         Ok(())
     }
 
-    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Vec<u8>> {
-        let ret = tokio::time::timeout(timeout.clone(), self.request_internal(data)).await;
-        Ok(ret.map_err(as_timeout_error)??)
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        let ret = tokio::time::timeout(timeout, self.request_internal(data)).await;
+        ret.map_err(|_| request_timed_out(timeout))?
+    }
+
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        let batch_timeout = batch_timeout(timeout, requests.len());
+        let ret = tokio::time::timeout(batch_timeout, self.request_batch_internal(requests)).await;
+        ret.map_err(|_| request_timed_out(timeout))?
     }
 
     async fn is_connected(&self) -> crate::Result<bool> {
         Ok(self.peripheral.is_connected().await? && self.endpoints.is_some())
     }
-}
 
-/// Searches for a control unit bluetooth device in the range of the given adapter and returns the first instance.
-/// Returns the found control unit if any was available, otherwise none on timeout or an error when any error occurs.
-pub async fn discover_first_ble(
-    adapter: &Adapter,
-    timeout: Duration,
-) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
-    Ok(discover_first_ble_internal(&adapter, timeout).await?)
-}
-
-async fn discover_first_ble_internal(
-    adapter: &Adapter,
-    timeout: Duration,
-) -> btleplug::Result<Option<ControlUnit<BackendBLE>>> {
-    adapter.start_scan(ScanFilter::default()).await?;
-
-    let ret = tokio::time::timeout(timeout, wait_for_control_unit(adapter))
-        .await
-        .map_err(|_| btleplug::Error::TimedOut(timeout));
-
-    adapter.stop_scan().await?;
-    ret?
-}
-
-async fn wait_for_control_unit(
-    adapter: &Adapter,
-) -> btleplug::Result<Option<ControlUnit<BackendBLE>>> {
-    let mut events = adapter.events().await?;
-    while let Some(event) = events.next().await {
-        match event {
-            CentralEvent::DeviceDiscovered(peripheral_id) => {
-                let peripheral = adapter.peripheral(&peripheral_id).await?;
-                if is_control_unit(&peripheral).await? {
-                    return Ok(Some(ControlUnit::new(BackendBLE::new(peripheral))));
-                }
-            }
-            _ => (),
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        if let Some(endpoints) = self.endpoints.as_mut() {
+            endpoints.discard_notifications();
         }
-    }
-
-    Ok(None)
-}
-
-async fn is_control_unit(peripheral: &Peripheral) -> btleplug::Result<bool> {
-    match peripheral.properties().await? {
-        Some(properties) => match properties.local_name {
-            Some(name) => Ok(name == "Control_Unit"),
-            None => Ok(false),
-        },
-        None => Ok(false),
+        Ok(())
     }
 }
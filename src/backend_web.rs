@@ -0,0 +1,197 @@
+//! Module which implements a Web Bluetooth backend for applications running in a browser.
+//!
+//! The Web Bluetooth bindings of `web-sys` are unstable, so the crate has to be built with
+//! `RUSTFLAGS=--cfg=web_sys_unstable_apis` for the `wasm32-unknown-unknown` target.
+
+use crate::backend::normalize_ble_response;
+use crate::{Backend, Error, Frame, Operation};
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::StreamExt;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    BluetoothDevice, BluetoothLeScanFilterInit, BluetoothRemoteGattCharacteristic,
+    BluetoothRemoteGattServer, BluetoothRemoteGattService, RequestDeviceOptions,
+};
+
+const SERVICE_UUID: &str = "39df7777-b1b4-b90b-57f1-7144ae4e4a6a";
+const NOTIFY_UUID: &str = "39df9999-b1b4-b90b-57f1-7144ae4e4a6a";
+const OUTPUT_UUID: &str = "39df8888-b1b4-b90b-57f1-7144ae4e4a6a";
+const DEVICE_NAME: &str = "Control_Unit";
+
+fn js_error(error: JsValue) -> Error {
+    Error::RuntimeError(format!("{:?}", error))
+}
+
+/// Waits for the given duration using the timer of the browser.
+async fn sleep(duration: Duration) {
+    let milliseconds = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, milliseconds);
+        }
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+struct EndpointsWeb {
+    output_char: BluetoothRemoteGattCharacteristic,
+    notify_char: BluetoothRemoteGattCharacteristic,
+    notifications: mpsc::UnboundedReceiver<Vec<u8>>,
+    // keeps the listener alive as long as the endpoints are in use
+    _listener: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl EndpointsWeb {
+    /// Drops the responses of requests which timed out earlier.
+    fn discard_notifications(&mut self) {
+        while self.notifications.try_recv().is_ok() {}
+    }
+}
+
+/// Backend which manages a Web Bluetooth connection with the control unit.
+pub struct WebBluetoothBackend {
+    device: BluetoothDevice,
+    endpoints: Option<EndpointsWeb>,
+}
+
+impl WebBluetoothBackend {
+    /// Creates the backend for an already selected device.
+    pub fn new(device: BluetoothDevice) -> WebBluetoothBackend {
+        WebBluetoothBackend {
+            device,
+            endpoints: None,
+        }
+    }
+
+    /// Asks the user to select a control unit in the device chooser of the browser.
+    /// Browsers only show the chooser in response to a user gesture, e.g. a click on a button.
+    pub async fn request_device() -> crate::Result<WebBluetoothBackend> {
+        let bluetooth = web_sys::window()
+            .and_then(|window| window.navigator().bluetooth())
+            .ok_or_else(|| Error::NotSupported("Web Bluetooth is not available".to_owned()))?;
+
+        let filter = BluetoothLeScanFilterInit::new();
+        filter.set_name(DEVICE_NAME);
+        let options = RequestDeviceOptions::new();
+        options.set_filters(&[filter]);
+        options.set_optional_services(&[js_sys::JsString::from(SERVICE_UUID)]);
+
+        let device = JsFuture::from(bluetooth.request_device(&options))
+            .await
+            .map_err(|_| Error::DeviceNotFound)?;
+        Ok(Self::new(device.unchecked_into()))
+    }
+
+    fn server(&self) -> crate::Result<BluetoothRemoteGattServer> {
+        self.device
+            .gatt()
+            .ok_or_else(|| Error::NotSupported("device has no GATT server".to_owned()))
+    }
+
+    async fn characteristic(
+        service: &BluetoothRemoteGattService,
+        uuid: &str,
+    ) -> crate::Result<BluetoothRemoteGattCharacteristic> {
+        let characteristic = JsFuture::from(service.get_characteristic_with_str(uuid))
+            .await
+            .map_err(js_error)?;
+        Ok(characteristic.unchecked_into())
+    }
+}
+
+#[async_trait(?Send)]
+impl Backend for WebBluetoothBackend {
+    /// Connects the backend with the selected device.
+    async fn connect(&mut self) -> crate::Result<()> {
+        let server: BluetoothRemoteGattServer = JsFuture::from(self.server()?.connect())
+            .await
+            .map_err(js_error)?
+            .unchecked_into();
+        let service: BluetoothRemoteGattService =
+            JsFuture::from(server.get_primary_service_with_str(SERVICE_UUID))
+                .await
+                .map_err(js_error)?
+                .unchecked_into();
+
+        let notify_char = Self::characteristic(&service, NOTIFY_UUID).await?;
+        let output_char = Self::characteristic(&service, OUTPUT_UUID).await?;
+
+        let (sender, notifications) = mpsc::unbounded();
+        let source = notify_char.clone();
+        let listener = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+            if let Some(value) = source.value() {
+                let value = js_sys::Uint8Array::new(&value.buffer());
+                let _ = sender.unbounded_send(value.to_vec());
+            }
+        });
+        notify_char
+            .add_event_listener_with_callback(
+                "characteristicvaluechanged",
+                listener.as_ref().unchecked_ref(),
+            )
+            .map_err(js_error)?;
+        JsFuture::from(notify_char.start_notifications())
+            .await
+            .map_err(js_error)?;
+
+        self.endpoints = Some(EndpointsWeb {
+            output_char,
+            notify_char,
+            notifications,
+            _listener: listener,
+        });
+        Ok(())
+    }
+
+    /// Disconnects the backend from the selected device.
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        if let Some(endpoints) = self.endpoints.take() {
+            let _ = JsFuture::from(endpoints.notify_char.stop_notifications()).await;
+        }
+        self.server()?.disconnect();
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        Ok(self.server()?.connected() && self.endpoints.is_some())
+    }
+
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        let endpoints = self.endpoints.as_mut().ok_or(Error::NotConnected)?;
+
+        endpoints.discard_notifications();
+
+        let request = js_sys::Uint8Array::from(data);
+        JsFuture::from(
+            endpoints
+                .output_char
+                .write_value_without_response_with_u8_array(&request)
+                .map_err(js_error)?,
+        )
+        .await
+        .map_err(js_error)?;
+
+        let response = endpoints.notifications.next();
+        match future::select(response, Box::pin(sleep(timeout))).await {
+            Either::Left((Some(response), _)) => normalize_ble_response(data, &response),
+            Either::Left((None, _)) => Err(Error::NoResponse),
+            Either::Right(_) => Err(Error::TimedOut {
+                operation: Operation::Request,
+                timeout,
+            }),
+        }
+    }
+
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        if let Some(endpoints) = self.endpoints.as_mut() {
+            endpoints.discard_notifications();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+//! Terminal dashboard which shows the live timing of a race.
+//!
+//! Usage: `carrlink-dashboard [--remote <host:port> | --simulated <cars>]`, the control unit is
+//! searched via bluetooth by default. Press `s` to start a race, `r` to reset the session and `q`
+//! to quit.
+
+use carrlink::{
+    AdaptivePollRate, Backend, BleDiscovery, ConnectionGuard, ControlUnit, ControllerId,
+    RaceSession, RemoteBackend, SimulatedBackend, StartDetector, StartEvent, StartSignal, Status,
+    TrackStatus,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: carrlink-dashboard [--remote <host:port> | --simulated <cars>]";
+
+/// Interval in which the dashboard handles key presses and redraws.
+const TICK: Duration = Duration::from_millis(50);
+
+/// Number of lights of the start light gantry.
+const START_LIGHT_COUNT: u8 = 5;
+
+enum Connection {
+    Bluetooth,
+    Remote(String),
+    Simulated(usize),
+}
+
+fn parse_options() -> Result<Connection, String> {
+    let mut connection = Connection::Bluetooth;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--remote" => connection = Connection::Remote(value()?),
+            "--simulated" => {
+                let car_count = value()?
+                    .parse()
+                    .map_err(|_| "invalid car count".to_owned())?;
+                connection = Connection::Simulated(car_count);
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(connection)
+}
+
+/// State of the race which is shown on the dashboard.
+#[derive(Default)]
+struct Dashboard {
+    session: RaceSession,
+    start_detector: StartDetector,
+    track: TrackStatus,
+    message: String,
+}
+
+impl Dashboard {
+    fn update_status(&mut self, status: &Status) {
+        if let Status::Track(track) = status {
+            self.track = *track;
+        }
+        if let Some(StartEvent::RaceStarted) = self.start_detector.update_status(status) {
+            self.message = "race started".to_owned();
+        }
+        if let Some(lap) = self.session.update(status) {
+            self.message = format!(
+                "controller {} completed lap {} in {}",
+                lap.controller.index() + 1,
+                lap.number,
+                format_duration(Some(lap.lap_time))
+            );
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [lights, body, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [standings, fuel] =
+            Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+                .areas(body);
+
+        self.draw_start_lights(frame, lights);
+        self.draw_standings(frame, standings);
+        self.draw_fuel(frame, fuel);
+        frame.render_widget(
+            Paragraph::new(format!(
+                "s start  r reset  q quit  {}",
+                self.message.as_str()
+            ))
+            .dark_gray(),
+            footer,
+        );
+    }
+
+    fn draw_start_lights(&self, frame: &mut Frame, area: Rect) {
+        let signal = self.track.start_signal;
+        let lit = match signal.seconds_remaining() {
+            Some(seconds) if seconds > 0 => START_LIGHT_COUNT + 1 - seconds,
+            _ => 0,
+        };
+        let mut spans: Vec<Span> = (0..START_LIGHT_COUNT)
+            .map(|light| match light < lit {
+                true => Span::styled(" ● ", Style::new().fg(Color::Red)),
+                false => Span::styled(" ○ ", Style::new().fg(Color::DarkGray)),
+            })
+            .collect();
+        if signal == StartSignal::Go {
+            spans.push(Span::styled("  GO", Style::new().fg(Color::Green).bold()));
+        }
+
+        frame.render_widget(
+            Paragraph::new(Line::from(spans)).block(Block::bordered().title(" carrlink ")),
+            area,
+        );
+    }
+
+    fn draw_standings(&self, frame: &mut Frame, area: Rect) {
+        let standings = self.session.standings();
+        let leader = standings.first().copied();
+        let rows = standings.iter().map(|standing| {
+            let gap = match leader {
+                Some(leader) if leader.laps > standing.laps => {
+                    format!("+{} laps", leader.laps - standing.laps)
+                }
+                Some(leader) if standing.position > 1 => format!(
+                    "+{}",
+                    format_duration(Some(standing.total_time.saturating_sub(leader.total_time)))
+                ),
+                _ => String::new(),
+            };
+            Row::new([
+                standing.position.to_string(),
+                (standing.controller.index() + 1).to_string(),
+                standing.laps.to_string(),
+                format_duration(standing.last_lap),
+                format_duration(standing.best_lap),
+                gap,
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(4),
+                Constraint::Length(6),
+                Constraint::Length(5),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ],
+        )
+        .header(Row::new(["Pos", "Car", "Laps", "Last", "Best", "Gap"]).bold())
+        .block(Block::bordered().title(" Standings "));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_fuel(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Fuel ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let controllers: Vec<ControllerId> = self.track.active_controllers().collect();
+        let rows = Layout::vertical(controllers.iter().map(|_| Constraint::Length(1))).split(inner);
+        for (controller, row) in controllers.iter().zip(rows.iter()) {
+            let level = self.track.fuel_level(*controller);
+            let color = match self.track.is_refueling[controller.index()] {
+                true => Color::Blue,
+                false if level.as_percent() < 25.0 => Color::Red,
+                false => Color::Green,
+            };
+            let gauge = Gauge::default()
+                .label(format!(
+                    "Car {} {:>2}/15",
+                    controller.index() + 1,
+                    level.value()
+                ))
+                .ratio(f64::from(level.as_percent()) / 100.0)
+                .gauge_style(Style::new().fg(color));
+            frame.render_widget(gauge, *row);
+        }
+    }
+}
+
+/// Formats a lap time as minutes, seconds and milliseconds.
+fn format_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!(
+            "{}:{:02}.{:03}",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60,
+            duration.subsec_millis()
+        ),
+        None => "-".to_owned(),
+    }
+}
+
+/// Polls the control unit and redraws the dashboard until the user quits.
+async fn run<T: Backend>(
+    terminal: &mut DefaultTerminal,
+    control_unit: &mut ControlUnit<T>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dashboard = Dashboard::default();
+    let mut poll_rate = AdaptivePollRate::new();
+    let mut next_poll = Instant::now();
+
+    loop {
+        let now = Instant::now();
+        if now >= next_poll {
+            match control_unit.get_status().await {
+                Ok(status) => {
+                    dashboard.update_status(&status);
+                    next_poll = now + poll_rate.update_status(&status, now);
+                }
+                Err(error) => {
+                    dashboard.message = format!("error: {}", error);
+                    next_poll = now + poll_rate.interval();
+                }
+            }
+        }
+
+        terminal.draw(|frame| dashboard.draw(frame))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('s') => {
+                        if let Err(error) = control_unit.start().await {
+                            dashboard.message = format!("error: {}", error);
+                        }
+                    }
+                    KeyCode::Char('r') => {
+                        dashboard.session.reset();
+                        dashboard.start_detector.reset();
+                        dashboard.message = "session reset".to_owned();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Connects to the control unit and shows the dashboard in the terminal.
+async fn show<T: Backend + 'static>(
+    control_unit: ControlUnit<T>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut control_unit = ConnectionGuard::new(control_unit);
+    control_unit.connect().await?;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut control_unit).await;
+    ratatui::restore();
+
+    let closed = control_unit.close().await;
+    result?;
+    Ok(closed?)
+}
+
+async fn execute(connection: Connection) -> Result<(), Box<dyn std::error::Error>> {
+    match connection {
+        Connection::Bluetooth => {
+            let adapter = carrlink::first_adapter().await?;
+            let control_unit = BleDiscovery::new(&adapter)
+                .with_timeout(Duration::from_secs(10))
+                .first()
+                .await?
+                .ok_or(carrlink::Error::DeviceNotFound)?;
+            show(control_unit).await
+        }
+        Connection::Remote(address) => show(ControlUnit::new(RemoteBackend::new(&address))).await,
+        Connection::Simulated(car_count) => {
+            show(ControlUnit::new(SimulatedBackend::new(car_count))).await
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let connection = match parse_options() {
+        Ok(connection) => connection,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match execute(connection).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Simulator of a control unit which is served over TCP with the protocol of the `RemoteBackend`.
+//!
+//! The simulator does not speak the wire protocol of a real control unit. Every request and
+//! response is wrapped in the typed, length prefixed frames of `serve_backend`, so only a
+//! `RemoteBackend` can talk to it. Clients which implement the serial or Bluetooth framing of the
+//! control unit themselves, e.g. applications which do not use carrlink, cannot connect. The BLE
+//! and Web Bluetooth transports are not exercised either, only the request handling and the
+//! decoders of `ControlUnit` above the backend.
+//!
+//! Usage: `carrlink-simulator [--address <host:port>] [--cars <count>] [--lap-time <ms>] [--seed <seed>]`
+
+use carrlink::{serve_backend, SimulatedBackend};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:6800";
+const DEFAULT_CAR_COUNT: usize = 2;
+
+struct Options {
+    address: String,
+    car_count: usize,
+    lap_time: Option<Duration>,
+    seed: Option<u64>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        address: DEFAULT_ADDRESS.to_owned(),
+        car_count: DEFAULT_CAR_COUNT,
+        lap_time: None,
+        seed: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--address" => options.address = value()?,
+            "--cars" => {
+                options.car_count = value()?
+                    .parse()
+                    .map_err(|_| "invalid car count".to_owned())?
+            }
+            "--lap-time" => {
+                let milliseconds = value()?
+                    .parse()
+                    .map_err(|_| "invalid lap time".to_owned())?;
+                options.lap_time = Some(Duration::from_millis(milliseconds));
+            }
+            "--seed" => {
+                options.seed = Some(value()?.parse().map_err(|_| "invalid seed".to_owned())?)
+            }
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("usage: carrlink-simulator [--address <host:port>] [--cars <count>] [--lap-time <ms>] [--seed <seed>]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut backend = SimulatedBackend::new(options.car_count).connected();
+    if let Some(lap_time) = options.lap_time {
+        backend = backend.with_base_lap_time(lap_time);
+    }
+    if let Some(seed) = options.seed {
+        backend = backend.with_seed(seed);
+    }
+
+    let listener = match TcpListener::bind(&options.address).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("failed to listen on {}: {}", options.address, error);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "simulating {} cars on {}",
+        options.car_count, options.address
+    );
+
+    match serve_backend(Arc::new(Mutex::new(backend)), listener).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("failed to serve the simulator: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! Command line tool which controls a control unit via bluetooth, a remote backend or the simulator.
+//!
+//! Usage: `carrlink [--remote <host:port> | --simulated] [--timeout <ms>] <command>`, run it
+//! without arguments for the list of commands.
+
+use carrlink::conformance::{describe, Decoded};
+use carrlink::{
+    AdaptivePollRate, Backend, BleDiscovery, BrakeLevel, ConnectionGuard, ControlUnit,
+    ControllerId, DiscoveryEvent, FuelLevel, RaceSession, RemoteBackend, SessionReplayer,
+    SimulatedBackend, SpeedLevel, StartDetector, Status, TelemetryEvent, TelemetryLogger,
+};
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str =
+    "usage: carrlink [--remote <host:port> | --simulated] [--timeout <ms>] <command>
+
+commands:
+  scan                                  search for control units via bluetooth
+  status                                print the current status
+  watch                                 print the statuses until interrupted
+  press <button>                        press enter, esc, speed, brake, fuel or code
+  set <speed|brake|fuel> <controller> <level>
+                                        configure the car of a controller
+  start-race                            start the countdown of a race
+  record <file>                         log the statuses as telemetry until interrupted
+  replay <file> [--speed <factor>]      replay a recorded telemetry log";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+/// Channel to the control unit which is used by the commands.
+enum Connection {
+    Bluetooth,
+    Remote(String),
+    Simulated,
+}
+
+/// Setting of a car which is configured by the `set` command.
+enum Setting {
+    Speed,
+    Brake,
+    Fuel,
+}
+
+enum Command {
+    Scan,
+    Status,
+    Watch,
+    Press(String),
+    Set {
+        setting: Setting,
+        controller: usize,
+        level: u8,
+    },
+    StartRace,
+    Record(PathBuf),
+    Replay {
+        path: PathBuf,
+        speed: f64,
+    },
+}
+
+struct Options {
+    connection: Connection,
+    timeout: Duration,
+    command: Command,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut connection = Connection::Bluetooth;
+    let mut timeout = DEFAULT_TIMEOUT;
+
+    let mut args = std::env::args().skip(1);
+    let command = loop {
+        let arg = args.next().ok_or("missing command")?;
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--remote" => connection = Connection::Remote(value()?),
+            "--simulated" => connection = Connection::Simulated,
+            "--timeout" => {
+                let milliseconds = value()?.parse().map_err(|_| "invalid timeout".to_owned())?;
+                timeout = Duration::from_millis(milliseconds);
+            }
+            _ => break arg,
+        }
+    };
+
+    let rest: Vec<String> = args.collect();
+    let arg = |index: usize, name: &str| {
+        rest.get(index)
+            .cloned()
+            .ok_or(format!("missing {} for {}", name, command))
+    };
+    let command = match command.as_str() {
+        "scan" => Command::Scan,
+        "status" => Command::Status,
+        "watch" => Command::Watch,
+        "press" => Command::Press(arg(0, "button")?),
+        "set" => Command::Set {
+            setting: match arg(0, "setting")?.as_str() {
+                "speed" => Setting::Speed,
+                "brake" => Setting::Brake,
+                "fuel" => Setting::Fuel,
+                setting => return Err(format!("unknown setting {}", setting)),
+            },
+            controller: arg(1, "controller")?
+                .parse()
+                .map_err(|_| "invalid controller".to_owned())?,
+            level: arg(2, "level")?
+                .parse()
+                .map_err(|_| "invalid level".to_owned())?,
+        },
+        "start-race" => Command::StartRace,
+        "record" => Command::Record(arg(0, "file")?.into()),
+        "replay" => Command::Replay {
+            path: arg(0, "file")?.into(),
+            speed: match rest.get(1).map(String::as_str) {
+                Some("--speed") => arg(2, "speed")?
+                    .parse()
+                    .map_err(|_| "invalid speed".to_owned())?,
+                Some(arg) => return Err(format!("unknown argument {}", arg)),
+                None => 1.0,
+            },
+        },
+        command => return Err(format!("unknown command {}", command)),
+    };
+
+    Ok(Options {
+        connection,
+        timeout,
+        command,
+    })
+}
+
+/// Prints the peripherals in range of the first bluetooth adapter and the first control unit.
+async fn scan(timeout: Duration) -> CliResult {
+    let adapter = carrlink::first_adapter().await?;
+    let control_unit = BleDiscovery::new(&adapter)
+        .with_timeout(timeout)
+        .on_event(|event| match event {
+            DiscoveryEvent::ScanStarted { adapter } => println!("scanning on {}", adapter),
+            DiscoveryEvent::PeripheralSeen {
+                peripheral,
+                name,
+                rssi,
+            } => println!(
+                "seen {} name={} rssi={}",
+                peripheral,
+                name.unwrap_or_default(),
+                rssi.map(|rssi| rssi.to_string()).unwrap_or_default()
+            ),
+            DiscoveryEvent::ControlUnitFound { peripheral } => {
+                println!("found control unit {}", peripheral)
+            }
+            _ => {}
+        })
+        .first()
+        .await?;
+
+    if control_unit.is_none() {
+        println!("no control unit found");
+    }
+    Ok(())
+}
+
+/// Presses the button with the given name.
+async fn press<T: Backend>(
+    control_unit: &mut ControlUnit<T>,
+    button: &str,
+) -> carrlink::Result<()> {
+    match button {
+        "enter" => control_unit.press_enter().await,
+        "esc" => control_unit.press_esc().await,
+        "speed" => control_unit.press_speed().await,
+        "brake" => control_unit.press_brake().await,
+        "fuel" => control_unit.press_fuel().await,
+        "code" => control_unit.press_code().await,
+        button => Err(carrlink::Error::InvalidArgument(format!(
+            "unknown button {}",
+            button
+        ))),
+    }
+}
+
+/// Configures the given setting of the car of a controller.
+async fn configure<T: Backend>(
+    control_unit: &mut ControlUnit<T>,
+    setting: Setting,
+    controller: usize,
+    level: u8,
+) -> carrlink::Result<()> {
+    let controller = ControllerId::try_from(controller)?;
+    match setting {
+        Setting::Speed => {
+            let level = SpeedLevel::try_from(level)?;
+            control_unit.set_speed_level(controller, level).await
+        }
+        Setting::Brake => {
+            let level = BrakeLevel::try_from(level)?;
+            control_unit.set_brake_level(controller, level).await
+        }
+        Setting::Fuel => {
+            let level = FuelLevel::try_from(level)?;
+            control_unit.set_fuel_level(controller, level).await
+        }
+    }
+}
+
+/// Polls statuses until interrupted and passes every status to the callback.
+async fn poll<T: Backend, F: FnMut(&Status) -> CliResult>(
+    control_unit: &mut ControlUnit<T>,
+    mut on_status: F,
+) -> CliResult {
+    let mut poll_rate = AdaptivePollRate::new();
+    loop {
+        let status = control_unit.get_status().await?;
+        on_status(&status)?;
+        let interval = poll_rate.update_status(&status, Instant::now());
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Prints lap statuses and track statuses which differ from the previous one.
+async fn watch<T: Backend>(control_unit: &mut ControlUnit<T>) -> CliResult {
+    let mut previous_track = String::new();
+    poll(control_unit, |status| {
+        let description = describe(&Ok(Decoded::Status(*status)));
+        match status {
+            Status::Track(_) if description == previous_track => {}
+            Status::Track(_) => {
+                println!("{}", description);
+                previous_track = description;
+            }
+            Status::Lap(_) => println!("{}", description),
+            _ => {}
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Logs every status and the derived laps and start events until interrupted.
+async fn record<T: Backend>(control_unit: &mut ControlUnit<T>, path: PathBuf) -> CliResult {
+    let mut logger = TelemetryLogger::create(&path)?;
+    let mut session = RaceSession::new();
+    let mut start_detector = StartDetector::new();
+    println!("recording to {}, press ctrl-c to stop", path.display());
+
+    let result = poll(control_unit, |status| {
+        if matches!(status, Status::NoData) {
+            return Ok(());
+        }
+        logger.log_status(status)?;
+        if let Some(event) = start_detector.update_status(status) {
+            logger.log_start(event)?;
+        }
+        if let Some(lap) = session.update(status) {
+            logger.log_lap(&lap)?;
+            println!(
+                "lap {} of controller {}: {:?}",
+                lap.number,
+                lap.controller.index(),
+                lap.lap_time
+            );
+        }
+        Ok(())
+    })
+    .await;
+
+    logger.flush()?;
+    result
+}
+
+/// Replays a telemetry log into a new session and prints the statuses and derived laps.
+async fn replay(path: PathBuf, speed: f64) -> CliResult {
+    let replayer = SessionReplayer::open(path)?.with_speed(speed);
+    let mut session = RaceSession::new();
+    replayer
+        .replay(&mut session, |event| match event {
+            TelemetryEvent::Status(status @ Status::Lap(_)) => {
+                println!("{}", describe(&Ok(Decoded::Status(*status))))
+            }
+            TelemetryEvent::Lap(lap) => println!(
+                "lap {} of controller {}: {:?}",
+                lap.number,
+                lap.controller.index(),
+                lap.lap_time
+            ),
+            TelemetryEvent::Start(event) => println!("{:?}", event),
+            _ => {}
+        })
+        .await;
+
+    for standing in session.standings() {
+        println!("{:?}", standing);
+    }
+    Ok(())
+}
+
+/// Runs a command which communicates with the control unit.
+async fn run<T: Backend + 'static>(control_unit: ControlUnit<T>, command: Command) -> CliResult {
+    let mut control_unit = ConnectionGuard::new(control_unit);
+    control_unit.connect().await?;
+
+    let result = match command {
+        Command::Status => control_unit
+            .get_status()
+            .await
+            .map(|status| println!("{}", describe(&Ok(Decoded::Status(status)))))
+            .map_err(Into::into),
+        Command::Watch => watch(&mut control_unit).await,
+        Command::Press(button) => press(&mut control_unit, &button).await.map_err(Into::into),
+        Command::Set {
+            setting,
+            controller,
+            level,
+        } => configure(&mut control_unit, setting, controller, level)
+            .await
+            .map_err(Into::into),
+        Command::StartRace => control_unit.start().await.map_err(Into::into),
+        Command::Record(path) => record(&mut control_unit, path).await,
+        Command::Scan | Command::Replay { .. } => unreachable!("command needs no control unit"),
+    };
+
+    let closed = control_unit.close().await;
+    result?;
+    Ok(closed?)
+}
+
+async fn execute(options: Options) -> CliResult {
+    match (options.command, options.connection) {
+        (Command::Replay { path, speed }, _) => replay(path, speed).await,
+        (Command::Scan, Connection::Bluetooth) => scan(options.timeout).await,
+        (Command::Scan, _) => Err("scan is only supported via bluetooth".into()),
+        (command, Connection::Bluetooth) => {
+            let adapter = carrlink::first_adapter().await?;
+            let control_unit = BleDiscovery::new(&adapter)
+                .with_timeout(options.timeout)
+                .first()
+                .await?
+                .ok_or(carrlink::Error::DeviceNotFound)?;
+            run(control_unit, command).await
+        }
+        (command, Connection::Remote(address)) => {
+            let mut control_unit = ControlUnit::new(RemoteBackend::new(&address));
+            control_unit.set_timeout(options.timeout);
+            run(control_unit, command).await
+        }
+        (command, Connection::Simulated) => {
+            run(ControlUnit::new(SimulatedBackend::new(2)), command).await
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match execute(options).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            let mut source = error.source();
+            while let Some(cause) = source {
+                eprintln!("caused by: {}", cause);
+                source = cause.source();
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,605 @@
+//! Module which implements the discovery of control units via bluetooth low energy.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::backend_ble::SERVICE_UUID;
+use crate::{BackendBLE, ControlUnit, Error, Operation};
+use btleplug::api::{
+    BDAddr, Central as _, CentralEvent, Manager as _, Peripheral as _, PeripheralProperties,
+    ScanFilter,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures::future::{self, Either};
+use futures::stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+/// Local name which is advertised by control units.
+const CONTROL_UNIT_NAME: &str = "Control_Unit";
+
+/// Identity of the peripheral of a control unit, which can be persisted to find it again quickly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeripheralIdentity {
+    /// Platform specific identifier of the peripheral.
+    pub id: String,
+
+    /// Bluetooth address of the peripheral, some platforms like macOS do not reveal it.
+    pub address: String,
+}
+
+impl PeripheralIdentity {
+    /// Determines the identity of the given peripheral.
+    pub fn of(peripheral: &Peripheral) -> PeripheralIdentity {
+        PeripheralIdentity {
+            id: peripheral.id().to_string(),
+            address: peripheral.address().to_string(),
+        }
+    }
+
+    /// Determines if the given peripheral has this identity.
+    fn matches(&self, peripheral: &Peripheral) -> bool {
+        let address = peripheral.address();
+        peripheral.id().to_string() == self.id
+            || (address != BDAddr::default() && address.to_string() == self.address)
+    }
+}
+
+/// Reason why a peripheral seen during the discovery is not considered a control unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RejectionReason {
+    /// The peripheral did not report any properties.
+    NoProperties,
+
+    /// The peripheral neither advertises matching manufacturer data, nor the service of a
+    /// control unit, nor a local name.
+    NoName,
+
+    /// The peripheral neither advertises matching manufacturer data, nor the service of a
+    /// control unit, and its local name is not the one of a control unit.
+    NameMismatch,
+}
+
+/// Manufacturer specific advertisement data which identifies a control unit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ManufacturerData {
+    /// Company identifier which is assigned by the Bluetooth SIG.
+    pub company_id: u16,
+
+    /// Bytes the advertised data has to start with, empty to match any data of the company.
+    pub prefix: Vec<u8>,
+}
+
+impl ManufacturerData {
+    fn matches(&self, properties: &PeripheralProperties) -> bool {
+        properties
+            .manufacturer_data
+            .get(&self.company_id)
+            .is_some_and(|data| data.starts_with(&self.prefix))
+    }
+}
+
+/// Progress of the discovery which is passed to the callback registered with [`BleDiscovery::on_event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryEvent {
+    /// The scan was started on the adapter with the given description.
+    ScanStarted {
+        /// Description of the adapter as reported by the platform.
+        adapter: String,
+    },
+
+    /// A peripheral was seen for the first time or reported updated properties.
+    PeripheralSeen {
+        /// Platform specific identifier of the peripheral.
+        peripheral: String,
+
+        /// Local name of the peripheral, if it advertises one.
+        name: Option<String>,
+
+        /// Signal strength of the peripheral in dBm, if known.
+        rssi: Option<i16>,
+    },
+
+    /// A newly seen peripheral is not considered a control unit.
+    CandidateRejected {
+        /// Platform specific identifier of the peripheral.
+        peripheral: String,
+
+        /// Reason why the peripheral was rejected.
+        reason: RejectionReason,
+    },
+
+    /// A control unit was found, which ends the discovery.
+    ControlUnitFound {
+        /// Platform specific identifier of the peripheral.
+        peripheral: String,
+    },
+}
+
+/// Determines when the discovery gives up if no control unit was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ScanPolicy {
+    /// Scan for a fixed window, the timeout of the discovery.
+    #[default]
+    FixedWindow,
+
+    /// Scan until a control unit was found or the discovery was cancelled, ignoring the timeout.
+    UntilFound,
+}
+
+/// Duty cycle of the scan, which scans for a window at the beginning of every interval.
+///
+/// The platforms do not expose the scan parameters of the radio, so the scan is paused and
+/// resumed in software. Longer pauses save power on battery powered hosts at the cost of finding
+/// control units later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScanDutyCycle {
+    /// Duration of scanning in every interval.
+    pub window: Duration,
+
+    /// Duration of a complete cycle of scanning and pausing.
+    pub interval: Duration,
+}
+
+type EventCallback<'a> = Box<dyn FnMut(DiscoveryEvent) + Send + 'a>;
+
+/// Discovery of control units in the range of a bluetooth adapter.
+///
+/// ```no_run
+/// # async fn example(adapter: btleplug::platform::Adapter) -> carrlink::Result<()> {
+/// use carrlink::{BleDiscovery, DiscoveryEvent};
+/// use std::time::Duration;
+///
+/// let control_unit = BleDiscovery::new(&adapter)
+///     .with_timeout(Duration::from_secs(10))
+///     .on_event(|event| println!("{:?}", event))
+///     .first()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BleDiscovery<'a> {
+    adapter: &'a Adapter,
+    timeout: Duration,
+    cancellation: CancellationToken,
+    identity: Option<PeripheralIdentity>,
+    manufacturer_data: Vec<ManufacturerData>,
+    scan_policy: ScanPolicy,
+    duty_cycle: Option<ScanDutyCycle>,
+    on_event: Option<EventCallback<'a>>,
+}
+
+impl<'a> BleDiscovery<'a> {
+    /// Creates a discovery on the given adapter with a timeout of five seconds.
+    pub fn new(adapter: &'a Adapter) -> BleDiscovery<'a> {
+        BleDiscovery {
+            adapter,
+            timeout: Duration::from_secs(5),
+            cancellation: CancellationToken::new(),
+            identity: None,
+            manufacturer_data: Vec::new(),
+            scan_policy: ScanPolicy::FixedWindow,
+            duty_cycle: None,
+            on_event: None,
+        }
+    }
+
+    /// Sets the duration after which the discovery gives up, if it scans for a fixed window.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets when the discovery gives up, the default is a fixed window of the timeout.
+    pub fn with_scan_policy(mut self, scan_policy: ScanPolicy) -> Self {
+        self.scan_policy = scan_policy;
+        self
+    }
+
+    /// Sets the duty cycle of the scan, by default the adapter scans continuously.
+    /// Duty cycles whose window is not shorter than their interval scan continuously as well.
+    pub fn with_duty_cycle(mut self, duty_cycle: ScanDutyCycle) -> Self {
+        self.duty_cycle = Some(duty_cycle);
+        self
+    }
+
+    /// Sets the token which stops the discovery as soon as it is cancelled.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Sets the identity of a previously connected control unit, which is looked up among the
+    /// peripherals already known to the adapter before scanning.
+    pub fn with_identity(mut self, identity: PeripheralIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Adds manufacturer data which identifies a control unit.
+    ///
+    /// Peripherals are matched by any of the added manufacturer data first, then by the advertised
+    /// service of the control unit and finally by their local name, so control units are found
+    /// even if they were renamed.
+    pub fn with_manufacturer_data(mut self, manufacturer_data: ManufacturerData) -> Self {
+        self.manufacturer_data.push(manufacturer_data);
+        self
+    }
+
+    /// Registers a callback which is invoked with the progress of the discovery,
+    /// e.g. to show a live list of the peripherals in range.
+    pub fn on_event<F: FnMut(DiscoveryEvent) + Send + 'a>(mut self, on_event: F) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    fn emit(&mut self, event: DiscoveryEvent) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(event);
+        }
+    }
+
+    /// Searches for the first control unit.
+    /// Returns none if the discovery was cancelled and fails if no control unit was found within the timeout.
+    pub async fn first(mut self) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+        self.first_internal()
+            .await
+            .map_err(|error| Error::from(error).during(Operation::Discover))
+    }
+
+    async fn first_internal(&mut self) -> btleplug::Result<Option<ControlUnit<BackendBLE>>> {
+        if self.cancellation.is_cancelled() {
+            return Ok(None);
+        }
+
+        let adapter = self.adapter;
+        if let Some(identity) = &self.identity {
+            if let Some(peripheral) = find_known_peripheral(adapter, identity).await? {
+                self.emit(DiscoveryEvent::ControlUnitFound {
+                    peripheral: identity.id.clone(),
+                });
+                return Ok(Some(ControlUnit::new(BackendBLE::new(peripheral))));
+            }
+        }
+
+        adapter.start_scan(ScanFilter::default()).await?;
+        let description = adapter.adapter_info().await.unwrap_or_default();
+        self.emit(DiscoveryEvent::ScanStarted {
+            adapter: description,
+        });
+
+        let cancellation = self.cancellation.clone();
+        let is_scanning = AtomicBool::new(true);
+        let duty_cycle = drive_duty_cycle(adapter, self.duty_cycle, &is_scanning);
+        let timeout = match self.scan_policy {
+            ScanPolicy::FixedWindow => Some(self.timeout),
+            ScanPolicy::UntilFound => None,
+        };
+        let search = future::select(
+            Box::pin(self.wait_for_control_unit()),
+            future::select(Box::pin(duty_cycle), Box::pin(cancellation.cancelled())),
+        );
+        let outcome = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, search)
+                .await
+                .map_err(|_| btleplug::Error::TimedOut(timeout)),
+            None => Ok(search.await),
+        };
+        let ret = match outcome {
+            Ok(Either::Left((found, _))) => found,
+            Ok(Either::Right((Either::Left((result, _)), _))) => result.map(|_| None),
+            Ok(Either::Right((Either::Right(_), _))) => Ok(None),
+            Err(error) => Err(error),
+        };
+
+        if is_scanning.load(Ordering::Relaxed) {
+            adapter.stop_scan().await?;
+        }
+        ret
+    }
+
+    /// Looks for a control unit among the peripherals the adapter already knows, e.g. because
+    /// they were paired or seen before, without scanning.
+    /// Returns none if the adapter knows no control unit.
+    pub async fn first_known(mut self) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+        let candidates = self
+            .known_candidates()
+            .await
+            .map_err(|error| Error::from(error).during(Operation::Discover))?;
+        Ok(candidates
+            .into_iter()
+            .next()
+            .map(|peripheral| ControlUnit::new(BackendBLE::new(peripheral))))
+    }
+
+    /// Returns the known peripherals of the adapter which are considered control units.
+    async fn known_candidates(&mut self) -> btleplug::Result<Vec<Peripheral>> {
+        let mut candidates = Vec::new();
+        for peripheral in self.adapter.peripherals().await? {
+            let peripheral_id = peripheral.id().to_string();
+            match self.check_candidate(&peripheral).await? {
+                Ok(()) => {
+                    self.emit(DiscoveryEvent::ControlUnitFound {
+                        peripheral: peripheral_id,
+                    });
+                    candidates.push(peripheral);
+                }
+                Err(reason) => self.emit(DiscoveryEvent::CandidateRejected {
+                    peripheral: peripheral_id,
+                    reason,
+                }),
+            }
+        }
+        Ok(candidates)
+    }
+
+    async fn wait_for_control_unit(&mut self) -> btleplug::Result<Option<ControlUnit<BackendBLE>>> {
+        let mut events = self.adapter.events().await?;
+        while let Some(event) = events.next().await {
+            let (peripheral_id, is_new) = match event {
+                CentralEvent::DeviceDiscovered(peripheral_id) => (peripheral_id, true),
+                CentralEvent::DeviceUpdated(peripheral_id) => (peripheral_id, false),
+                _ => continue,
+            };
+
+            let peripheral = self.adapter.peripheral(&peripheral_id).await?;
+            match self.check_candidate(&peripheral).await? {
+                Ok(()) => {
+                    self.emit(DiscoveryEvent::ControlUnitFound {
+                        peripheral: peripheral_id.to_string(),
+                    });
+                    return Ok(Some(ControlUnit::new(BackendBLE::new(peripheral))));
+                }
+                // the name is often only advertised in a later scan response,
+                // so rejections are reported once but updates are checked again
+                Err(reason) if is_new => self.emit(DiscoveryEvent::CandidateRejected {
+                    peripheral: peripheral_id.to_string(),
+                    reason,
+                }),
+                Err(_) => (),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Reports the peripheral and determines if it is a control unit.
+    async fn check_candidate(
+        &mut self,
+        peripheral: &Peripheral,
+    ) -> btleplug::Result<Result<(), RejectionReason>> {
+        let Some(properties) = peripheral.properties().await? else {
+            return Ok(Err(RejectionReason::NoProperties));
+        };
+
+        self.emit(DiscoveryEvent::PeripheralSeen {
+            peripheral: peripheral.id().to_string(),
+            name: properties.local_name.clone(),
+            rssi: properties.rssi,
+        });
+
+        let is_manufacturer_match = self
+            .manufacturer_data
+            .iter()
+            .any(|manufacturer_data| manufacturer_data.matches(&properties));
+        if is_manufacturer_match || properties.services.contains(&SERVICE_UUID) {
+            return Ok(Ok(()));
+        }
+
+        Ok(match properties.local_name {
+            Some(name) if name == CONTROL_UNIT_NAME => Ok(()),
+            Some(_) => Err(RejectionReason::NameMismatch),
+            None => Err(RejectionReason::NoName),
+        })
+    }
+}
+
+/// Pauses and resumes the running scan according to the duty cycle, never returns unless scanning fails.
+async fn drive_duty_cycle(
+    adapter: &Adapter,
+    duty_cycle: Option<ScanDutyCycle>,
+    is_scanning: &AtomicBool,
+) -> btleplug::Result<()> {
+    let Some(duty_cycle) = duty_cycle.filter(|cycle| cycle.window < cycle.interval) else {
+        return future::pending().await;
+    };
+
+    loop {
+        tokio::time::sleep(duty_cycle.window).await;
+        adapter.stop_scan().await?;
+        is_scanning.store(false, Ordering::Relaxed);
+
+        tokio::time::sleep(duty_cycle.interval - duty_cycle.window).await;
+        adapter.start_scan(ScanFilter::default()).await?;
+        is_scanning.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Looks up the peripheral with the given identity among the peripherals known to the adapter.
+async fn find_known_peripheral(
+    adapter: &Adapter,
+    identity: &PeripheralIdentity,
+) -> btleplug::Result<Option<Peripheral>> {
+    let peripherals = adapter.peripherals().await?;
+    Ok(peripherals
+        .into_iter()
+        .find(|peripheral| identity.matches(peripheral)))
+}
+
+/// Returns the first bluetooth adapter of the system, which is the one to use on most machines.
+/// Fails with [`Error::DeviceNotFound`] if the system has no adapter.
+pub async fn first_adapter() -> crate::Result<Adapter> {
+    let manager = Manager::new().await?;
+    manager
+        .adapters()
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(Error::DeviceNotFound)
+}
+
+/// Connects to the control unit with the given identity, e.g. the one of the last session.
+/// The peripheral is looked up without scanning first, which takes milliseconds instead of seconds.
+/// If it is unknown to the adapter or can not be connected, falls back to a full discovery
+/// within the timeout and connects to the first control unit found.
+/// Each connection attempt gets the timeout as well.
+/// Returns none if the discovery did not find a control unit within the timeout.
+pub async fn reconnect_ble(
+    adapter: &Adapter,
+    identity: &PeripheralIdentity,
+    timeout: Duration,
+) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+    let known = find_known_peripheral(adapter, identity)
+        .await
+        .map_err(|error| Error::from(error).during(Operation::Discover))?;
+    if let Some(peripheral) = known {
+        let mut control_unit = ControlUnit::new(BackendBLE::new(peripheral));
+        match connect_within(&mut control_unit, timeout).await {
+            Ok(()) => return Ok(Some(control_unit)),
+            Err(error) => log::debug!("known control unit failed to connect: {}", error),
+        }
+    }
+
+    let Some(mut control_unit) = discover_within(adapter, timeout).await? else {
+        return Ok(None);
+    };
+    connect_within(&mut control_unit, timeout).await?;
+    Ok(Some(control_unit))
+}
+
+/// Connects to the first control unit among the peripherals the adapter already knows, without
+/// scanning. Every known control unit gets the timeout to connect, unreachable ones are skipped.
+/// Returns none if no known control unit could be connected.
+pub async fn connect_known_ble(
+    adapter: &Adapter,
+    timeout: Duration,
+) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+    let candidates = BleDiscovery::new(adapter)
+        .known_candidates()
+        .await
+        .map_err(|error| Error::from(error).during(Operation::Discover))?;
+
+    for peripheral in candidates {
+        let mut control_unit = ControlUnit::new(BackendBLE::new(peripheral));
+        match connect_within(&mut control_unit, timeout).await {
+            Ok(()) => return Ok(Some(control_unit)),
+            Err(error) => log::debug!("known control unit failed to connect: {}", error),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Transport over which [`connect_any_ble`] connected to the control unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Transport {
+    /// The remembered bluetooth peripheral, which was connected without scanning.
+    KnownBle(PeripheralIdentity),
+
+    /// A bluetooth peripheral which was found by a scan.
+    ScannedBle(PeripheralIdentity),
+}
+
+/// Connects to a control unit over bluetooth, for applications which should not ask their users
+/// which peripheral to use.
+///
+/// The remembered peripheral, e.g. the [`identity`](BackendBLE::identity) of the last session,
+/// is tried first without scanning, then the first bluetooth adapter scans for a control unit.
+/// Each of both attempts gets the timeout to connect, and the scan gets it to find a control unit.
+/// Only bluetooth is tried, serial connections are not probed.
+/// Fails with [`Error::DeviceNotFound`] if the system has no usable adapter or no control unit
+/// could be connected.
+pub async fn connect_any_ble(
+    remembered: Option<&PeripheralIdentity>,
+    timeout: Duration,
+) -> crate::Result<(ControlUnit<BackendBLE>, Transport)> {
+    let adapter = match first_adapter().await {
+        Ok(adapter) => adapter,
+        Err(error) => {
+            log::debug!("no bluetooth adapter available: {}", error);
+            return Err(Error::DeviceNotFound);
+        }
+    };
+
+    if let Some(identity) = remembered {
+        match find_known_peripheral(&adapter, identity).await {
+            Ok(Some(peripheral)) => {
+                let mut control_unit = ControlUnit::new(BackendBLE::new(peripheral));
+                match connect_within(&mut control_unit, timeout).await {
+                    Ok(()) => {
+                        let transport = Transport::KnownBle(control_unit.backend().identity());
+                        return Ok((control_unit, transport));
+                    }
+                    Err(error) => {
+                        log::debug!("remembered control unit failed to connect: {}", error)
+                    }
+                }
+            }
+            Ok(None) => log::debug!("remembered control unit is unknown to the adapter"),
+            Err(error) => log::debug!("failed to look up the remembered control unit: {}", error),
+        }
+    }
+
+    let mut control_unit = discover_within(&adapter, timeout)
+        .await?
+        .ok_or(Error::DeviceNotFound)?;
+    connect_within(&mut control_unit, timeout).await?;
+    let transport = Transport::ScannedBle(control_unit.backend().identity());
+    Ok((control_unit, transport))
+}
+
+/// Connects the control unit and fails with a timeout if it does not connect in time.
+async fn connect_within(
+    control_unit: &mut ControlUnit<BackendBLE>,
+    timeout: Duration,
+) -> crate::Result<()> {
+    match tokio::time::timeout(timeout, control_unit.connect()).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::TimedOut {
+            operation: Operation::Connect,
+            timeout,
+        }),
+    }
+}
+
+/// Searches for a control unit and treats a discovery which timed out like one which found nothing.
+async fn discover_within(
+    adapter: &Adapter,
+    timeout: Duration,
+) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+    match discover_first_ble(adapter, timeout).await {
+        Err(Error::TimedOut {
+            operation: Operation::Discover,
+            ..
+        }) => Ok(None),
+        result => result,
+    }
+}
+
+/// Searches for a control unit bluetooth device in the range of the given adapter and returns the first instance.
+/// Fails with [`Error::TimedOut`] if no control unit was found within the timeout.
+/// Returns none if the adapter stopped reporting peripherals before.
+pub async fn discover_first_ble(
+    adapter: &Adapter,
+    timeout: Duration,
+) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+    BleDiscovery::new(adapter)
+        .with_timeout(timeout)
+        .first()
+        .await
+}
+
+/// Searches for a control unit like [`discover_first_ble`], but stops the scan as soon as the
+/// given token is cancelled, e.g. when the user aborts the search. Returns none if cancelled.
+pub async fn discover_first_ble_cancellable(
+    adapter: &Adapter,
+    timeout: Duration,
+    cancellation: &CancellationToken,
+) -> crate::Result<Option<ControlUnit<BackendBLE>>> {
+    BleDiscovery::new(adapter)
+        .with_timeout(timeout)
+        .with_cancellation(cancellation.clone())
+        .first()
+        .await
+}
@@ -0,0 +1,98 @@
+//! Module which defines the macro generating the bounded value types of the protocol, e.g. the
+//! speed level of a car or the identifier of a controller.
+
+/// Describes a value which is outside the range of a bounded type.
+#[cfg(feature = "std")]
+pub(crate) fn out_of_range(name: &str, value: usize, min: usize, max: usize) -> crate::Error {
+    crate::Error::InvalidArgument(format!(
+        "{} {} is not in range [{}, {}]",
+        name, value, min, max
+    ))
+}
+
+/// Defines a type which stores a `u8` within an inclusive range and converts from and to the
+/// given integer type.
+///
+/// Generates the validating constructor `new`, the fallible `TryFrom` conversion, the conversion
+/// back into the integer type and the serde implementations, which serialize the plain integer.
+/// The remaining constants and accessors are left to the type.
+macro_rules! bounded_type {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($repr:ty) in [$min:expr, $max:expr], $description:literal;
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(u8);
+
+        impl $name {
+            /// Creates the value from its integer representation.
+            /// Returns none if the integer is outside the range of the type.
+            pub const fn new(value: $repr) -> Option<$name> {
+                const MIN: $repr = $min;
+                const MAX: $repr = $max;
+                match value.checked_sub(MIN) {
+                    Some(offset) if offset <= MAX - MIN => Some($name(value as u8)),
+                    _ => None,
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl TryFrom<$repr> for $name {
+            type Error = crate::Error;
+
+            fn try_from(value: $repr) -> Result<Self, Self::Error> {
+                $name::new(value).ok_or_else(|| {
+                    crate::bounded::out_of_range(
+                        $description,
+                        value as usize,
+                        $min as usize,
+                        $max as usize,
+                    )
+                })
+            }
+        }
+
+        impl From<$name> for $repr {
+            fn from(value: $name) -> Self {
+                <$repr>::from(value.0)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                <$repr as serde::Serialize>::serialize(&<$repr>::from(*self), serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = <$repr as serde::Deserialize>::deserialize(deserializer)?;
+                <$name>::try_from(value).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+pub(crate) use bounded_type;
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{ControllerId, SpeedLevel};
+
+    #[test]
+    fn names_the_range_of_rejected_values() {
+        let error = ControllerId::try_from(8).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("controller 8 is not in range [0, 7]"));
+
+        let error = SpeedLevel::try_from(16).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("speed level 16 is not in range [0, 15]"));
+    }
+}
@@ -0,0 +1,42 @@
+//! Module which defines the brake level of a car.
+
+use crate::bounded::bounded_type;
+
+bounded_type! {
+    /// Brake force of a car as it is configured on the control unit.
+    /// The level is always in range [0, 15], higher levels make the car brake harder.
+    pub struct BrakeLevel(u8) in [0, 15], "brake level";
+}
+
+impl BrakeLevel {
+    /// Weakest brake level.
+    pub const MIN: BrakeLevel = BrakeLevel(0);
+
+    /// Strongest brake level, which is the default of the control unit.
+    pub const MAX: BrakeLevel = BrakeLevel(15);
+
+    /// Returns the raw brake level in range [0, 15].
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for BrakeLevel {
+    /// Creates the strongest brake level.
+    fn default() -> Self {
+        Self::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_levels_up_to_the_maximum() {
+        assert_eq!(BrakeLevel::new(0), Some(BrakeLevel::MIN));
+        assert_eq!(BrakeLevel::new(15), Some(BrakeLevel::MAX));
+        assert_eq!(BrakeLevel::new(16), None);
+        assert_eq!(u8::from(BrakeLevel::MAX), 15);
+    }
+}
@@ -0,0 +1,115 @@
+//! Module which abstracts the source of the current point in time.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of the current point in time.
+/// Time dependent components take a clock, so their behavior can be tested without sleeping.
+pub trait Clock: Send + Sync {
+    /// Returns the current point in time.
+    fn now(&self) -> Instant;
+}
+
+/// Returns the current point in time of the clock or none in browsers, which provide no clock via
+/// std, so statuses stay without timestamp and connections without keepalive there.
+pub(crate) fn try_now(clock: &dyn Clock) -> Option<Instant> {
+    if cfg!(target_arch = "wasm32") {
+        None
+    } else {
+        Some(clock.now())
+    }
+}
+
+/// Clock which is shared by a component and can be cloned with it, e.g. as part of a session.
+#[derive(Clone)]
+pub(crate) struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub(crate) fn new<C: Clock + 'static>(clock: C) -> SharedClock {
+        SharedClock(Arc::new(clock))
+    }
+
+    /// Returns the current point in time or none in browsers, see [`try_now`].
+    pub(crate) fn try_now(&self) -> Option<Instant> {
+        try_now(self.0.as_ref())
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock::new(SystemClock)
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("SharedClock")
+    }
+}
+
+/// Clock which returns the real time, the default of all components.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock which only moves forward when told to, for deterministic tests.
+/// Clones share the same time, so a test can keep one and hand another to the component under test.
+#[derive(Clone)]
+pub struct TestClock {
+    start: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestClock {
+    /// Creates a clock which stands still at the current point in time.
+    pub fn new() -> TestClock {
+        TestClock {
+            start: Instant::now(),
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Moves the clock forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.lock() += duration;
+    }
+
+    /// Returns the time the clock was moved forward since its creation.
+    pub fn elapsed(&self) -> Duration {
+        *self.lock()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Duration> {
+        // the guarded duration is always valid, even if another thread panicked while holding it
+        self.elapsed
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+impl fmt::Debug for TestClock {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("TestClock")
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
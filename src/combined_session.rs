@@ -0,0 +1,201 @@
+//! Module which merges the lap statuses of several control units into one race session, e.g. for
+//! layouts whose sections are timed by different control units.
+
+use crate::{Lap, LapStatus, LapTime, RaceSession, Standing, Status};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Clock calibration of a single control unit within a combined session.
+#[derive(Debug, Clone, Copy, Default)]
+struct SourceClock {
+    /// Offset in milliseconds which is added to the timer of the control unit.
+    offset: Option<i64>,
+
+    /// Whether the offset was set explicitly or laps were timed with it, so it is not
+    /// calibrated anymore.
+    is_fixed: bool,
+}
+
+/// Race session which classifies the laps reported by several control units together.
+///
+/// Each control unit is a source which is identified by its index. The controllers of all
+/// sources are the same cars, so the additional control units should report their sensors as
+/// check lanes, whose times become the splits of the laps.
+///
+/// The timers of the control units are not synchronized, so the timer of every source is shifted
+/// by a clock offset into a common time base. Unless an offset is set explicitly, it is
+/// calibrated from the point in time the first lap status of the source was received. The offset
+/// is frozen as soon as a lap is timed with it, correcting it later would shorten or lengthen
+/// the laps which are in progress.
+#[derive(Debug, Clone, Default)]
+pub struct CombinedSession {
+    session: RaceSession,
+    clocks: HashMap<usize, SourceClock>,
+    epoch: Option<Instant>,
+}
+
+impl CombinedSession {
+    /// Creates a combined session which classifies the laps in the given session.
+    pub fn new(session: RaceSession) -> CombinedSession {
+        CombinedSession {
+            session,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the clock offset of a source in milliseconds, which is added to its timer.
+    /// The offset is no longer calibrated afterwards, e.g. when the clocks of all control units
+    /// were reset at the same time and the offsets are known to be zero.
+    pub fn set_clock_offset(&mut self, source: usize, offset: i64) {
+        *self.clock_mut(source) = SourceClock {
+            offset: Some(offset),
+            is_fixed: true,
+        };
+    }
+
+    /// Returns the clock offset of a source in milliseconds or none if it is not known yet.
+    pub fn clock_offset(&self, source: usize) -> Option<i64> {
+        self.clocks.get(&source).and_then(|clock| clock.offset)
+    }
+
+    /// Discards the clock offset of a source, so it is calibrated again.
+    /// Call this after the clock of the control unit was reset, laps which are in progress are
+    /// timed with the new offset.
+    pub fn reset_clock_offset(&mut self, source: usize) {
+        *self.clock_mut(source) = SourceClock::default();
+    }
+
+    /// Consumes the next status of the control unit with the given source index.
+    /// Returns the completed lap if the status completed one.
+    pub fn update(&mut self, source: usize, status: &Status) -> Option<Lap> {
+        match status {
+            Status::Lap(status) => self.update_lap(source, status),
+            _ => None,
+        }
+    }
+
+    /// Consumes the next lap status of the control unit with the given source index.
+    /// Returns the completed lap if the status completed one. Statuses of sources whose offset
+    /// is neither set nor calibrated from a reception time are dropped.
+    pub fn update_lap(&mut self, source: usize, status: &LapStatus) -> Option<Lap> {
+        if let Some(received_at) = status.received_at {
+            self.calibrate(source, status.time, received_at);
+        }
+
+        let clock = self.clock_mut(source);
+        let offset = clock.offset?;
+        clock.is_fixed = true;
+        let time = LapTime::from_millis(status.time.as_millis().wrapping_add(offset as u32));
+        self.session.update_lap(&status.with_time(time))
+    }
+
+    /// Returns the session with the laps of all sources.
+    pub fn session(&self) -> &RaceSession {
+        &self.session
+    }
+
+    /// Returns all laps in the order they were completed.
+    pub fn laps(&self) -> &[Lap] {
+        self.session.laps()
+    }
+
+    /// Returns the combined standings of all controllers.
+    pub fn standings(&self) -> Vec<Standing> {
+        self.session.standings()
+    }
+
+    /// Discards all laps and the clock offsets of all sources.
+    pub fn reset(&mut self) {
+        self.session.reset();
+        self.clocks.clear();
+        self.epoch = None;
+    }
+
+    fn clock_mut(&mut self, source: usize) -> &mut SourceClock {
+        self.clocks.entry(source).or_default()
+    }
+
+    fn calibrate(&mut self, source: usize, time: LapTime, received_at: Instant) {
+        let epoch = *self.epoch.get_or_insert(received_at);
+        let received = match received_at.checked_duration_since(epoch) {
+            Some(elapsed) => elapsed.as_millis() as i64,
+            None => -(epoch.duration_since(received_at).as_millis() as i64),
+        };
+        let offset = received - i64::from(time.as_millis());
+
+        let clock = self.clock_mut(source);
+        if !clock.is_fixed {
+            clock.offset = Some(offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ControllerId;
+    use std::time::Duration;
+
+    fn crossing(millis: u32) -> LapStatus {
+        LapStatus::default()
+            .with_controller(ControllerId::ALL[0])
+            .with_sector(1)
+            .with_time(LapTime::from_millis(millis))
+    }
+
+    #[test]
+    fn freezes_the_calibrated_offset_while_laps_are_timed() {
+        let epoch = Instant::now();
+        let mut combined = CombinedSession::new(RaceSession::new());
+
+        // the first status was delayed by the transmission, the second was not
+        let first = crossing(1_000).with_received_at(epoch);
+        let second = crossing(11_000).with_received_at(epoch + Duration::from_millis(9_700));
+        assert_eq!(combined.update_lap(0, &first), None);
+        assert_eq!(combined.clock_offset(0), Some(-1_000));
+
+        let lap = combined.update_lap(0, &second).unwrap();
+        assert_eq!(lap.lap_time, Duration::from_millis(10_000));
+        assert_eq!(combined.clock_offset(0), Some(-1_000));
+    }
+
+    #[test]
+    fn recalibrates_the_offset_after_it_was_reset() {
+        let epoch = Instant::now();
+        let mut combined = CombinedSession::new(RaceSession::new());
+
+        combined.update_lap(0, &crossing(1_000).with_received_at(epoch));
+        combined.reset_clock_offset(0);
+        assert_eq!(combined.clock_offset(0), None);
+
+        let restarted = crossing(0).with_received_at(epoch + Duration::from_millis(5_000));
+        combined.update_lap(0, &restarted);
+        assert_eq!(combined.clock_offset(0), Some(5_000));
+    }
+
+    #[test]
+    fn uses_explicit_offsets_without_reception_times() {
+        let mut combined = CombinedSession::new(RaceSession::new());
+
+        assert_eq!(combined.update_lap(1, &crossing(1_000)), None);
+        assert!(combined.laps().is_empty());
+
+        combined.set_clock_offset(1, 500);
+        combined.update_lap(1, &crossing(1_000));
+        let lap = combined.update_lap(1, &crossing(4_000)).unwrap();
+        assert_eq!(lap.time, LapTime::from_millis(4_500));
+        assert_eq!(lap.lap_time, Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn accepts_any_source_index() {
+        let mut combined = CombinedSession::new(RaceSession::new());
+
+        combined.update(
+            usize::MAX,
+            &Status::Lap(crossing(1_000).with_received_at(Instant::now())),
+        );
+        assert_eq!(combined.clock_offset(usize::MAX), Some(-1_000));
+        assert_eq!(combined.clock_offset(0), None);
+    }
+}
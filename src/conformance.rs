@@ -0,0 +1,232 @@
+//! Module which replays traces of control unit traffic through the decoders.
+//!
+//! A trace is a text file with the request and response frames as they are logged by the
+//! [`HexDumpBackend`](crate::HexDumpBackend), each response optionally followed by the expected
+//! outcome of decoding it:
+//!
+//! ```text
+//! # firmware: 5337
+//!       12.501ms TX 3F |?|
+//!       20.017ms RX 3F 3A 3F 3F 3F 3F 3F 3F 3F 3F 30 30 30 30 30 32 |?:????????000002|
+//! = track fuel=15,15,15,15,15,15,15,15 start=0 mode=0 refueling=0 controllers=0
+//! ```
+//!
+//! The timestamps in front of the direction are optional. Lines starting with `#` are comments,
+//! a comment of the form `# firmware: <version>` records the firmware of the capture. Expected
+//! outcomes have one of the forms produced by [`describe`]:
+//!
+//! * `= no_data`
+//! * `= lap controller=<index> sector=<sector> time=<ms>`
+//! * `= track fuel=<8 levels> start=<signal> mode=<bits> refueling=<mask> controllers=<count>`
+//! * `= version <version>`
+//! * `= ack`
+//! * `= error <reason>`, with the name of the [`DecodeError`]
+
+use crate::messages::{
+    decode_acknowledgement, decode_status, decode_version, STATUS_REQUEST, VERSION_REQUEST,
+};
+use crate::{ChecksumValidation, DecodeError, Error, Status};
+use std::path::Path;
+
+/// Outcome of decoding a response frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoded {
+    /// Response to a status request.
+    Status(Status),
+
+    /// Response to a firmware version request.
+    Version(String),
+
+    /// Acknowledgement of a request which does not return any data.
+    Acknowledged,
+}
+
+/// A request frame and the response of the control unit to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exchange {
+    /// Line of the response in the trace.
+    pub line: usize,
+
+    /// Raw request which was sent to the control unit.
+    pub request: Vec<u8>,
+
+    /// Raw response of the control unit.
+    pub response: Vec<u8>,
+
+    /// Expected outcome of decoding the response, if the trace contains one.
+    pub expected: Option<String>,
+}
+
+impl Exchange {
+    /// Decodes the response with the decoder matching the request.
+    pub fn decode(&self, validation: ChecksumValidation) -> Result<Decoded, DecodeError> {
+        if self.request == STATUS_REQUEST {
+            decode_status(&self.response, validation).map(Decoded::Status)
+        } else if self.request == VERSION_REQUEST {
+            decode_version(&self.response, validation).map(Decoded::Version)
+        } else {
+            decode_acknowledgement(&self.request, &self.response, validation)
+                .map(|_| Decoded::Acknowledged)
+        }
+    }
+}
+
+/// Expected outcome of an exchange which differs from the actual outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Line of the response in the trace.
+    pub line: usize,
+
+    /// Outcome as expected by the trace.
+    pub expected: String,
+
+    /// Outcome as produced by the decoders.
+    pub actual: String,
+}
+
+/// Captured traffic of a control unit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// Firmware version of the control unit the trace was captured from.
+    pub firmware: Option<String>,
+
+    /// Exchanges in the order of the trace.
+    pub exchanges: Vec<Exchange>,
+}
+
+/// Parses the hex bytes of a logged frame, which are followed by their printable characters.
+fn parse_frame<'a>(tokens: impl Iterator<Item = &'a str>) -> Option<Vec<u8>> {
+    tokens
+        .take_while(|token| !token.starts_with('|'))
+        .map(|token| {
+            u8::from_str_radix(token, 16)
+                .ok()
+                .filter(|_| token.len() == 2)
+        })
+        .collect()
+}
+
+impl Trace {
+    /// Parses a trace from its text.
+    pub fn parse(text: &str) -> crate::Result<Trace> {
+        let mut trace = Trace::default();
+        let mut request: Option<Vec<u8>> = None;
+        let invalid = |line: usize, reason: &str| {
+            Error::InvalidArgument(format!("line {} of the trace {}", line, reason))
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(firmware) = comment.trim().strip_prefix("firmware:") {
+                    trace.firmware = Some(firmware.trim().to_owned());
+                }
+                continue;
+            }
+
+            if let Some(expected) = line.strip_prefix('=') {
+                let exchange = trace
+                    .exchanges
+                    .last_mut()
+                    .filter(|exchange| exchange.expected.is_none())
+                    .ok_or_else(|| invalid(line_number, "expects an outcome without response"))?;
+                exchange.expected = Some(expected.trim().to_owned());
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+            let direction = tokens
+                .by_ref()
+                .find(|token| *token == "TX" || *token == "RX");
+            match direction {
+                Some("TX") => {
+                    let frame = parse_frame(tokens)
+                        .ok_or_else(|| invalid(line_number, "contains an invalid frame"))?;
+                    request = Some(frame);
+                }
+                Some(_) => {
+                    // responses which are errors of the backend, e.g. timeouts, carry no frame
+                    let Some(response) = parse_frame(tokens) else {
+                        request = None;
+                        continue;
+                    };
+                    let request = request.take().ok_or_else(|| {
+                        invalid(line_number, "contains a response without request")
+                    })?;
+                    trace.exchanges.push(Exchange {
+                        line: line_number,
+                        request,
+                        response,
+                        expected: None,
+                    });
+                }
+                None if line.is_empty() => (),
+                None => return Err(invalid(line_number, "is neither a frame nor an outcome")),
+            }
+        }
+
+        Ok(trace)
+    }
+
+    /// Loads and parses the trace stored at the given path.
+    pub fn load<P: AsRef<Path>>(path: P) -> crate::Result<Trace> {
+        let text = std::fs::read_to_string(path).map_err(|error| Error::Other(Box::new(error)))?;
+        Self::parse(&text)
+    }
+
+    /// Decodes every exchange with an expected outcome and returns those which differ.
+    pub fn verify(&self, validation: ChecksumValidation) -> Vec<Mismatch> {
+        self.exchanges
+            .iter()
+            .filter_map(|exchange| {
+                let expected = exchange.expected.as_ref()?;
+                let actual = describe(&exchange.decode(validation));
+                let normalized: Vec<&str> = expected.split_whitespace().collect();
+                (normalized != actual.split_whitespace().collect::<Vec<_>>()).then(|| Mismatch {
+                    line: exchange.line,
+                    expected: expected.clone(),
+                    actual,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Describes the outcome of decoding a response in the form of the expected outcomes of a trace.
+pub fn describe(outcome: &Result<Decoded, DecodeError>) -> String {
+    match outcome {
+        Err(reason) => format!("error {}", reason.name()),
+        Ok(Decoded::Acknowledged) => "ack".to_owned(),
+        Ok(Decoded::Version(version)) => format!("version {}", version),
+        Ok(Decoded::Status(Status::NoData)) => "no_data".to_owned(),
+        Ok(Decoded::Status(Status::Lap(status))) => format!(
+            "lap controller={} sector={} time={}",
+            status.controller.index(),
+            status.sector,
+            status.time.as_millis()
+        ),
+        Ok(Decoded::Status(Status::Track(status))) => {
+            let fuel: Vec<String> = status
+                .fuel_levels
+                .iter()
+                .map(|level| level.value().to_string())
+                .collect();
+            let refueling = status
+                .is_refueling
+                .iter()
+                .enumerate()
+                .filter(|(_, is_refueling)| **is_refueling)
+                .fold(0u8, |mask, (index, _)| mask | (1 << index));
+            format!(
+                "track fuel={} start={} mode={} refueling={} controllers={}",
+                fuel.join(","),
+                status.start_signal as u8,
+                status.mode.bits(),
+                refueling,
+                status.controller_count
+            )
+        }
+    }
+}
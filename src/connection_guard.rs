@@ -0,0 +1,79 @@
+//! Module which disconnects a control unit when its owner forgets to.
+
+use crate::{Backend, ControlUnit};
+use std::ops::{Deref, DerefMut};
+
+/// Owner of a connected control unit which disconnects it when dropped.
+///
+/// The control unit keeps a bluetooth link open until it is disconnected, so a forgotten
+/// [`disconnect`](ControlUnit::disconnect) leaves the link dangling until it times out. Dropping
+/// the guard spawns a best-effort disconnect on the current tokio runtime instead, whose errors
+/// are only logged. Call [`close`](ConnectionGuard::close) to disconnect deterministically.
+///
+/// The guard dereferences to the control unit, so it is used just like the control unit itself.
+pub struct ConnectionGuard<T: Backend + 'static> {
+    control_unit: Option<ControlUnit<T>>,
+}
+
+impl<T: Backend + 'static> ConnectionGuard<T> {
+    /// Creates a guard which disconnects the given control unit when dropped.
+    pub fn new(control_unit: ControlUnit<T>) -> ConnectionGuard<T> {
+        ConnectionGuard {
+            control_unit: Some(control_unit),
+        }
+    }
+
+    /// Disconnects the control unit and waits until it is disconnected.
+    pub async fn close(mut self) -> crate::Result<()> {
+        match self.control_unit.take() {
+            Some(mut control_unit) => control_unit.disconnect().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the control unit without disconnecting it.
+    pub fn into_inner(mut self) -> ControlUnit<T> {
+        self.control_unit
+            .take()
+            .expect("control unit is only taken when the guard is consumed")
+    }
+}
+
+impl<T: Backend + 'static> Deref for ConnectionGuard<T> {
+    type Target = ControlUnit<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.control_unit
+            .as_ref()
+            .expect("control unit is only taken when the guard is consumed")
+    }
+}
+
+impl<T: Backend + 'static> DerefMut for ConnectionGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.control_unit
+            .as_mut()
+            .expect("control unit is only taken when the guard is consumed")
+    }
+}
+
+impl<T: Backend + 'static> Drop for ConnectionGuard<T> {
+    fn drop(&mut self) {
+        let Some(mut control_unit) = self.control_unit.take() else {
+            return;
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(error) = control_unit.disconnect().await {
+                        log::warn!("failed to disconnect dropped control unit: {}", error);
+                    }
+                });
+            }
+            Err(_) => {
+                log::warn!("dropped control unit outside of a tokio runtime, not disconnected")
+            }
+        }
+    }
+}
@@ -1,20 +1,30 @@
 //! Module which implements the core logic to interact with a control unit.
 
 use super::{messages::*, Error, Status};
-use crate::Backend;
-use std::time::Duration;
+use crate::clock::try_now;
+use crate::protocol::{make_set_word_request, WordAddress};
+use crate::{
+    Accessories, Backend, BrakeLevel, CarSettings, ChecksumValidation, Clock, ControllerId,
+    CuProfile, Frame, FuelLevel, LapStatus, Operation, ProtocolProfile, RequestBatch, SpeedLevel,
+    StartSignal, SystemClock, TowerPosition, TrackStatus,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct ControlUnit<T: Backend> {
     backend: T,
     timeout: Duration,
+    profile: Option<ProtocolProfile>,
+    checksum_validation: ChecksumValidation,
+    clock: Arc<dyn Clock>,
+    retries: u32,
+    keepalive: Option<Duration>,
+    last_exchange: Option<Instant>,
 }
 
-fn decode_result_to_error<T>(result: Option<T>) -> Result<T, Error> {
-    match result {
-        Some(value) => Ok(value),
-        None => Err(Error::InvalidResponse),
-    }
-}
+/// Maximum number of pipelined requests which await their response at the same time.
+/// The control unit only buffers a few requests, larger batches are sent in chunks.
+pub const MAX_IN_FLIGHT_REQUESTS: usize = 8;
 
 const BUTTON_ESCAPE: u8 = 1;
 const BUTTON_ENTER: u8 = 2;
@@ -23,26 +33,114 @@ const BUTTON_BRAKE: u8 = 6;
 const BUTTON_FUEL: u8 = 7;
 const BUTTON_CODE: u8 = 8;
 
+const ENTER_REQUEST: [u8; 3] = make_button_press_request(BUTTON_ENTER);
+const ESCAPE_REQUEST: [u8; 3] = make_button_press_request(BUTTON_ESCAPE);
+const SPEED_REQUEST: [u8; 3] = make_button_press_request(BUTTON_SPEED);
+const BRAKE_REQUEST: [u8; 3] = make_button_press_request(BUTTON_BRAKE);
+const FUEL_REQUEST: [u8; 3] = make_button_press_request(BUTTON_FUEL);
+const CODE_REQUEST: [u8; 3] = make_button_press_request(BUTTON_CODE);
+const RESET_POSITIONS_REQUEST: [u8; 6] = make_reset_positions_request();
+const RESET_CLOCK_REQUEST: [u8; 4] = make_reset_clock_request();
+
+/// Determines if the request can be repeated without changing the outcome, i.e. a word write or a
+/// version request. Repeating a button press may press the button twice if the first request
+/// timed out after it reached the control unit, and repeating a status poll may lose a lap status.
+fn is_idempotent(request: &[u8]) -> bool {
+    matches!(request.first(), Some(b'J') | Some(b'0'))
+}
+
 impl<T: Backend> ControlUnit<T> {
     pub fn new(backend: T) -> ControlUnit<T> {
         ControlUnit {
             backend,
             timeout: Duration::from_secs(2),
+            profile: None,
+            checksum_validation: ChecksumValidation::Strict,
+            clock: Arc::new(SystemClock),
+            retries: 0,
+            keepalive: None,
+            last_exchange: None,
         }
     }
 
+    /// Creates a builder to configure a control unit with the given backend.
+    pub fn builder(backend: T) -> ControlUnitBuilder<T> {
+        ControlUnitBuilder {
+            control_unit: ControlUnit::new(backend),
+        }
+    }
+
+    /// Sets how often requests which failed with a transient error are repeated.
+    /// Only word writes and version requests are repeated, button presses and status polls are
+    /// not, since a request which timed out may still have reached the control unit.
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Sets the idle duration after which [`keep_alive`](ControlUnit::keep_alive) pings the control unit.
+    pub fn set_keepalive(&mut self, keepalive: Option<Duration>) {
+        self.keepalive = keepalive;
+    }
+
     /// Sets the timeout which is used for control unit communication.
     pub fn set_timeout(&mut self, timeout: Duration) {
         self.timeout = timeout;
     }
 
+    /// Sets the strictness which is applied when validating the checksums of responses.
+    /// Some firmware revisions are known to emit wrong checksums on specific responses.
+    pub fn set_checksum_validation(&mut self, validation: ChecksumValidation) {
+        self.checksum_validation = validation;
+    }
+
+    /// Sets the clock which timestamps received statuses, the default is the [`SystemClock`].
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock = Arc::new(clock);
+    }
+
+    /// Returns the backend of the control unit.
+    pub fn backend(&self) -> &T {
+        &self.backend
+    }
+
+    /// Returns the backend of the control unit mutably, e.g. to configure a discovered backend.
+    pub fn backend_mut(&mut self) -> &mut T {
+        &mut self.backend
+    }
+
+    /// Returns the protocol profile of the control unit.
+    /// The profile is determined from the firmware version when connecting and is none while disconnected.
+    pub fn profile(&self) -> Option<&ProtocolProfile> {
+        self.profile.as_ref()
+    }
+
     /// Connects the control unit with the configured backend.
+    /// Queries the firmware version of the control unit to determine its protocol profile and
+    /// disconnects again if the control unit does not answer.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub async fn connect(&mut self) -> Result<(), Error> {
-        self.backend.connect().await
+        self.backend
+            .connect()
+            .await
+            .map_err(|error| error.during(Operation::Connect))?;
+        // a control unit which does not answer is not usable, so the connection is not kept
+        let version = match self.get_version().await {
+            Ok(version) => version,
+            Err(error) => {
+                if let Err(disconnect_error) = self.backend.disconnect().await {
+                    log::warn!("failed to disconnect after {}: {}", error, disconnect_error);
+                }
+                return Err(error);
+            }
+        };
+        self.profile = Some(ProtocolProfile::new(version));
+        Ok(())
     }
 
     /// Disconnects the control unit from the configured backend.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub async fn disconnect(&mut self) -> Result<(), Error> {
+        self.profile = None;
         self.backend.disconnect().await
     }
 
@@ -51,110 +149,696 @@ impl<T: Backend> ControlUnit<T> {
         self.backend.is_connected().await
     }
 
+    /// Pings the control unit with a version request if no request was sent for the configured
+    /// keepalive duration, which keeps idle connections from being dropped.
+    /// Call it periodically, returns if a ping was sent. Does nothing without a keepalive duration
+    /// and in browsers, which provide no clock to measure the idle duration.
+    pub async fn keep_alive(&mut self) -> Result<bool, Error> {
+        let (Some(keepalive), Some(now)) = (self.keepalive, try_now(self.clock.as_ref())) else {
+            return Ok(false);
+        };
+        let is_idle = self
+            .last_exchange
+            .is_none_or(|last_exchange| now.saturating_duration_since(last_exchange) >= keepalive);
+        if !is_idle {
+            return Ok(false);
+        }
+
+        self.get_version().await?;
+        Ok(true)
+    }
+
     /// Reads the current status during a race.
     /// The control unit can either return a track status or a lap status object.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
     pub async fn get_status(&mut self) -> Result<Status, Error> {
-        let response = self.backend.request(&STATUS_REQUEST, self.timeout).await?;
-        decode_result_to_error(decode_status(&response))
+        let response = self.request(Operation::Status, &STATUS_REQUEST).await?;
+        let received_at = try_now(self.clock.as_ref());
+
+        let mut status = decode_status(&response, self.checksum_validation)
+            .map_err(|reason| Error::invalid_response(&response, reason))?;
+        if let Some(received_at) = received_at {
+            status.set_received_at(received_at);
+        }
+        Ok(status)
+    }
+
+    /// Reads all statuses which are currently queued on the control unit.
+    /// Lap statuses are queued when several cars cross a sensor in short succession, so this polls
+    /// the control unit until it reports a track status or no data. The track status is included
+    /// as last element of the result.
+    pub async fn get_pending_statuses(&mut self) -> Result<Vec<Status>, Error> {
+        // safeguard against a control unit which never stops reporting lap statuses
+        const MAX_PENDING_STATUSES: usize = 32;
+
+        let mut result = Vec::new();
+        while result.len() < MAX_PENDING_STATUSES {
+            match self.get_status().await? {
+                Status::Lap(status) => result.push(Status::Lap(status)),
+                Status::NoData => break,
+                status => {
+                    result.push(status);
+                    break;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Polls the control unit until it reports a track status, at most for the configured timeout.
+    /// Lap statuses which are reported meanwhile are discarded, use
+    /// [`get_pending_statuses`](ControlUnit::get_pending_statuses) to keep them.
+    pub async fn get_track_status(&mut self) -> Result<TrackStatus, Error> {
+        self.wait_for(self.timeout, |status| match status {
+            Status::Track(status) => Some(status),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Reports which accessories are connected, based on the mode of the next track status.
+    /// See [`Accessories`] for the accessories which can not be detected.
+    pub async fn detect_accessories(&mut self) -> Result<Accessories, Error> {
+        let status = self.get_track_status().await?;
+        Ok(Accessories::from_track_status(&status))
+    }
+
+    /// Polls the control unit until it reports a lap status, at most for the given timeout.
+    /// Track statuses which are reported meanwhile are discarded.
+    pub async fn wait_for_lap(&mut self, timeout: Duration) -> Result<LapStatus, Error> {
+        self.wait_for(timeout, |status| match status {
+            Status::Lap(status) => Some(status),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Polls the control unit until the start lights reach the given signal, at most for the
+    /// given timeout. Returns the first track status which shows the signal or a later one of the
+    /// countdown, so a signal which was shown between two polls is not missed.
+    /// Lap statuses which are reported meanwhile are discarded.
+    pub async fn wait_for_start_signal(
+        &mut self,
+        signal: StartSignal,
+        timeout: Duration,
+    ) -> Result<TrackStatus, Error> {
+        self.wait_for(timeout, |status| match status {
+            Status::Track(status) if signal == StartSignal::None => {
+                (status.start_signal == StartSignal::None).then_some(status)
+            }
+            Status::Track(status) => (status.start_signal >= signal).then_some(status),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Polls statuses back-to-back until the given function selects one of them or the timeout
+    /// elapsed. Browsers provide no clock via std, so the timeout is not enforced there.
+    async fn wait_for<R>(
+        &mut self,
+        timeout: Duration,
+        mut select: impl FnMut(Status) -> Option<R>,
+    ) -> Result<R, Error> {
+        let deadline = try_now(self.clock.as_ref()).map(|now| now + timeout);
+        loop {
+            if let Some(result) = select(self.get_status().await?) {
+                return Ok(result);
+            }
+            if deadline.is_some_and(|deadline| self.clock.now() >= deadline) {
+                return Err(Error::TimedOut {
+                    operation: Operation::WaitForStatus,
+                    timeout,
+                });
+            }
+        }
     }
 
     /// Requests the current firmware version of the control unit.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub async fn get_version(&mut self) -> Result<String, Error> {
-        let response = self.backend.request(&VERSION_REQUEST, self.timeout).await?;
-        decode_result_to_error(decode_version(&response))
+        let response = self.request(Operation::Version, &VERSION_REQUEST).await?;
+        decode_version(&response, self.checksum_validation)
+            .map_err(|reason| Error::invalid_response(&response, reason))
+    }
+
+    /// Sends the request via the backend and attributes timeouts to the given operation.
+    async fn request(&mut self, operation: Operation, request: &[u8]) -> Result<Frame, Error> {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let started_at = Instant::now();
+
+        let mut attempt = 0;
+        let result = loop {
+            let result = self
+                .backend
+                .request(request, self.timeout)
+                .await
+                .map_err(|error| error.during(operation));
+            match result {
+                Err(error)
+                    if error.is_transient() && is_idempotent(request) && attempt < self.retries =>
+                {
+                    attempt += 1;
+                    log::debug!("repeating request after {}, attempt {}", error, attempt);
+                    self.backend.discard_stale_responses().await?;
+                }
+                result => break result,
+            }
+        };
+        if self.keepalive.is_some() {
+            self.last_exchange = try_now(self.clock.as_ref());
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            let command = request.first().map(|command| *command as char);
+            let latency_us = started_at.elapsed().as_micros() as u64;
+            match &result {
+                Ok(response) => {
+                    tracing::trace!(?command, latency_us, ?response, "request completed")
+                }
+                Err(error) => tracing::warn!(?command, latency_us, %error, "request failed"),
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let operation = operation.name();
+            metrics::counter!("carrlink_requests_total", "operation" => operation).increment(1);
+            metrics::histogram!("carrlink_request_duration_seconds", "operation" => operation)
+                .record(started_at.elapsed().as_secs_f64());
+            if let Err(error) = &result {
+                metrics::counter!("carrlink_request_errors_total", "operation" => operation, "error" => error.name())
+                    .increment(1);
+            }
+        }
+
+        result
+    }
+
+    /// Fails if the firmware of the connected control unit is not known to support commands.
+    fn require_commands(&self) -> Result<(), Error> {
+        match &self.profile {
+            Some(profile) if !profile.supports_commands() => Err(Error::NotSupported(format!(
+                "commands are not supported by firmware {:?}",
+                profile.version()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends a request which is only acknowledged by the control unit.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, request))
+    )]
+    async fn send(&mut self, request: &[u8]) -> Result<(), Error> {
+        self.require_commands()?;
+        let response = self.request(Operation::Request, request).await?;
+        decode_acknowledgement(request, &response, self.checksum_validation)
+            .map_err(|reason| Error::invalid_response(&response, reason))
     }
 
     /// Causes a press of the enter button of the control unit.
     pub async fn press_enter(&mut self) -> Result<(), Error> {
-        self.press_button(BUTTON_ENTER).await
+        self.press_button(&ENTER_REQUEST).await
     }
 
     /// Causes a press of the escape button of the control unit.
     pub async fn press_esc(&mut self) -> Result<(), Error> {
-        self.press_button(BUTTON_ESCAPE).await
+        self.press_button(&ESCAPE_REQUEST).await
     }
 
     /// Causes a press of the speed button of the control unit.
     pub async fn press_speed(&mut self) -> Result<(), Error> {
-        self.press_button(BUTTON_SPEED).await
+        self.press_button(&SPEED_REQUEST).await
     }
 
     /// Causes a press of the brake button of the control unit.
     pub async fn press_brake(&mut self) -> Result<(), Error> {
-        self.press_button(BUTTON_BRAKE).await
+        self.press_button(&BRAKE_REQUEST).await
     }
 
     /// Causes a press of the fuel button of the control unit.
     pub async fn press_fuel(&mut self) -> Result<(), Error> {
-        self.press_button(BUTTON_FUEL).await
+        self.press_button(&FUEL_REQUEST).await
     }
 
     /// Causes a press of the code button of the control unit.
     pub async fn press_code(&mut self) -> Result<(), Error> {
-        self.press_button(BUTTON_CODE).await
+        self.press_button(&CODE_REQUEST).await
     }
 
-    /// Simulates a button press with the given button ID.
-    async fn press_button(&mut self, button: u8) -> Result<(), Error> {
-        let request = make_button_press_request(button);
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+    /// Simulates a button press with the given button press request.
+    async fn press_button(&mut self, request: &[u8]) -> Result<(), Error> {
+        self.send(request).await
     }
 
     /// Resets the positions of the players displayed on the position tower.
     pub async fn reset_positions(&mut self) -> Result<(), Error> {
-        let request = make_reset_positions_request();
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+        self.send(&RESET_POSITIONS_REQUEST).await
+    }
+
+    /// Sets the position of the given player displayed on the position tower.
+    pub async fn set_position(
+        &mut self,
+        player: ControllerId,
+        position: TowerPosition,
+    ) -> Result<(), Error> {
+        let request =
+            make_set_word_request(WordAddress::Position(player.address()), position.raw());
+        self.send(&request).await
+    }
+
+    /// Makes the control unit ignore the controllers whose bits are set in the given mask.
+    /// Bit 0 corresponds to the first controller.
+    pub async fn ignore_controllers(&mut self, mask: u8) -> Result<(), Error> {
+        let request = make_ignore_controllers_request(mask);
+        self.send(&request).await
+    }
+
+    /// Starts a race, or the countdown of a race, which is the same as pressing the enter button.
+    /// The startlight shows this built-in countdown, it can not be driven directly.
+    pub async fn start(&mut self) -> Result<(), Error> {
+        self.press_enter().await
     }
 
     /// Resets the clock for all players.
     pub async fn reset_clock(&mut self) -> Result<(), Error> {
-        let request = make_reset_clock_request();
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+        self.send(&RESET_CLOCK_REQUEST).await
     }
 
     /// Sets the speed level of the given player to the given value.
-    /// The speed value will be clamped to [0, 15].
-    pub async fn set_speed_level(&mut self, player: usize, speed: usize) -> Result<(), Error> {
-        let request = make_set_speed_level_request(player as u8, speed as u8);
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+    pub async fn set_speed_level(
+        &mut self,
+        player: ControllerId,
+        speed: SpeedLevel,
+    ) -> Result<(), Error> {
+        let request = make_set_word_request(WordAddress::Speed(player.address()), speed.value());
+        self.send(&request).await
     }
 
     /// Sets the brake level of the given player to the given value.
-    /// The brake value will be clamped to [0, 15].
-    pub async fn set_brake_level(&mut self, player: usize, brake: usize) -> Result<(), Error> {
-        let request = make_set_brake_level_request(player as u8, brake as u8);
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+    pub async fn set_brake_level(
+        &mut self,
+        player: ControllerId,
+        brake: BrakeLevel,
+    ) -> Result<(), Error> {
+        let request = make_set_word_request(WordAddress::Brake(player.address()), brake.value());
+        self.send(&request).await
     }
 
     /// Sets the fuel level of the given player to the given value.
-    /// The fuel value will be clamped to [0, 15].
-    pub async fn set_fuel_level(&mut self, player: usize, brake: usize) -> Result<(), Error> {
-        let request = make_set_fuel_level_request(player as u8, brake as u8);
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+    /// The protocol has no separate configuration word for the fuel a car starts the race with.
+    pub async fn set_fuel_level(
+        &mut self,
+        player: ControllerId,
+        fuel: FuelLevel,
+    ) -> Result<(), Error> {
+        let request = make_set_word_request(WordAddress::Fuel(player.address()), fuel.value());
+        self.send(&request).await
     }
 
-    async fn set_lap_low(&mut self, lap: usize) -> Result<(), Error> {
-        let request = make_set_lap_low_request((lap as u8) & 0x0F);
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+    /// Sets speed, brake and fuel level of the given player at once.
+    /// The requests are pipelined if the backend supports it, which is considerably faster
+    /// than setting each level on its own.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub async fn set_car_settings(
+        &mut self,
+        player: ControllerId,
+        settings: CarSettings,
+    ) -> Result<(), Error> {
+        self.send_batch(RequestBatch::new().set_car_settings(player, settings))
+            .await
     }
 
-    async fn set_lap_high(&mut self, lap: usize) -> Result<(), Error> {
-        let request = make_set_lap_high_request((lap as u8) >> 4);
-        let response = self.backend.request(&request, self.timeout).await?;
-        decode_result_to_error(decode_empty(&request, &response))
+    /// Applies the car settings and the fuel mode of the profile.
+    /// The fuel mode can only be switched with the fuel button, so the button is pressed until
+    /// the track reports the mode of the profile, which requires that no race is running.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn apply_profile(&mut self, profile: &CuProfile) -> Result<(), Error> {
+        let mut batch = RequestBatch::new();
+        for (player, settings) in profile.car_settings() {
+            batch.set_car_settings(player, settings);
+        }
+        self.send_batch(&batch).await?;
+
+        let Some(fuel_mode) = profile.fuel_mode else {
+            return Ok(());
+        };
+        let mut current = self.get_track_status().await?.fuel_mode();
+        // the button cycles through the modes, so each mode is reached with at most two presses
+        for _ in 0..2 {
+            if current == fuel_mode {
+                break;
+            }
+            self.press_fuel().await?;
+            current = self.get_track_status().await?.fuel_mode();
+        }
+
+        match current == fuel_mode {
+            true => Ok(()),
+            false => Err(Error::RuntimeError(format!(
+                "fuel mode is {:?} instead of {:?}, is a race running?",
+                current, fuel_mode
+            ))),
+        }
+    }
+
+    /// Sends all requests of the batch and checks that each of them was acknowledged.
+    /// The requests are pipelined if the backend supports it, with at most
+    /// [`MAX_IN_FLIGHT_REQUESTS`] awaiting their response at any time. Requests which were
+    /// sent before a failure are not rolled back.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub async fn send_batch(&mut self, batch: &RequestBatch) -> Result<(), Error> {
+        self.require_commands()?;
+        for chunk in batch.requests().chunks(MAX_IN_FLIGHT_REQUESTS) {
+            let requests: Vec<&[u8]> = chunk.iter().map(|request| request.as_slice()).collect();
+            let is_repeatable = requests.iter().all(|request| is_idempotent(request));
+            let mut attempt = 0;
+            let responses = loop {
+                let result = self
+                    .backend
+                    .request_batch(&requests, self.timeout)
+                    .await
+                    .map_err(|error| error.during(Operation::Request));
+                match result {
+                    Err(error)
+                        if error.is_transient() && is_repeatable && attempt < self.retries =>
+                    {
+                        attempt += 1;
+                        log::debug!("repeating batch after {}, attempt {}", error, attempt);
+                        self.backend.discard_stale_responses().await?;
+                    }
+                    result => break result?,
+                }
+            };
+            if self.keepalive.is_some() {
+                self.last_exchange = try_now(self.clock.as_ref());
+            }
+            if responses.len() != requests.len() {
+                return Err(Error::NoResponse);
+            }
+
+            // responses are matched by order, the acknowledgement carries the command character
+            for (request, response) in requests.iter().zip(responses.iter()) {
+                decode_acknowledgement(request, response, self.checksum_validation)
+                    .map_err(|reason| Error::invalid_response(response, reason))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_lap_low(&mut self, lap: u8) -> Result<(), Error> {
+        let request = make_set_word_request(WordAddress::LapLow, lap & 0x0F);
+        self.send(&request).await
+    }
+
+    async fn set_lap_high(&mut self, lap: u8) -> Result<(), Error> {
+        let request = make_set_word_request(WordAddress::LapHigh, lap >> 4);
+        self.send(&request).await
     }
 
     /// Sets the lap currently displayed lap by the position tower.
+    /// The lap is written as two nibbles, so laps above 255 are rejected as invalid argument.
     pub async fn set_lap(&mut self, lap: usize) -> Result<(), Error> {
+        let lap = u8::try_from(lap).map_err(|_| {
+            Error::InvalidArgument(format!("lap {} exceeds maximum of {}", lap, u8::MAX))
+        })?;
         self.set_lap_high(lap).await?;
         self.set_lap_low(lap).await?;
         Ok(())
     }
 }
+
+/// Builder which configures a [`ControlUnit`], created with [`ControlUnit::builder`].
+///
+/// ```no_run
+/// # fn example<T: carrlink::Backend>(backend: T) {
+/// use carrlink::ControlUnit;
+/// use std::time::Duration;
+///
+/// let control_unit = ControlUnit::builder(backend)
+///     .timeout(Duration::from_millis(500))
+///     .retries(2)
+///     .keepalive(Duration::from_secs(10))
+///     .build();
+/// # }
+/// ```
+pub struct ControlUnitBuilder<T: Backend> {
+    control_unit: ControlUnit<T>,
+}
+
+impl<T: Backend> ControlUnitBuilder<T> {
+    /// Sets the timeout which is used for control unit communication, the default is two seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.control_unit.set_timeout(timeout);
+        self
+    }
+
+    /// Sets how often requests which failed with a transient error, e.g. a timeout, are repeated.
+    /// The default is no repetition, see [`ControlUnit::set_retries`] for the repeated requests.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.control_unit.set_retries(retries);
+        self
+    }
+
+    /// Sets the idle duration after which [`ControlUnit::keep_alive`] pings the control unit.
+    pub fn keepalive(mut self, keepalive: Duration) -> Self {
+        self.control_unit.set_keepalive(Some(keepalive));
+        self
+    }
+
+    /// Sets the strictness which is applied when validating the checksums of responses.
+    pub fn checksum_validation(mut self, validation: ChecksumValidation) -> Self {
+        self.control_unit.set_checksum_validation(validation);
+        self
+    }
+
+    /// Sets the clock which timestamps received statuses, the default is the [`SystemClock`].
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.control_unit.set_clock(clock);
+        self
+    }
+
+    /// Creates the configured control unit.
+    pub fn build(self) -> ControlUnit<T> {
+        self.control_unit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Backend which times out the given number of requests before it answers them.
+    #[derive(Default)]
+    struct FlakyBackend {
+        failures: usize,
+        requests: Vec<Vec<u8>>,
+        discarded: usize,
+        is_connected: bool,
+        version_response: Option<&'static [u8]>,
+    }
+
+    impl FlakyBackend {
+        fn failing(failures: usize) -> FlakyBackend {
+            FlakyBackend {
+                failures,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Backend for FlakyBackend {
+        async fn connect(&mut self) -> crate::Result<()> {
+            self.is_connected = true;
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> crate::Result<()> {
+            self.is_connected = false;
+            Ok(())
+        }
+
+        async fn is_connected(&self) -> crate::Result<bool> {
+            Ok(self.is_connected)
+        }
+
+        async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+            self.requests.push(data.to_vec());
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(Error::TimedOut {
+                    operation: Operation::Request,
+                    timeout,
+                });
+            }
+
+            let response: &[u8] = match data[0] {
+                b'0' => self.version_response.unwrap_or(b"053372"),
+                command => &[command],
+            };
+            Ok(Frame::try_from(response).unwrap())
+        }
+
+        async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+            self.discarded += 1;
+            Ok(())
+        }
+    }
+
+    fn flaky_control_unit(failures: usize) -> ControlUnit<FlakyBackend> {
+        ControlUnit::builder(FlakyBackend::failing(failures))
+            .retries(2)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn does_not_repeat_button_presses() {
+        let mut control_unit = flaky_control_unit(1);
+        let result = control_unit.press_enter().await;
+
+        assert!(matches!(result, Err(Error::TimedOut { .. })));
+        assert_eq!(control_unit.backend().requests, [ENTER_REQUEST.to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn does_not_repeat_status_polls() {
+        let mut control_unit = flaky_control_unit(1);
+        let result = control_unit.get_status().await;
+
+        assert!(matches!(result, Err(Error::TimedOut { .. })));
+        assert_eq!(control_unit.backend().requests.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn repeats_word_writes_after_discarding_stale_responses() {
+        let mut control_unit = flaky_control_unit(1);
+        control_unit
+            .set_speed_level(ControllerId::ALL[1], SpeedLevel::new(8).unwrap())
+            .await
+            .unwrap();
+
+        let backend = control_unit.backend();
+        assert_eq!(backend.requests.len(), 2);
+        assert_eq!(backend.requests[0], backend.requests[1]);
+        assert_eq!(backend.discarded, 1);
+    }
+
+    #[tokio::test]
+    async fn repeats_version_requests() {
+        let mut control_unit = flaky_control_unit(1);
+
+        assert_eq!(control_unit.get_version().await.unwrap(), "5337");
+        assert_eq!(control_unit.backend().requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn repeats_batches_of_word_writes() {
+        let mut control_unit = flaky_control_unit(1);
+        let settings = CarSettings {
+            speed: SpeedLevel::MAX,
+            brake: BrakeLevel::MAX,
+            fuel: FuelLevel::FULL,
+        };
+        control_unit
+            .set_car_settings(ControllerId::ALL[0], settings)
+            .await
+            .unwrap();
+
+        // the first write timed out, then the complete batch was sent again
+        assert_eq!(control_unit.backend().requests.len(), 4);
+        assert_eq!(control_unit.backend().discarded, 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_configured_retries() {
+        let mut control_unit = flaky_control_unit(5);
+        let result = control_unit.get_version().await;
+
+        assert!(matches!(result, Err(Error::TimedOut { .. })));
+        assert_eq!(control_unit.backend().requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn writes_both_nibbles_of_the_lap() {
+        let mut control_unit = flaky_control_unit(0);
+        control_unit.set_lap(0x2A).await.unwrap();
+
+        assert_eq!(
+            control_unit.backend().requests,
+            [
+                make_set_word_request(WordAddress::LapHigh, 0x02).to_vec(),
+                make_set_word_request(WordAddress::LapLow, 0x0A).to_vec(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_laps_which_do_not_fit_the_tower() {
+        let mut control_unit = flaky_control_unit(0);
+        let result = control_unit.set_lap(256).await;
+
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+        assert!(control_unit.backend().requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn writes_the_position_of_a_player() {
+        let mut control_unit = flaky_control_unit(0);
+        control_unit
+            .set_position(ControllerId::ALL[2], TowerPosition::LAST)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            control_unit.backend().requests,
+            [make_set_word_request(WordAddress::Position(2), 8).to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn records_the_profile_when_connecting() {
+        let mut control_unit = flaky_control_unit(0);
+        control_unit.connect().await.unwrap();
+
+        assert!(control_unit.is_connected().await.unwrap());
+        assert_eq!(control_unit.profile().unwrap().firmware(), Some(5337));
+    }
+
+    #[tokio::test]
+    async fn rejects_commands_for_unknown_firmware() {
+        let backend = FlakyBackend {
+            version_response: Some(b"0A337>"),
+            ..Default::default()
+        };
+        let mut control_unit = ControlUnit::new(backend);
+        control_unit.connect().await.unwrap();
+        assert!(!control_unit.profile().unwrap().supports_commands());
+
+        let result = control_unit.press_enter().await;
+        assert!(matches!(result, Err(Error::NotSupported(_))));
+        let mut batch = RequestBatch::new();
+        batch.set_speed_level(ControllerId::ALL[0], SpeedLevel::MAX);
+        let result = control_unit.send_batch(&batch).await;
+        assert!(matches!(result, Err(Error::NotSupported(_))));
+
+        // only the version request reached the control unit, statuses are still polled
+        assert_eq!(control_unit.backend().requests, [b"0".to_vec()]);
+        control_unit.get_version().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn disconnects_if_the_version_is_not_answered() {
+        let mut control_unit = flaky_control_unit(5);
+        let result = control_unit.connect().await;
+
+        assert!(matches!(result, Err(Error::TimedOut { .. })));
+        assert!(!control_unit.is_connected().await.unwrap());
+        assert!(control_unit.profile().is_none());
+    }
+}
@@ -0,0 +1,50 @@
+//! Module which defines the identifier of a controller.
+
+use crate::bounded::bounded_type;
+use crate::MAX_CONTROLLER_COUNT;
+
+bounded_type! {
+    /// Identifier of a controller, i.e. of a player, as it is addressed by the control unit.
+    /// Identifiers are zero based and always in range [0, 7], even though the control unit displays
+    /// them one based.
+    #[derive(Default)]
+    pub struct ControllerId(usize) in [0, MAX_CONTROLLER_COUNT - 1], "controller";
+}
+
+impl ControllerId {
+    /// Identifiers of all controllers in ascending order.
+    pub const ALL: [ControllerId; MAX_CONTROLLER_COUNT] = [
+        ControllerId(0),
+        ControllerId(1),
+        ControllerId(2),
+        ControllerId(3),
+        ControllerId(4),
+        ControllerId(5),
+        ControllerId(6),
+        ControllerId(7),
+    ];
+
+    /// Returns the zero based index of the controller in range [0, 7].
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    /// Returns the index as it is written into the address field of a request.
+    #[cfg(feature = "std")]
+    pub(crate) fn address(&self) -> u8 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_indices_of_the_controllers() {
+        assert_eq!(ControllerId::new(0), Some(ControllerId::ALL[0]));
+        assert_eq!(ControllerId::new(7), Some(ControllerId::ALL[7]));
+        assert_eq!(ControllerId::new(8), None);
+        assert_eq!(usize::from(ControllerId::ALL[7]), 7);
+    }
+}
@@ -0,0 +1,157 @@
+//! Module which defines configuration profiles of a control unit, e.g. a slow setup for kids and
+//! a full speed setup for a race night, which are applied with
+//! [`apply_profile`](crate::ControlUnit::apply_profile).
+
+use crate::{CarSettings, ControllerId, FuelMode};
+
+/// Controller which is occupied by the pace car.
+pub const PACE_CAR_CONTROLLER: ControllerId = ControllerId::ALL[7];
+
+/// Settings of the car of a single controller within a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CarProfile {
+    /// Controller whose car is configured.
+    pub controller: ControllerId,
+
+    /// Speed, brake and fuel level of the car.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub settings: CarSettings,
+}
+
+/// Named set of settings which configures the control unit in one call.
+/// Settings which are not part of the profile are left unchanged when it is applied.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CuProfile {
+    /// Name of the profile, e.g. "kids mode".
+    pub name: String,
+
+    /// Fuel mode of the track.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub fuel_mode: Option<FuelMode>,
+
+    /// Number of laps of a race. The control unit does not end a race by itself, so the limit is
+    /// only kept for applications which end the race once the leader completed it.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub lap_limit: Option<usize>,
+
+    /// Settings of the pace car, which occupies the [`PACE_CAR_CONTROLLER`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub pace_car: Option<CarSettings>,
+
+    /// Settings of the cars of the controllers.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cars: Vec<CarProfile>,
+}
+
+impl CuProfile {
+    /// Creates an empty profile with the given name.
+    pub fn new<S: Into<String>>(name: S) -> CuProfile {
+        CuProfile {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the settings of the car of a controller, replacing previous settings of that
+    /// controller.
+    pub fn with_car(mut self, controller: ControllerId, settings: CarSettings) -> Self {
+        self.cars.retain(|car| car.controller != controller);
+        self.cars.push(CarProfile {
+            controller,
+            settings,
+        });
+        self
+    }
+
+    /// Sets the fuel mode of the track.
+    pub fn with_fuel_mode(mut self, fuel_mode: FuelMode) -> Self {
+        self.fuel_mode = Some(fuel_mode);
+        self
+    }
+
+    /// Sets the number of laps of a race.
+    pub fn with_lap_limit(mut self, lap_limit: usize) -> Self {
+        self.lap_limit = Some(lap_limit);
+        self
+    }
+
+    /// Sets the settings of the pace car.
+    pub fn with_pace_car(mut self, settings: CarSettings) -> Self {
+        self.pace_car = Some(settings);
+        self
+    }
+
+    /// Returns the settings of all cars including the pace car, which are written to the
+    /// control unit.
+    pub fn car_settings(&self) -> impl Iterator<Item = (ControllerId, CarSettings)> + '_ {
+        self.cars
+            .iter()
+            .map(|car| (car.controller, car.settings))
+            .chain(
+                self.pace_car
+                    .map(|settings| (PACE_CAR_CONTROLLER, settings)),
+            )
+    }
+
+    /// Saves the profile to the given path, as TOML if the path has the extension `toml` and as
+    /// JSON otherwise. TOML requires the `toml` feature.
+    #[cfg(feature = "serde")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let data = match is_toml(path) {
+            #[cfg(feature = "toml")]
+            true => toml::to_string_pretty(self)
+                .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?
+                .into_bytes(),
+            #[cfg(not(feature = "toml"))]
+            true => return Err(toml_unsupported()),
+            false => serde_json::to_vec_pretty(self)?,
+        };
+        std::fs::write(path, data)
+    }
+
+    /// Loads a profile which was saved with [`save`](CuProfile::save), the format is chosen by
+    /// the extension of the path just like when saving.
+    #[cfg(feature = "serde")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<CuProfile> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        match is_toml(path) {
+            #[cfg(feature = "toml")]
+            true => {
+                let data = std::str::from_utf8(&data)
+                    .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+                toml::from_str(data)
+                    .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+            }
+            #[cfg(not(feature = "toml"))]
+            true => Err(toml_unsupported()),
+            false => Ok(serde_json::from_slice(&data)?),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn is_toml(path: &std::path::Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml")
+}
+
+#[cfg(all(feature = "serde", not(feature = "toml")))]
+fn toml_unsupported() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "profiles are only saved as TOML with the toml feature",
+    )
+}
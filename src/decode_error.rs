@@ -0,0 +1,47 @@
+//! Defines the reasons why a response of the control unit could not be decoded.
+//! The type is part of the protocol core and therefore available without the `std` feature.
+
+/// Enumeration of reasons why a response of the control unit could not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    /// The checksum of the response does not match its content.
+    /// This typically indicates corruption on the wire.
+    #[error("checksum does not match the content")]
+    BadChecksum,
+
+    /// The response has a length which is not expected for its type.
+    #[error("unexpected length")]
+    WrongLength,
+
+    /// The response does not belong to the request which was sent.
+    #[error("response does not belong to the request")]
+    UnknownCommand,
+
+    /// A field of the response contains a value which is not supported.
+    #[error("unsupported field value")]
+    BadField,
+}
+
+impl DecodeError {
+    /// Returns the stable machine readable name of the reason, e.g. for metrics labels.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DecodeError::BadChecksum => "bad_checksum",
+            DecodeError::WrongLength => "wrong_length",
+            DecodeError::UnknownCommand => "unknown_command",
+            DecodeError::BadField => "bad_field",
+        }
+    }
+
+    /// Restores the reason from its [`name`](DecodeError::name).
+    #[cfg(feature = "remote")]
+    pub(crate) fn from_name(name: &str) -> Option<DecodeError> {
+        match name {
+            "bad_checksum" => Some(DecodeError::BadChecksum),
+            "wrong_length" => Some(DecodeError::WrongLength),
+            "unknown_command" => Some(DecodeError::UnknownCommand),
+            "bad_field" => Some(DecodeError::BadField),
+            _ => None,
+        }
+    }
+}
@@ -1,26 +1,363 @@
 //! Defines the general error type of carrlink.
 
+use crate::DecodeError;
 use std::error;
 use std::fmt;
+use std::time::Duration;
 
 /// Enumeration of error cases.
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
+    /// Access to the device was denied by the operating system.
+    #[error("permission denied to access the device")]
     PermissionDenied,
+
+    /// The control unit could not be found.
+    #[error("control unit was not found")]
     DeviceNotFound,
+
+    /// The operation requires a connection to the control unit.
+    #[error("not connected to the control unit")]
     NotConnected,
+
+    /// The operation is not supported by the backend or the control unit.
+    #[error("operation is not supported: {0}")]
     NotSupported(String),
-    TimedOut,
+
+    /// An argument is out of its valid range.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// The control unit did not answer in time.
+    #[error("timed out after {timeout:?} while {operation}")]
+    TimedOut {
+        /// Operation which timed out.
+        operation: Operation,
+
+        /// Timeout which was configured for the operation.
+        timeout: Duration,
+    },
+
+    /// The backend failed at runtime.
+    #[error("runtime error: {0}")]
     RuntimeError(String),
-    InvalidResponse,
+
+    /// The control unit sent a response which could not be decoded.
+    #[error("invalid response {raw:02X?} from the control unit")]
+    InvalidResponse {
+        /// Raw response as it was returned by the backend.
+        raw: Vec<u8>,
+
+        /// Reason why the response could not be decoded.
+        #[source]
+        reason: DecodeError,
+    },
+
+    /// The checksum of a response of the control unit does not match its content.
+    #[error("checksum mismatch in response {raw:02X?} from the control unit")]
+    ChecksumMismatch {
+        /// Raw response as it was returned by the backend.
+        raw: Vec<u8>,
+    },
+
+    /// The control unit did not send a response.
+    #[error("no response from the control unit")]
     NoResponse,
-    Other(Box<dyn error::Error>),
+
+    /// The bluetooth stack reported an error.
+    #[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+    #[error("bluetooth error")]
+    Bluetooth(#[source] btleplug::Error),
+
+    /// Any other error of an underlying library.
+    #[error("backend error")]
+    Other(#[source] Box<dyn error::Error + Send + Sync>),
 }
 
-impl fmt::Display for Error {
+impl Error {
+    /// Creates the error for a response which could not be decoded for the given reason.
+    pub(crate) fn invalid_response(raw: &[u8], reason: DecodeError) -> Error {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(?raw, %reason, "failed to decode response");
+        #[cfg(feature = "metrics")]
+        metrics::counter!("carrlink_decode_failures_total", "reason" => reason.name()).increment(1);
+
+        match reason {
+            DecodeError::BadChecksum => Error::ChecksumMismatch { raw: raw.to_vec() },
+            reason => Error::InvalidResponse {
+                raw: raw.to_vec(),
+                reason,
+            },
+        }
+    }
+
+    /// Attributes a timeout to the given operation, other errors are returned unchanged.
+    pub(crate) fn during(self, operation: Operation) -> Error {
+        match self {
+            Error::TimedOut { timeout, .. } => Error::TimedOut { operation, timeout },
+            error => error,
+        }
+    }
+
+    /// Returns the stable numeric code of the error.
+    /// Codes are never reused or changed, new variants get new codes.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::PermissionDenied => 1,
+            Error::DeviceNotFound => 2,
+            Error::NotConnected => 3,
+            Error::NotSupported(_) => 4,
+            Error::InvalidArgument(_) => 5,
+            Error::TimedOut { .. } => 6,
+            Error::RuntimeError(_) => 7,
+            Error::InvalidResponse { .. } => 8,
+            Error::NoResponse => 9,
+            Error::Other(_) => 10,
+            #[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+            Error::Bluetooth(_) => 11,
+            Error::ChecksumMismatch { .. } => 12,
+        }
+    }
+
+    /// Restores an error from its code and message, e.g. as transferred over the network.
+    /// Errors which carry more than the detail of [`detail`](Error::detail) can not be restored
+    /// and become a runtime error, as do details which can not be parsed.
+    #[cfg(feature = "remote")]
+    pub(crate) fn from_code(code: u32, message: String) -> Error {
+        let restored = match code {
+            1 => Some(Error::PermissionDenied),
+            2 => Some(Error::DeviceNotFound),
+            3 => Some(Error::NotConnected),
+            4 => Some(Error::NotSupported(message.clone())),
+            5 => Some(Error::InvalidArgument(message.clone())),
+            6 => message.parse().ok().map(|milliseconds| Error::TimedOut {
+                operation: Operation::Request,
+                timeout: Duration::from_millis(milliseconds),
+            }),
+            8 => message.split_once(' ').and_then(|(reason, raw)| {
+                Some(Error::InvalidResponse {
+                    raw: decode_hex(raw)?,
+                    reason: DecodeError::from_name(reason)?,
+                })
+            }),
+            9 => Some(Error::NoResponse),
+            12 => decode_hex(&message).map(|raw| Error::ChecksumMismatch { raw }),
+            _ => None,
+        };
+        restored.unwrap_or(Error::RuntimeError(message))
+    }
+
+    /// Returns the message which is needed to restore the error with [`from_code`](Error::from_code).
+    #[cfg(feature = "remote")]
+    pub(crate) fn detail(&self) -> String {
+        match self {
+            Error::NotSupported(message)
+            | Error::InvalidArgument(message)
+            | Error::RuntimeError(message) => message.clone(),
+            Error::TimedOut { timeout, .. } => timeout.as_millis().to_string(),
+            Error::InvalidResponse { raw, reason } => {
+                format!("{} {}", reason.name(), encode_hex(raw))
+            }
+            Error::ChecksumMismatch { raw } => encode_hex(raw),
+            error => error.to_string(),
+        }
+    }
+
+    /// Returns the stable machine readable name of the error, e.g. for structured logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::PermissionDenied => "permission_denied",
+            Error::DeviceNotFound => "device_not_found",
+            Error::NotConnected => "not_connected",
+            Error::NotSupported(_) => "not_supported",
+            Error::InvalidArgument(_) => "invalid_argument",
+            Error::TimedOut { .. } => "timed_out",
+            Error::RuntimeError(_) => "runtime_error",
+            Error::InvalidResponse { .. } => "invalid_response",
+            Error::NoResponse => "no_response",
+            Error::Other(_) => "other",
+            #[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+            Error::Bluetooth(_) => "bluetooth",
+            Error::ChecksumMismatch { .. } => "checksum_mismatch",
+        }
+    }
+
+    /// Determines if the error is temporary, i.e. repeating the operation might succeed.
+    /// This is the case for timeouts and responses which got corrupted on the wire.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Error::TimedOut { .. }
+                | Error::NoResponse
+                | Error::InvalidResponse { .. }
+                | Error::ChecksumMismatch { .. }
+        )
+    }
+
+    /// Determines if the error is permanent, i.e. repeating the operation will fail again.
+    /// Errors which are neither transient nor fatal, e.g. a lost connection, may be resolved
+    /// by reconnecting to the control unit.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Error::PermissionDenied | Error::NotSupported(_) | Error::InvalidArgument(_)
+        )
+    }
+}
+
+/// Encodes raw bytes as hexadecimal digits, two per byte.
+#[cfg(feature = "remote")]
+fn encode_hex(raw: &[u8]) -> String {
+    raw.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Decodes bytes which were encoded with [`encode_hex`].
+#[cfg(feature = "remote")]
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => {
+                let high = char::from(*high).to_digit(16)?;
+                let low = char::from(*low).to_digit(16)?;
+                Some((high << 4 | low) as u8)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Operations of the control unit communication which can time out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Connecting to the control unit.
+    Connect,
+
+    /// Searching for a control unit.
+    Discover,
+
+    /// Sending a request to the control unit.
+    Request,
+
+    /// Requesting the status of the control unit.
+    Status,
+
+    /// Requesting the firmware version of the control unit.
+    Version,
+
+    /// Polling the control unit until it reports an awaited status.
+    WaitForStatus,
+}
+
+impl Operation {
+    /// Returns the stable machine readable name of the operation, e.g. for metrics labels.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Connect => "connect",
+            Operation::Discover => "discover",
+            Operation::Request => "request",
+            Operation::Status => "status",
+            Operation::Version => "version",
+            Operation::WaitForStatus => "wait_for_status",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{:?}", self)
+        let description = match self {
+            Operation::Connect => "connecting to the control unit",
+            Operation::Discover => "searching for a control unit",
+            Operation::Request => "waiting for the response of the control unit",
+            Operation::Status => "requesting the status of the control unit",
+            Operation::Version => "requesting the firmware version of the control unit",
+            Operation::WaitForStatus => "waiting for a status of the control unit",
+        };
+        formatter.write_str(description)
     }
 }
 
-impl error::Error for Error {}
+#[cfg(all(test, feature = "remote"))]
+mod tests {
+    use super::*;
+
+    fn round_trip(error: &Error) -> Error {
+        Error::from_code(error.code(), error.detail())
+    }
+
+    #[test]
+    fn restores_errors_from_their_code() {
+        let errors = [
+            Error::PermissionDenied,
+            Error::DeviceNotFound,
+            Error::NotConnected,
+            Error::NotSupported("binary mode".to_owned()),
+            Error::InvalidArgument("speed 16".to_owned()),
+            Error::RuntimeError("socket closed".to_owned()),
+            Error::NoResponse,
+        ];
+        for error in errors {
+            let restored = round_trip(&error);
+            assert_eq!(restored.code(), error.code());
+            assert_eq!(restored.to_string(), error.to_string());
+        }
+    }
+
+    #[test]
+    fn restores_timeouts_as_transient() {
+        let error = Error::TimedOut {
+            operation: Operation::Status,
+            timeout: Duration::from_millis(1500),
+        };
+        let restored = round_trip(&error);
+        assert!(restored.is_transient());
+        assert!(matches!(
+            restored,
+            Error::TimedOut {
+                operation: Operation::Request,
+                timeout,
+            } if timeout == Duration::from_millis(1500)
+        ));
+    }
+
+    #[test]
+    fn restores_invalid_responses_with_raw_bytes() {
+        let error = Error::InvalidResponse {
+            raw: vec![b'?', b':', 0x00, 0xFF],
+            reason: DecodeError::WrongLength,
+        };
+        let restored = round_trip(&error);
+        assert!(restored.is_transient());
+        assert!(matches!(
+            restored,
+            Error::InvalidResponse {
+                ref raw,
+                reason: DecodeError::WrongLength,
+            } if raw == &[b'?', b':', 0x00, 0xFF]
+        ));
+    }
+
+    #[test]
+    fn restores_checksum_mismatches_with_raw_bytes() {
+        let error = Error::ChecksumMismatch {
+            raw: b"?1000000001$".to_vec(),
+        };
+        let restored = round_trip(&error);
+        assert!(restored.is_transient());
+        assert!(matches!(
+            restored,
+            Error::ChecksumMismatch { ref raw } if raw == b"?1000000001$"
+        ));
+    }
+
+    #[test]
+    fn keeps_unparseable_details_as_runtime_error() {
+        let restored = Error::from_code(6, "soon".to_owned());
+        assert!(matches!(restored, Error::RuntimeError(ref message) if message == "soon"));
+
+        let restored = Error::from_code(12, "0".to_owned());
+        assert!(matches!(restored, Error::RuntimeError(_)));
+    }
+}
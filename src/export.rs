@@ -0,0 +1,213 @@
+//! Module which exports the laps and results of a race session as CSV.
+
+use crate::{Lap, RaceSession, Standing};
+use std::io;
+use std::time::Duration;
+
+/// Columns which can be written for every lap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LapColumn {
+    /// Identifier of the controller which completed the lap.
+    Controller,
+
+    /// Number of the lap.
+    Lap,
+
+    /// Time of the lap in seconds.
+    LapTime,
+
+    /// Sector times of the lap in seconds, separated by semicolons.
+    Splits,
+
+    /// Control unit timer in milliseconds when the lap was completed.
+    Timestamp,
+
+    /// Whether the lap counts.
+    Valid,
+}
+
+/// Columns which can be written for the final results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResultColumn {
+    /// Position in the final standings.
+    Position,
+
+    /// Identifier of the controller.
+    Controller,
+
+    /// Number of completed laps.
+    Laps,
+
+    /// Sum of all lap times in seconds.
+    TotalTime,
+
+    /// Fastest valid lap in seconds.
+    BestLap,
+
+    /// Last completed lap in seconds.
+    LastLap,
+}
+
+impl LapColumn {
+    /// All columns in their default order.
+    pub const ALL: [LapColumn; 6] = [
+        LapColumn::Controller,
+        LapColumn::Lap,
+        LapColumn::LapTime,
+        LapColumn::Splits,
+        LapColumn::Timestamp,
+        LapColumn::Valid,
+    ];
+
+    fn header(&self) -> &'static str {
+        match self {
+            LapColumn::Controller => "controller",
+            LapColumn::Lap => "lap",
+            LapColumn::LapTime => "lap_time",
+            LapColumn::Splits => "splits",
+            LapColumn::Timestamp => "timestamp",
+            LapColumn::Valid => "valid",
+        }
+    }
+
+    fn value(&self, lap: &Lap) -> String {
+        match self {
+            LapColumn::Controller => lap.controller.index().to_string(),
+            LapColumn::Lap => lap.number.to_string(),
+            LapColumn::LapTime => format_seconds(lap.lap_time),
+            LapColumn::Splits => lap
+                .splits
+                .iter()
+                .map(|split| format_seconds(*split))
+                .collect::<Vec<_>>()
+                .join(";"),
+            LapColumn::Timestamp => lap.time.raw().to_string(),
+            LapColumn::Valid => lap.is_valid.to_string(),
+        }
+    }
+}
+
+impl ResultColumn {
+    /// All columns in their default order.
+    pub const ALL: [ResultColumn; 6] = [
+        ResultColumn::Position,
+        ResultColumn::Controller,
+        ResultColumn::Laps,
+        ResultColumn::TotalTime,
+        ResultColumn::BestLap,
+        ResultColumn::LastLap,
+    ];
+
+    fn header(&self) -> &'static str {
+        match self {
+            ResultColumn::Position => "position",
+            ResultColumn::Controller => "controller",
+            ResultColumn::Laps => "laps",
+            ResultColumn::TotalTime => "total_time",
+            ResultColumn::BestLap => "best_lap",
+            ResultColumn::LastLap => "last_lap",
+        }
+    }
+
+    fn value(&self, standing: &Standing) -> String {
+        match self {
+            ResultColumn::Position => standing.position.to_string(),
+            ResultColumn::Controller => standing.controller.index().to_string(),
+            ResultColumn::Laps => standing.laps.to_string(),
+            ResultColumn::TotalTime => format_seconds(standing.total_time),
+            ResultColumn::BestLap => standing.best_lap.map(format_seconds).unwrap_or_default(),
+            ResultColumn::LastLap => standing.last_lap.map(format_seconds).unwrap_or_default(),
+        }
+    }
+}
+
+/// Formats the duration as seconds with millisecond precision, e.g. "12.345".
+fn format_seconds(duration: Duration) -> String {
+    format!("{}.{:03}", duration.as_secs(), duration.subsec_millis())
+}
+
+/// Writes one CSV record, none of the values contain separators or quotes.
+fn write_record<W: io::Write>(writer: &mut W, values: &[String]) -> io::Result<()> {
+    writeln!(writer, "{}", values.join(","))
+}
+
+/// Exporter which writes the laps and the final results of a [`RaceSession`] as CSV.
+#[derive(Debug, Clone)]
+pub struct CsvExport {
+    lap_columns: Vec<LapColumn>,
+    result_columns: Vec<ResultColumn>,
+}
+
+impl Default for CsvExport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsvExport {
+    /// Creates an exporter which writes all columns.
+    pub fn new() -> CsvExport {
+        CsvExport {
+            lap_columns: LapColumn::ALL.to_vec(),
+            result_columns: ResultColumn::ALL.to_vec(),
+        }
+    }
+
+    /// Sets the columns and their order which are written for every lap.
+    pub fn with_lap_columns(mut self, columns: &[LapColumn]) -> Self {
+        self.lap_columns = columns.to_vec();
+        self
+    }
+
+    /// Sets the columns and their order which are written for the final results.
+    pub fn with_result_columns(mut self, columns: &[ResultColumn]) -> Self {
+        self.result_columns = columns.to_vec();
+        self
+    }
+
+    /// Writes a header and one record for every lap of the session in the order the laps were completed.
+    pub fn write_laps<W: io::Write>(&self, session: &RaceSession, mut writer: W) -> io::Result<()> {
+        let header: Vec<String> = self
+            .lap_columns
+            .iter()
+            .map(|column| column.header().to_owned())
+            .collect();
+        write_record(&mut writer, &header)?;
+
+        for lap in session.laps() {
+            let values: Vec<String> = self
+                .lap_columns
+                .iter()
+                .map(|column| column.value(lap))
+                .collect();
+            write_record(&mut writer, &values)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a header and one record for every controller in the order of the standings.
+    pub fn write_results<W: io::Write>(
+        &self,
+        session: &RaceSession,
+        mut writer: W,
+    ) -> io::Result<()> {
+        let header: Vec<String> = self
+            .result_columns
+            .iter()
+            .map(|column| column.header().to_owned())
+            .collect();
+        write_record(&mut writer, &header)?;
+
+        for standing in session.standings() {
+            let values: Vec<String> = self
+                .result_columns
+                .iter()
+                .map(|column| column.value(&standing))
+                .collect();
+            write_record(&mut writer, &values)?;
+        }
+
+        Ok(())
+    }
+}
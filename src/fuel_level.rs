@@ -0,0 +1,67 @@
+//! Module which defines the fuel level of a car.
+
+use crate::bounded::bounded_type;
+
+bounded_type! {
+    /// Fuel level of a car as it is managed by the control unit.
+    /// The tank of a car is divided into 15 steps, the level is always in range [0, 15].
+    #[derive(Default)]
+    pub struct FuelLevel(u8) in [0, 15], "fuel level";
+}
+
+impl FuelLevel {
+    /// Fuel level of an empty tank.
+    pub const EMPTY: FuelLevel = FuelLevel(0);
+
+    /// Fuel level of a full tank.
+    pub const FULL: FuelLevel = FuelLevel(15);
+
+    /// Creates a fuel level from a nibble transmitted by the control unit.
+    pub(crate) const fn from_nibble(value: u8) -> FuelLevel {
+        FuelLevel(value & 0x0F)
+    }
+
+    /// Returns the raw fuel level in range [0, 15].
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns the fuel level as percentage of a full tank in range [0, 100].
+    pub fn as_percent(&self) -> f32 {
+        (self.0 as f32) * 100.0 / (Self::FULL.0 as f32)
+    }
+
+    /// Determines if the tank is empty.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::EMPTY
+    }
+
+    /// Determines if the tank is full.
+    pub fn is_full(&self) -> bool {
+        *self == Self::FULL
+    }
+
+    /// Adds the given number of steps, saturating at a full tank.
+    pub fn saturating_add(self, steps: u8) -> FuelLevel {
+        FuelLevel(self.0.saturating_add(steps).min(Self::FULL.0))
+    }
+
+    /// Subtracts the given number of steps, saturating at an empty tank.
+    pub fn saturating_sub(self, steps: u8) -> FuelLevel {
+        FuelLevel(self.0.saturating_sub(steps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_levels_up_to_a_full_tank() {
+        assert_eq!(FuelLevel::new(0), Some(FuelLevel::EMPTY));
+        assert_eq!(FuelLevel::new(15), Some(FuelLevel::FULL));
+        assert_eq!(FuelLevel::new(16), None);
+        assert_eq!(FuelLevel::FULL.saturating_add(1), FuelLevel::FULL);
+        assert_eq!(FuelLevel::EMPTY.saturating_sub(1), FuelLevel::EMPTY);
+    }
+}
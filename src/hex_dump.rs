@@ -0,0 +1,121 @@
+//! Module which implements a backend decorator logging every frame on the wire.
+
+use crate::{Backend, Frame};
+use async_trait::async_trait;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+/// Target of the log records written by [`HexDumpBackend`].
+pub const HEX_DUMP_LOG_TARGET: &str = "carrlink::wire";
+
+/// Backend which wraps another backend and logs every outgoing and incoming frame as hex dump.
+///
+/// Frames are logged at debug level with the target [`HEX_DUMP_LOG_TARGET`], so they can be
+/// enabled on their own, e.g. with `RUST_LOG=carrlink::wire=debug` for `env_logger`.
+/// Every record contains the direction and the time elapsed since the backend was created.
+pub struct HexDumpBackend<T: Backend> {
+    backend: T,
+    created_at: Instant,
+}
+
+impl<T: Backend> HexDumpBackend<T> {
+    /// Wraps the given backend.
+    pub fn new(backend: T) -> HexDumpBackend<T> {
+        HexDumpBackend {
+            backend,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Returns the wrapped backend.
+    pub fn into_inner(self) -> T {
+        self.backend
+    }
+
+    fn dump(&self, direction: &str, data: &[u8]) {
+        log::debug!(
+            target: HEX_DUMP_LOG_TARGET,
+            "{:>12.3?} {} {}",
+            self.created_at.elapsed(),
+            direction,
+            format_frame(data)
+        );
+    }
+
+    fn dump_error(&self, error: &crate::Error) {
+        log::debug!(
+            target: HEX_DUMP_LOG_TARGET,
+            "{:>12.3?} RX {}",
+            self.created_at.elapsed(),
+            error
+        );
+    }
+}
+
+/// Formats the frame as hex bytes followed by their printable characters, e.g. "3F 31 |?1|".
+fn format_frame(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len() * 4 + 2);
+    for byte in data {
+        let _ = write!(result, "{:02X} ", byte);
+    }
+
+    result.push('|');
+    result.extend(data.iter().map(|byte| {
+        if byte.is_ascii_graphic() || *byte == b' ' {
+            *byte as char
+        } else {
+            '.'
+        }
+    }));
+    result.push('|');
+    result
+}
+
+#[async_trait]
+impl<T: Backend + Sync> Backend for HexDumpBackend<T> {
+    async fn connect(&mut self) -> crate::Result<()> {
+        self.backend.connect().await
+    }
+
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        self.backend.disconnect().await
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        self.backend.is_connected().await
+    }
+
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        self.dump("TX", data);
+        let response = self.backend.request(data, timeout).await;
+        match &response {
+            Ok(response) => self.dump("RX", response),
+            Err(error) => self.dump_error(error),
+        }
+        response
+    }
+
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        for request in requests {
+            self.dump("TX", request);
+        }
+        let responses = self.backend.request_batch(requests, timeout).await;
+        match &responses {
+            Ok(responses) => {
+                for response in responses {
+                    self.dump("RX", response);
+                }
+            }
+            Err(error) => self.dump_error(error),
+        }
+        responses
+    }
+
+    async fn discard_stale_responses(&mut self) -> crate::Result<()> {
+        self.backend.discard_stale_responses().await
+    }
+}
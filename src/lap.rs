@@ -0,0 +1,82 @@
+//! Module which defines the record of a completed lap.
+
+use crate::{ControllerId, LapTime};
+use std::time::{Duration, Instant};
+
+/// Record of a lap which was completed by a controller.
+/// Laps are produced by the [`RaceSession`](crate::RaceSession) from consecutive lap statuses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lap {
+    /// Identifier of the controller which completed the lap.
+    pub controller: ControllerId,
+
+    /// Number of the lap, the first completed lap has number 1.
+    pub number: usize,
+
+    /// Time which was needed for the lap.
+    pub lap_time: Duration,
+
+    /// Times which were needed for the sectors of the lap.
+    /// Is empty if no check lanes are installed on the track.
+    pub splits: Vec<Duration>,
+
+    /// Control unit timer when the controller crossed the finish line.
+    pub time: LapTime,
+
+    /// Point in time when the status completing the lap was received from the control unit, or
+    /// when the session recorded the lap if the status carries no reception time.
+    /// With the `serde` feature it is serialized as wall clock time in milliseconds since the
+    /// unix epoch, so it keeps its meaning across restarts of the application.
+    #[cfg_attr(feature = "serde", serde(default, with = "wall_clock"))]
+    pub received_at: Option<Instant>,
+
+    /// Determines if the lap counts, e.g. laps which are faster than the minimum lap time do not count.
+    pub is_valid: bool,
+}
+
+/// Converts points in time into wall clock time and back, relating both clocks at the moment of
+/// the conversion. Points in time which can not be represented on the other clock become none.
+#[cfg(feature = "serde")]
+mod wall_clock {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(
+        received_at: &Option<Instant>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        received_at.and_then(to_unix_millis).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Instant>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.and_then(from_unix_millis))
+    }
+
+    fn to_unix_millis(instant: Instant) -> Option<u64> {
+        let now = Instant::now();
+        let wall_clock = SystemTime::now();
+        let wall_clock = match instant.checked_duration_since(now) {
+            Some(ahead) => wall_clock.checked_add(ahead)?,
+            None => wall_clock.checked_sub(now.duration_since(instant))?,
+        };
+        u64::try_from(wall_clock.duration_since(UNIX_EPOCH).ok()?.as_millis()).ok()
+    }
+
+    fn from_unix_millis(milliseconds: u64) -> Option<Instant> {
+        // browsers provide no clock via std, points in time are never set there
+        if cfg!(target_arch = "wasm32") {
+            return None;
+        }
+
+        let wall_clock = UNIX_EPOCH.checked_add(Duration::from_millis(milliseconds))?;
+        let now = SystemTime::now();
+        let instant = Instant::now();
+        match wall_clock.duration_since(now) {
+            Ok(ahead) => instant.checked_add(ahead),
+            Err(behind) => instant.checked_sub(behind.duration()),
+        }
+    }
+}
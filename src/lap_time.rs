@@ -1,9 +1,19 @@
-use std::{
+#[cfg(feature = "std")]
+use crate::Error;
+use core::{
+    fmt,
     ops::{Add, Sub},
     time::Duration,
 };
+#[cfg(feature = "std")]
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy)]
+/// Time of the control unit timer in milliseconds.
+///
+/// With the `serde` feature the time is serialized as milliseconds and deserialized from either
+/// milliseconds or a string as accepted by [`FromStr`]. Use [`lap_time_text`](crate::lap_time_text)
+/// to serialize it as "m:ss.mmm" instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct LapTime {
     milliseconds: u32,
 }
@@ -12,15 +22,217 @@ impl LapTime {
     pub fn from_millis(milliseconds: u32) -> Self {
         Self { milliseconds }
     }
+
+    /// Returns the time in milliseconds.
+    pub fn as_millis(&self) -> u32 {
+        self.milliseconds
+    }
+
+    /// Returns the time as duration since the start of the control unit timer.
+    pub fn as_duration(&self) -> Duration {
+        Duration::from_millis(self.milliseconds as u64)
+    }
+
+    /// Returns the raw value of the control unit timer, which counts milliseconds.
+    pub fn raw(&self) -> u32 {
+        self.milliseconds
+    }
+
+    /// Adds the duration to the time.
+    /// Returns none if the result would exceed the maximum timer value.
+    pub fn checked_add(self, duration: Duration) -> Option<LapTime> {
+        let milliseconds = u32::try_from(duration.as_millis()).ok()?;
+        Some(Self::from_millis(
+            self.milliseconds.checked_add(milliseconds)?,
+        ))
+    }
+
+    /// Subtracts the duration from the time.
+    /// Returns none if the result would be negative.
+    pub fn checked_sub_duration(self, duration: Duration) -> Option<LapTime> {
+        let milliseconds = u32::try_from(duration.as_millis()).ok()?;
+        Some(Self::from_millis(
+            self.milliseconds.checked_sub(milliseconds)?,
+        ))
+    }
+
+    /// Computes the duration since the given earlier time.
+    /// Returns none if the given time is later, e.g. due to out of order statuses.
+    pub fn checked_sub(self, earlier: LapTime) -> Option<Duration> {
+        let delta = self.milliseconds.checked_sub(earlier.milliseconds)?;
+        Some(Duration::from_millis(delta as u64))
+    }
+
+    /// Computes the duration since the given earlier time.
+    /// Returns zero if the given time is later, e.g. due to out of order statuses.
+    pub fn saturating_sub(self, earlier: LapTime) -> Duration {
+        self.checked_sub(earlier).unwrap_or(Duration::ZERO)
+    }
+
+    /// Computes the duration since the given earlier time, assuming the control unit timer
+    /// wrapped around at most once in between.
+    /// Use this for consecutive times of the same session, where the timer only ever moves forward.
+    pub fn wrapping_delta(self, earlier: LapTime) -> Duration {
+        let delta = self.milliseconds.wrapping_sub(earlier.milliseconds);
+        Duration::from_millis(delta as u64)
+    }
+
+    /// Formats the difference to the given reference time as signed seconds, e.g. "+1.234" or "-0.050".
+    #[cfg(feature = "std")]
+    pub fn format_delta(&self, reference: LapTime) -> String {
+        let (sign, delta) = if self.milliseconds >= reference.milliseconds {
+            ('+', self.milliseconds - reference.milliseconds)
+        } else {
+            ('-', reference.milliseconds - self.milliseconds)
+        };
+
+        format!("{}{}.{:03}", sign, delta / 1000, delta % 1000)
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryFrom<Duration> for LapTime {
+    type Error = Error;
+
+    /// Converts the duration, failing if it exceeds the range of the control unit timer.
+    fn try_from(value: Duration) -> Result<Self, Self::Error> {
+        match u32::try_from(value.as_millis()) {
+            Ok(milliseconds) => Ok(Self::from_millis(milliseconds)),
+            Err(_) => Err(Error::InvalidArgument(format!(
+                "duration of {:?} exceeds the range of the control unit timer",
+                value
+            ))),
+        }
+    }
+}
+
+impl From<LapTime> for Duration {
+    fn from(value: LapTime) -> Self {
+        value.as_duration()
+    }
+}
+
+impl fmt::Display for LapTime {
+    /// Formats the time as minutes, seconds and milliseconds, e.g. "1:05.042".
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let minutes = self.milliseconds / 60_000;
+        let seconds = (self.milliseconds / 1000) % 60;
+        let milliseconds = self.milliseconds % 1000;
+        write!(formatter, "{}:{:02}.{:03}", minutes, seconds, milliseconds)
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for LapTime {
+    type Err = Error;
+
+    /// Parses either plain milliseconds, e.g. "65042", or minutes, seconds and milliseconds
+    /// as they are formatted by [`Display`](fmt::Display), e.g. "1:05.042".
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidArgument(format!("invalid lap time \"{}\"", value));
+        let parse = |digits: &str| -> Result<u32, Error> {
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            digits.parse::<u32>().map_err(|_| invalid())
+        };
+
+        let Some((minutes, rest)) = value.split_once(':') else {
+            return Ok(Self::from_millis(parse(value)?));
+        };
+        let (seconds, fraction) = rest.split_once('.').ok_or_else(invalid)?;
+        if seconds.len() != 2 || fraction.len() > 3 {
+            return Err(invalid());
+        }
+
+        let seconds = parse(seconds)?;
+        if seconds >= 60 {
+            return Err(invalid());
+        }
+        let milliseconds = parse(fraction)? * 10u32.pow(3 - fraction.len() as u32);
+
+        parse(minutes)?
+            .checked_mul(60_000)
+            .and_then(|total| total.checked_add(seconds * 1000 + milliseconds))
+            .map(Self::from_millis)
+            .ok_or_else(invalid)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LapTime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.milliseconds)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LapTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LapTimeVisitor;
+
+        impl serde::de::Visitor<'_> for LapTimeVisitor {
+            type Value = LapTime;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("milliseconds or a lap time formatted as \"m:ss.mmm\"")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<LapTime, E> {
+                u32::try_from(value)
+                    .map(LapTime::from_millis)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(value), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<LapTime, E> {
+                u32::try_from(value)
+                    .map(LapTime::from_millis)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(value), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<LapTime, E> {
+                value
+                    .parse()
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(LapTimeVisitor)
+    }
+}
+
+/// Serializes a [`LapTime`] as "m:ss.mmm" string, for use with `#[serde(with = "carrlink::lap_time_text")]`.
+/// Deserialization accepts the same forms as the default implementation.
+#[cfg(feature = "serde")]
+pub mod text {
+    use super::LapTime;
+
+    pub fn serialize<S: serde::Serializer>(
+        time: &LapTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(time)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<LapTime, D::Error> {
+        serde::Deserialize::deserialize(deserializer)
+    }
+}
+
+/// Converts a duration into milliseconds of the control unit timer, saturating at the maximum timer value.
+fn duration_to_millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
 }
 
 impl Add<Duration> for LapTime {
     type Output = Self;
 
+    /// Adds the duration to the time, saturating at the maximum timer value.
     fn add(self, rhs: Duration) -> Self::Output {
-        let rhs_millis = rhs.as_millis() as u32;
         Self {
-            milliseconds: self.milliseconds + rhs_millis,
+            milliseconds: self.milliseconds.saturating_add(duration_to_millis(rhs)),
         }
     }
 }
@@ -28,10 +240,10 @@ impl Add<Duration> for LapTime {
 impl Sub<Duration> for LapTime {
     type Output = Self;
 
+    /// Subtracts the duration from the time, saturating at zero.
     fn sub(self, rhs: Duration) -> Self::Output {
-        let rhs_millis = rhs.as_millis() as u32;
         Self {
-            milliseconds: self.milliseconds - rhs_millis,
+            milliseconds: self.milliseconds.saturating_sub(duration_to_millis(rhs)),
         }
     }
 }
@@ -39,8 +251,115 @@ impl Sub<Duration> for LapTime {
 impl Sub<LapTime> for LapTime {
     type Output = Duration;
 
+    /// Computes the duration between both times, saturating at zero if the right hand side is later.
     fn sub(self, rhs: LapTime) -> Self::Output {
-        let delta = self.milliseconds - rhs.milliseconds;
-        Duration::from_millis(delta as u64)
+        self.saturating_sub(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_deltas_across_the_wrap_of_the_timer() {
+        let before_wrap = LapTime::from_millis(u32::MAX - 499);
+        let after_wrap = LapTime::from_millis(1_500);
+
+        assert_eq!(
+            after_wrap.wrapping_delta(before_wrap),
+            Duration::from_millis(2_000)
+        );
+        assert_eq!(after_wrap.checked_sub(before_wrap), None);
+        assert_eq!(before_wrap.wrapping_delta(before_wrap), Duration::ZERO);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parses_what_it_displays() {
+        for milliseconds in [0, 42, 65_042, 3_599_999, u32::MAX] {
+            let time = LapTime::from_millis(milliseconds);
+            assert_eq!(time.to_string().parse::<LapTime>().unwrap(), time);
+        }
+        assert_eq!(LapTime::from_millis(65_042).to_string(), "1:05.042");
+        assert_eq!(LapTime::from_millis(u32::MAX).to_string(), "71582:47.295");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parses_milliseconds_and_short_fractions() {
+        assert_eq!(
+            "65042".parse::<LapTime>().unwrap(),
+            LapTime::from_millis(65_042)
+        );
+        assert_eq!(
+            "1:05.5".parse::<LapTime>().unwrap(),
+            LapTime::from_millis(65_500)
+        );
+        assert_eq!(
+            "0:05.05".parse::<LapTime>().unwrap(),
+            LapTime::from_millis(5_050)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn rejects_malformed_lap_times() {
+        for value in [
+            "",
+            "1:",
+            "1:05",
+            "1:5.000",
+            "1:60.000",
+            "1:05.0420",
+            "-1",
+            "-1:05.000",
+            "1:-5.000",
+            "+100",
+            "1.5",
+            "4294967296",
+            "71582:47.296",
+            "71583:00.000",
+        ] {
+            assert!(
+                matches!(value.parse::<LapTime>(), Err(Error::InvalidArgument(_))),
+                "accepted \"{}\"",
+                value
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializes_numbers_and_strings() {
+        let time = LapTime::from_millis(65_042);
+        assert_eq!(serde_json::to_string(&time).unwrap(), "65042");
+        assert_eq!(serde_json::from_str::<LapTime>("65042").unwrap(), time);
+        assert_eq!(
+            serde_json::from_str::<LapTime>("\"1:05.042\"").unwrap(),
+            time
+        );
+        assert_eq!(serde_json::from_str::<LapTime>("\"65042\"").unwrap(), time);
+
+        assert!(serde_json::from_str::<LapTime>("-1").is_err());
+        assert!(serde_json::from_str::<LapTime>("4294967296").is_err());
+        assert!(serde_json::from_str::<LapTime>("\"1:60.000\"").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes_as_text_on_request() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Record {
+            #[serde(with = "text")]
+            time: LapTime,
+        }
+
+        let record = Record {
+            time: LapTime::from_millis(65_042),
+        };
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(json, r#"{"time":"1:05.042"}"#);
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
     }
 }
@@ -1,20 +1,188 @@
 //! carrlink is a library for interfacing with a Carrera control unit either
 //! using a bluetooth connector or a direct serial connection.
+//!
+//! Without the default `std` feature only the protocol core is available: the encoding of
+//! requests, the decoding of responses including their checksums and the status types.
+//! The core neither needs the standard library nor allocates, so it can be used on microcontrollers.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod accessories;
+#[cfg(feature = "std")]
 mod backend;
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
 mod backend_ble;
+#[cfg(all(target_arch = "wasm32", feature = "web-bluetooth"))]
+mod backend_web;
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+mod ble_discovery;
+mod bounded;
+mod brake_level;
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+mod combined_session;
+#[cfg(feature = "std")]
+pub mod conformance;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+mod connection_guard;
+#[cfg(feature = "std")]
 mod control_unit;
+mod controller_id;
+#[cfg(feature = "std")]
+mod cu_profile;
+mod decode_error;
+#[cfg(feature = "std")]
 mod error;
+#[cfg(feature = "std")]
+mod export;
+mod fuel_level;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+mod hex_dump;
+#[cfg(feature = "std")]
+mod lap;
 mod lap_time;
-mod messages;
+#[cfg(feature = "serde")]
+mod live_timing;
+pub mod messages;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "std")]
+mod poll_rate;
+pub mod prelude;
+#[cfg(feature = "std")]
+mod profile;
+pub mod protocol;
+#[cfg(feature = "remote")]
+mod remote;
+#[cfg(feature = "telemetry")]
+mod replay;
+#[cfg(feature = "std")]
+mod request_batch;
+#[cfg(feature = "rest")]
+mod rest;
+#[cfg(feature = "std")]
+mod session;
+mod settings;
+#[cfg(feature = "std")]
+mod shared_control_unit;
+#[cfg(feature = "simulator")]
+mod simulator;
+mod speed_level;
+mod start_detector;
 mod status;
+#[cfg(feature = "std")]
+mod status_cache;
+#[cfg(feature = "sqlite")]
+mod store;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+#[cfg(feature = "std")]
+pub mod test_vectors;
+mod tower_position;
+#[cfg(feature = "std")]
+mod udp_broadcast;
+#[cfg(feature = "std")]
+mod watchdog;
+#[cfg(feature = "ws-server")]
+mod ws_server;
 
+pub use accessories::{Accessories, AccessoryState};
+#[cfg(feature = "std")]
 pub use backend::Backend;
-pub use backend_ble::{discover_first_ble, BackendBLE};
-pub use control_unit::ControlUnit;
-pub use error::Error;
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+pub use backend_ble::{BackendBLE, RequestCategory};
+#[cfg(all(target_arch = "wasm32", feature = "web-bluetooth"))]
+pub use backend_web::WebBluetoothBackend;
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+pub use ble_discovery::{
+    connect_any_ble, connect_known_ble, discover_first_ble, discover_first_ble_cancellable,
+    first_adapter, reconnect_ble, BleDiscovery, DiscoveryEvent, ManufacturerData,
+    PeripheralIdentity, RejectionReason, ScanDutyCycle, ScanPolicy, Transport,
+};
+pub use brake_level::BrakeLevel;
+#[cfg(feature = "std")]
+pub use clock::{Clock, SystemClock, TestClock};
+#[cfg(feature = "std")]
+pub use combined_session::CombinedSession;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use connection_guard::ConnectionGuard;
+#[cfg(feature = "std")]
+pub use control_unit::{ControlUnit, ControlUnitBuilder, MAX_IN_FLIGHT_REQUESTS};
+pub use controller_id::ControllerId;
+#[cfg(feature = "std")]
+pub use cu_profile::{CarProfile, CuProfile, PACE_CAR_CONTROLLER};
+pub use decode_error::DecodeError;
+#[cfg(feature = "std")]
+pub use error::{Error, Operation};
+#[cfg(feature = "std")]
+pub use export::{CsvExport, LapColumn, ResultColumn};
+pub use fuel_level::FuelLevel;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use hex_dump::{HexDumpBackend, HEX_DUMP_LOG_TARGET};
+#[cfg(feature = "std")]
+pub use lap::Lap;
+#[cfg(feature = "serde")]
+pub use lap_time::text as lap_time_text;
 pub use lap_time::LapTime;
-pub use status::{LapStatus, StartSignal, Status, TrackStatus, MAX_CONTROLLER_COUNT};
+#[cfg(feature = "serde")]
+pub use live_timing::{
+    LapEvent, LiveTimingMessage, SessionInfo, StandingEntry, Standings, LIVE_TIMING_SCHEMA_VERSION,
+};
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttPublisher, MqttTopics};
+#[cfg(feature = "std")]
+pub use poll_rate::{AdaptivePollRate, PollPhase};
+#[cfg(feature = "std")]
+pub use profile::ProtocolProfile;
+pub use protocol::{ChecksumValidation, Frame, MAX_FRAME_SIZE};
+#[cfg(feature = "remote")]
+pub use remote::{serve_backend, RemoteBackend};
+#[cfg(feature = "telemetry")]
+pub use replay::SessionReplayer;
+#[cfg(feature = "std")]
+pub use request_batch::RequestBatch;
+#[cfg(feature = "rest")]
+pub use rest::{control_router, serve_control_api};
+#[cfg(feature = "std")]
+pub use session::{RaceSession, Standing};
+pub use settings::CarSettings;
+#[cfg(feature = "std")]
+pub use shared_control_unit::SharedControlUnit;
+#[cfg(feature = "simulator")]
+pub use simulator::SimulatedBackend;
+pub use speed_level::SpeedLevel;
+pub use start_detector::{StartDetector, StartEvent};
+pub use status::{
+    FuelMode, LapStatus, StartSignal, Status, TimerGroup, TrackMode, TrackStatus,
+    MAX_CONTROLLER_COUNT,
+};
+#[cfg(feature = "std")]
+pub use status_cache::StatusCache;
+#[cfg(feature = "sqlite")]
+pub use store::{BestLap, LapStore, StoredSession};
+#[cfg(feature = "telemetry")]
+pub use telemetry::{TelemetryEvent, TelemetryLogger, TelemetryRecord};
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+pub use tokio_util::sync::CancellationToken;
+pub use tower_position::TowerPosition;
+#[cfg(feature = "std")]
+pub use udp_broadcast::{
+    encode_lap_packet, encode_standings_packet, UdpBroadcaster, UDP_PACKET_MAGIC,
+    UDP_PACKET_VERSION,
+};
+#[cfg(feature = "std")]
+pub use watchdog::{StallEvent, StallWatchdog};
+#[cfg(feature = "ws-server")]
+pub use ws_server::LiveTimingServer;
+
+/// Entry points of the decoders for the fuzz targets in `fuzz/`.
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzzing {
+    pub use crate::messages::{decode_status, decode_version};
+}
 
 /// Convenience type for a result using the carrlink [`Error`] type.
+#[cfg(feature = "std")]
 pub type Result<T> = std::result::Result<T, Error>;
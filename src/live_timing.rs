@@ -0,0 +1,202 @@
+//! Module which defines the live timing messages for web overlays and third party displays.
+//!
+//! Every message is a JSON object with a `type` field naming the message and a `schema_version`
+//! field which is incremented whenever a field is removed or changes its meaning. New fields may
+//! be added without changing the version. Times are given as integer milliseconds.
+//!
+//! ```json
+//! {"type":"session_info","schema_version":1,"controllers":[0,1],"completed_laps":12,"min_lap_time_ms":3000}
+//! {"type":"lap","schema_version":1,"controller":0,"lap":7,"lap_time_ms":8123,"splits_ms":[4012,4111],"timestamp_ms":61234,"valid":true}
+//! {"type":"standings","schema_version":1,"standings":[{"position":1,"controller":0,"laps":7,"total_time_ms":57012,"best_lap_ms":7954,"last_lap_ms":8123,"gap_ms":null,"laps_behind":0}]}
+//! ```
+
+use crate::{ControllerId, Lap, RaceSession, Standing};
+use std::time::Duration;
+
+/// Version of the live timing schema which is written into every message.
+pub const LIVE_TIMING_SCHEMA_VERSION: u32 = 1;
+
+/// General information about a session.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SessionInfo {
+    /// Controllers which took part in the session so far.
+    pub controllers: Vec<usize>,
+
+    /// Number of laps which were completed by all controllers together.
+    pub completed_laps: usize,
+
+    /// Minimum lap time of valid laps.
+    pub min_lap_time_ms: u64,
+}
+
+/// A lap which was completed by a controller.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LapEvent {
+    /// Identifier of the controller.
+    pub controller: usize,
+
+    /// Number of the lap, starting at 1.
+    pub lap: usize,
+
+    /// Time of the lap.
+    pub lap_time_ms: u64,
+
+    /// Sector times of the lap, empty without check lanes.
+    pub splits_ms: Vec<u64>,
+
+    /// Control unit timer when the lap was completed.
+    pub timestamp_ms: u32,
+
+    /// Whether the lap counts.
+    pub valid: bool,
+}
+
+/// Position of a single controller in the standings.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StandingEntry {
+    /// Position, the leader has position 1.
+    pub position: usize,
+
+    /// Identifier of the controller.
+    pub controller: usize,
+
+    /// Number of completed laps.
+    pub laps: usize,
+
+    /// Sum of all lap times.
+    pub total_time_ms: u64,
+
+    /// Fastest valid lap.
+    pub best_lap_ms: Option<u64>,
+
+    /// Last completed lap.
+    pub last_lap_ms: Option<u64>,
+
+    /// Time behind the leader, none for the leader and controllers which are laps behind.
+    pub gap_ms: Option<u64>,
+
+    /// Number of laps behind the leader.
+    pub laps_behind: usize,
+}
+
+/// Current standings of a session.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Standings {
+    /// All controllers which completed a lap, ordered by their position.
+    pub standings: Vec<StandingEntry>,
+}
+
+/// Message of the live timing schema.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveTimingMessage {
+    /// General information about the session.
+    SessionInfo {
+        schema_version: u32,
+        #[serde(flatten)]
+        info: SessionInfo,
+    },
+
+    /// A completed lap.
+    Lap {
+        schema_version: u32,
+        #[serde(flatten)]
+        lap: LapEvent,
+    },
+
+    /// The current standings.
+    Standings {
+        schema_version: u32,
+        #[serde(flatten)]
+        standings: Standings,
+    },
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+impl From<&Lap> for LapEvent {
+    fn from(lap: &Lap) -> Self {
+        LapEvent {
+            controller: lap.controller.index(),
+            lap: lap.number,
+            lap_time_ms: as_millis(lap.lap_time),
+            splits_ms: lap.splits.iter().copied().map(as_millis).collect(),
+            timestamp_ms: lap.time.raw(),
+            valid: lap.is_valid,
+        }
+    }
+}
+
+impl Standings {
+    /// Converts standings as computed by [`RaceSession::standings`].
+    pub fn from_standings(standings: &[Standing]) -> Standings {
+        let leader = standings.first();
+        let standings = standings
+            .iter()
+            .map(|standing| {
+                let laps_behind = leader.map_or(0, |leader| leader.laps - standing.laps);
+                let gap_ms = match leader {
+                    Some(leader) if standing.position > 1 && laps_behind == 0 => Some(as_millis(
+                        standing.total_time.saturating_sub(leader.total_time),
+                    )),
+                    _ => None,
+                };
+
+                StandingEntry {
+                    position: standing.position,
+                    controller: standing.controller.index(),
+                    laps: standing.laps,
+                    total_time_ms: as_millis(standing.total_time),
+                    best_lap_ms: standing.best_lap.map(as_millis),
+                    last_lap_ms: standing.last_lap.map(as_millis),
+                    gap_ms,
+                    laps_behind,
+                }
+            })
+            .collect();
+
+        Standings { standings }
+    }
+}
+
+impl LiveTimingMessage {
+    /// Creates the session information message of the session.
+    pub fn session_info(session: &RaceSession) -> LiveTimingMessage {
+        LiveTimingMessage::SessionInfo {
+            schema_version: LIVE_TIMING_SCHEMA_VERSION,
+            info: SessionInfo {
+                controllers: ControllerId::ALL
+                    .into_iter()
+                    .filter(|controller| session.lap_count(*controller) > 0)
+                    .map(|controller| controller.index())
+                    .collect(),
+                completed_laps: session.laps().len(),
+                min_lap_time_ms: as_millis(session.min_lap_time()),
+            },
+        }
+    }
+
+    /// Creates the message of a completed lap.
+    pub fn lap(lap: &Lap) -> LiveTimingMessage {
+        LiveTimingMessage::Lap {
+            schema_version: LIVE_TIMING_SCHEMA_VERSION,
+            lap: LapEvent::from(lap),
+        }
+    }
+
+    /// Creates the message of the current standings of the session.
+    pub fn standings(session: &RaceSession) -> LiveTimingMessage {
+        LiveTimingMessage::Standings {
+            schema_version: LIVE_TIMING_SCHEMA_VERSION,
+            standings: Standings::from_standings(&session.standings()),
+        }
+    }
+
+    /// Serializes the message as JSON.
+    pub fn to_json(&self) -> String {
+        // the messages consist of plain data only, which always serializes
+        serde_json::to_string(self).expect("live timing messages are serializable")
+    }
+}
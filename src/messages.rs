@@ -1,60 +1,70 @@
-use crate::LapTime;
+//! Module which encodes the requests and decodes the responses of the control unit.
+//!
+//! Responses are expected in the common format every [`Backend`](crate::Backend) returns: the
+//! command character first, followed by the payload and the checksum. Nothing in this module
+//! allocates, so it is available without the `std` feature.
+
+use crate::protocol::{
+    compute_checksum, decode_uint32, encode_nibble, make_set_word_request, ChecksumValidation,
+    WordAddress, UINT32_SIZE,
+};
+use crate::{ControllerId, DecodeError, FuelLevel, LapTime};
 
 use super::StartSignal;
 use super::MAX_CONTROLLER_COUNT;
 
-use super::{LapStatus, Status, TrackStatus};
+use super::{LapStatus, Status, TrackMode, TrackStatus};
 
 const MIN_CHECKSUM_MESSAGE_LEN: usize = 2;
+
+/// Request which polls the next status of the control unit.
 pub const STATUS_REQUEST: [u8; 1] = [b'?'];
+
+/// Request which queries the firmware version of the control unit.
 pub const VERSION_REQUEST: [u8; 1] = [b'0'];
 
-/// Computes the checksum of the given slice of data.
-fn compute_checksum(data: &[u8]) -> u8 {
-    let sum: u32 = data.iter().map(|c| *c as u32).sum();
-    (sum & 0x0F) as u8
+/// Number of characters of the firmware version reported by the control unit.
+pub const VERSION_SIZE: usize = 4;
+
+/// Applies the given validation strictness to the outcome of a checksum comparison.
+fn validate_checksum(
+    is_valid: bool,
+    data: &[u8],
+    validation: ChecksumValidation,
+) -> Result<(), DecodeError> {
+    match validation {
+        _ if is_valid => Ok(()),
+        ChecksumValidation::Strict => Err(DecodeError::BadChecksum),
+        ChecksumValidation::WarnOnly => {
+            log::warn!("ignoring checksum mismatch of response {:?}", data);
+            Ok(())
+        }
+        ChecksumValidation::Off => Ok(()),
+    }
 }
 
 /// Verifies if the given data slice has a valid checksum.
-fn check_checksum(data: &[u8]) -> bool {
+fn check_checksum(data: &[u8], validation: ChecksumValidation) -> Result<(), DecodeError> {
     if data.len() < MIN_CHECKSUM_MESSAGE_LEN {
-        return false;
+        return Err(DecodeError::WrongLength);
     }
 
-    let expected = compute_checksum(&data[1..data.len() - 1]);
-    let actual = data.last().unwrap() & 0x0F;
-    actual == expected
-}
-
-const UINT32_SIZE: usize = 8;
-
-fn decode_uint32(data: &[u8]) -> u32 {
-    return ((data[0] & 0x0F) as u32) << 24
-        | ((data[1] & 0x0F) as u32) << 28
-        | ((data[2] & 0x0F) as u32) << 16
-        | ((data[3] & 0x0F) as u32) << 20
-        | ((data[4] & 0x0F) as u32) << 8
-        | ((data[5] & 0x0F) as u32) << 12
-        | ((data[6] & 0x0F) as u32)
-        | ((data[7] & 0x0F) as u32) << 4;
-}
-
-// All values accepted by the control unti have to be added on top of this base.
-const VALUE_BASE: u8 = b'0';
-
-/// Encodes the lower nibble of the given value onto the buffer.
-const fn encode_nibble(value: u8) -> u8 {
-    VALUE_BASE + (value & 0x0F)
-}
+    let (payload, checksum) = match data {
+        [_, payload @ .., checksum] => (payload, checksum),
+        _ => return Err(DecodeError::WrongLength),
+    };
 
-/// Encodes the address of the player for writing a word.
-const fn encode_player_address(offset: u8, player: u8) -> u8 {
-    let player_mask: u8 = 0x07;
-    let offset_mask: u8 = 0x1F;
-    ((player & player_mask) << 5) | (offset & offset_mask)
+    validate_checksum(
+        (checksum & 0x0F) == compute_checksum(payload),
+        data,
+        validation,
+    )
 }
 
-fn decode_track_status(data: &[u8]) -> Option<TrackStatus> {
+fn decode_track_status(
+    data: &[u8],
+    validation: ChecksumValidation,
+) -> Result<TrackStatus, DecodeError> {
     const FUEL_LEVEL_OFFSET: usize = 2;
     const START_SIGNAL_OFFSET: usize = FUEL_LEVEL_OFFSET + 8;
     const TRACK_MODE_OFFSET: usize = START_SIGNAL_OFFSET + 1;
@@ -65,183 +75,270 @@ fn decode_track_status(data: &[u8]) -> Option<TrackStatus> {
     const LONG_RESPONSE_SIZE: usize = SHORT_RESPONSE_SIZE + 2;
 
     if data.len() != SHORT_RESPONSE_SIZE && data.len() != LONG_RESPONSE_SIZE {
-        return None;
+        return Err(DecodeError::WrongLength);
     }
 
-    if data[0] != b'?' && data[1] != b':' {
-        return None;
+    if data[0] != b'?' || data[1] != b':' {
+        return Err(DecodeError::UnknownCommand);
     }
 
-    if !check_checksum(data) {
-        return None;
-    }
+    check_checksum(data, validation)?;
 
-    let mut result = TrackStatus::new();
+    let mut result = TrackStatus::default();
 
     // parse fuel levels
     let fuel_level_data = &data[FUEL_LEVEL_OFFSET..FUEL_LEVEL_OFFSET + MAX_CONTROLLER_COUNT];
     for (fuel_level, value) in result.fuel_levels.iter_mut().zip(fuel_level_data.iter()) {
-        *fuel_level = (value & 0x0F) as usize;
+        *fuel_level = FuelLevel::from_nibble(*value);
     }
 
     // parse start light indicator
     match StartSignal::try_from(data[START_SIGNAL_OFFSET] & 0x0F) {
         Ok(start_signal) => result.start_signal = start_signal,
-        Err(_) => return None,
+        Err(_) => return Err(DecodeError::BadField),
     };
 
     // parse track mode
-    let track_mode = data[TRACK_MODE_OFFSET];
-    result.is_fuel_enabled = (track_mode & 0x01) != 0x00;
-    result.is_real_fuel_enabled = (track_mode & 0x02) != 0x00;
-    result.is_pit_lane_connected = (track_mode & 0x01) != 0x00;
-    result.is_lap_counter_connected = (track_mode & 0x02) != 0x00;
+    result.mode = TrackMode::from_bits_retain(data[TRACK_MODE_OFFSET] & 0x0F);
 
-    // parse is_refueling
+    // parse is_refueling, the mask is transmitted lower nibble first
     let refuel_mask =
-        (data[IS_REFUELING_OFFSET] & 0x0F) | ((data[IS_REFUELING_OFFSET] & 0x0F) << 4);
+        (data[IS_REFUELING_OFFSET] & 0x0F) | ((data[IS_REFUELING_OFFSET + 1] & 0x0F) << 4);
     for (i, is_refueling) in result.is_refueling.iter_mut().enumerate() {
         *is_refueling = (refuel_mask & (0x01 << i)) != 0x00;
     }
 
     result.controller_count = (data[CONTROLLER_COUNT_OFFSET] & 0x0F) as usize;
 
-    Some(result)
+    Ok(result)
 }
 
-fn decode_lap_status(data: &[u8]) -> Option<LapStatus> {
+fn decode_lap_status(
+    data: &[u8],
+    validation: ChecksumValidation,
+) -> Result<LapStatus, DecodeError> {
     const CONTROLLER_OFFSET: usize = 1;
     const TIME_OFFSET: usize = CONTROLLER_OFFSET + 1;
-    const SECTOR_OFFSET: usize = TIME_OFFSET + 8;
+    const SECTOR_OFFSET: usize = TIME_OFFSET + UINT32_SIZE;
     const CHECKSUM_OFFSET: usize = SECTOR_OFFSET + 1;
     const RESPONSE_SIZE: usize = CHECKSUM_OFFSET + 1;
 
     if data.len() != RESPONSE_SIZE {
-        return None;
+        return Err(DecodeError::WrongLength);
     }
 
     if data[0] != b'?' {
-        return None;
+        return Err(DecodeError::UnknownCommand);
     }
 
-    if !check_checksum(data) {
-        return None;
-    }
+    check_checksum(data, validation)?;
 
-    let mut result = LapStatus::new();
+    // controllers are transmitted one based
+    let controller = match (data[CONTROLLER_OFFSET] & 0x0F).checked_sub(1) {
+        Some(controller) => match ControllerId::new(controller as usize) {
+            Some(controller) => controller,
+            None => return Err(DecodeError::BadField),
+        },
+        None => return Err(DecodeError::BadField),
+    };
 
-    result.controller = ((data[CONTROLLER_OFFSET] & 0x0F) - 1) as usize;
-    result.time =
-        LapTime::from_millis(decode_uint32(&data[TIME_OFFSET..TIME_OFFSET + UINT32_SIZE]));
-    result.sector = (data[SECTOR_OFFSET] & 0x0F) as usize;
+    let time_data: &[u8; UINT32_SIZE] = match data[TIME_OFFSET..].first_chunk() {
+        Some(time_data) => time_data,
+        None => return Err(DecodeError::WrongLength),
+    };
 
-    Some(result)
+    Ok(LapStatus::default()
+        .with_controller(controller)
+        .with_time(LapTime::from_millis(decode_uint32(time_data)))
+        .with_sector((data[SECTOR_OFFSET] & 0x0F) as usize))
 }
 
-pub fn decode_status(data: &[u8]) -> Option<Status> {
-    match decode_track_status(data) {
-        Some(status) => Some(Status::Track(status)),
-        None => match decode_lap_status(data) {
-            Some(status) => Some(Status::Lap(status)),
-            None => None,
-        },
+/// Decodes the response to a [`STATUS_REQUEST`].
+/// The compact binary responses of newer firmwares are not supported and rejected as error.
+pub fn decode_status(data: &[u8], validation: ChecksumValidation) -> Result<Status, DecodeError> {
+    // a bare command character signals that no new data is available,
+    // track status responses are marked with a colon after the command character,
+    // everything else is treated as lap status
+    match data {
+        [] => Err(DecodeError::WrongLength),
+        [b'?'] => Ok(Status::NoData),
+        [_] => Err(DecodeError::UnknownCommand),
+        [_, b':', ..] => Ok(Status::Track(decode_track_status(data, validation)?)),
+        _ => Ok(Status::Lap(decode_lap_status(data, validation)?)),
     }
 }
 
-pub fn decode_version(data: &[u8]) -> Option<String> {
-    const RESPONSE_SIZE: usize = 6;
+/// Decodes the characters of the firmware version from the response to a [`VERSION_REQUEST`].
+pub fn decode_version_chars(
+    data: &[u8],
+    validation: ChecksumValidation,
+) -> Result<[u8; VERSION_SIZE], DecodeError> {
+    const RESPONSE_SIZE: usize = VERSION_SIZE + 2;
 
     if data.len() != RESPONSE_SIZE {
-        return None;
+        return Err(DecodeError::WrongLength);
     }
 
     if data[0] != b'0' {
-        return None;
+        return Err(DecodeError::UnknownCommand);
     }
 
-    if !check_checksum(data) {
-        return None;
+    check_checksum(data, validation)?;
+
+    match data[1..].first_chunk() {
+        Some(version) => Ok(*version),
+        None => Err(DecodeError::WrongLength),
     }
+}
 
-    let result: String = data[1..data.len() - 1].iter().map(|v| *v as char).collect();
-    Some(result)
+/// Decodes the firmware version from the response to a [`VERSION_REQUEST`].
+#[cfg(feature = "std")]
+pub fn decode_version(data: &[u8], validation: ChecksumValidation) -> Result<String, DecodeError> {
+    let version = decode_version_chars(data, validation)?;
+    Ok(version.iter().map(|v| *v as char).collect())
 }
 
-pub fn decode_empty(data_in: &[u8], data_out: &[u8]) -> Option<()> {
-    if data_out.len() > 0 && data_in.len() > 0 && data_out[0] == data_in[0] {
-        return Some(());
-    } else {
-        return None;
+/// Decodes the acknowledgement of the control unit for a request which does not return any data,
+/// e.g. word writes, clock resets and button presses.
+/// The control unit either acknowledges with the bare command character or echoes the complete request.
+pub fn decode_acknowledgement(
+    request: &[u8],
+    response: &[u8],
+    validation: ChecksumValidation,
+) -> Result<(), DecodeError> {
+    let command = match request.first() {
+        Some(command) => command,
+        None => return Err(DecodeError::WrongLength),
+    };
+
+    match response {
+        [] => Err(DecodeError::WrongLength),
+        [actual, ..] if actual != command => Err(DecodeError::UnknownCommand),
+        [_] => Ok(()),
+        _ if response.len() != request.len() => Err(DecodeError::WrongLength),
+        [payload @ .., checksum] => {
+            validate_checksum(
+                (checksum & 0x0F) == compute_checksum(payload),
+                response,
+                validation,
+            )?;
+
+            // the checksum is not part of the comparison as it was validated according to the strictness
+            if payload != request.split_at(payload.len()).0 {
+                return Err(DecodeError::BadField);
+            }
+
+            Ok(())
+        }
     }
 }
 
-pub fn make_button_press_request(button: u8) -> [u8; 3] {
+/// Creates a request which simulates a press of the button with the given code.
+pub const fn make_button_press_request(button: u8) -> [u8; 3] {
     let mut result: [u8; 3] = [b'T', encode_nibble(button), 0];
-    result[2] = compute_checksum(&result[..2]);
-    result
-}
-
-fn make_set_word_request(address: u8, value: u8, repetitions: u8) -> [u8; 6] {
-    let mut result: [u8; 6] = [
-        b'J',
-        VALUE_BASE + (address & 0x0F),
-        VALUE_BASE + (address >> 4),
-        encode_nibble(value),
-        encode_nibble(repetitions),
-        0,
-    ];
-
-    result[5] = compute_checksum(&result[..5]);
+    result[2] = compute_checksum(result.split_at(2).0);
     result
 }
 
-pub fn make_reset_positions_request() -> [u8; 6] {
-    const WORD_ADDRESS: u8 = 0x06;
-    const WORD_VALUE: u8 = 0x09;
-    const WORD_REPETITIONS: u8 = 0x01;
+/// Creates a request which clears the position tower.
+pub const fn make_reset_positions_request() -> [u8; 6] {
+    // writing the clear value to the position of the first player clears the complete tower
+    const CLEAR_POSITIONS_VALUE: u8 = 0x09;
 
-    make_set_word_request(WORD_ADDRESS, WORD_VALUE, WORD_REPETITIONS)
+    make_set_word_request(WordAddress::Position(0), CLEAR_POSITIONS_VALUE)
 }
 
-pub fn make_reset_clock_request() -> [u8; 4] {
-    let mut result: [u8; 4] = [b'=', encode_nibble(0x01), encode_nibble(0x00), 0];
+/// Creates a request which makes the control unit ignore the controllers set in the mask.
+pub const fn make_ignore_controllers_request(mask: u8) -> [u8; 4] {
+    let mut result: [u8; 4] = [b':', encode_nibble(mask), encode_nibble(mask >> 4), 0];
 
-    result[3] = compute_checksum(&result[..3]);
+    result[3] = compute_checksum(result.split_at(3).0);
     result
 }
 
-pub fn make_set_speed_level_request(player: u8, value: u8) -> [u8; 6] {
-    const ADDRESS_OFFSET: u8 = 0x00;
-    const WORD_REPETITIONS: u8 = 0x02;
-    let word_address = encode_player_address(ADDRESS_OFFSET, player);
+/// Creates a request which resets the race clock.
+pub const fn make_reset_clock_request() -> [u8; 4] {
+    let mut result: [u8; 4] = [b'=', encode_nibble(0x01), encode_nibble(0x00), 0];
 
-    make_set_word_request(word_address, value, WORD_REPETITIONS)
+    result[3] = compute_checksum(result.split_at(3).0);
+    result
 }
 
-pub fn make_set_brake_level_request(player: u8, value: u8) -> [u8; 6] {
-    const ADDRESS_OFFSET: u8 = 0x01;
-    const WORD_REPETITIONS: u8 = 0x02;
-    let word_address = encode_player_address(ADDRESS_OFFSET, player);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FuelMode;
+
+    /// Creates the track status of an idle track with full tanks and the given mode nibble.
+    fn track_status_frame(mode: u8) -> [u8; 16] {
+        let mut frame = *b"?:????????000000";
+        frame[11] = encode_nibble(mode);
+        frame[15] = encode_nibble(compute_checksum(&frame[1..15]));
+        frame
+    }
 
-    make_set_word_request(word_address, value, WORD_REPETITIONS)
-}
+    fn decode_mode(mode: u8) -> TrackStatus {
+        decode_track_status(&track_status_frame(mode), ChecksumValidation::Strict).unwrap()
+    }
 
-pub fn make_set_fuel_level_request(player: u8, value: u8) -> [u8; 6] {
-    const ADDRESS_OFFSET: u8 = 0x02;
-    const WORD_REPETITIONS: u8 = 0x02;
-    let word_address = encode_player_address(ADDRESS_OFFSET, player);
+    #[test]
+    fn decodes_each_mode_bit() {
+        let status = decode_mode(0x01);
+        assert_eq!(status.mode, TrackMode::FUEL);
+        assert!(status.is_fuel_enabled());
+        assert!(!status.is_real_fuel_enabled());
+        assert!(!status.is_pit_lane_connected());
+        assert!(!status.is_lap_counter_connected());
+
+        let status = decode_mode(0x02);
+        assert_eq!(status.mode, TrackMode::REAL_FUEL);
+        assert!(!status.is_fuel_enabled());
+        assert!(status.is_real_fuel_enabled());
+
+        let status = decode_mode(0x04);
+        assert_eq!(status.mode, TrackMode::PIT_LANE);
+        assert!(status.is_pit_lane_connected());
+        assert!(!status.is_lap_counter_connected());
+
+        let status = decode_mode(0x08);
+        assert_eq!(status.mode, TrackMode::LAP_COUNTER);
+        assert!(status.is_lap_counter_connected());
+        assert!(!status.is_pit_lane_connected());
+    }
 
-    make_set_word_request(word_address, value, WORD_REPETITIONS)
-}
+    #[test]
+    fn decodes_combined_mode_bits() {
+        assert_eq!(decode_mode(0x00).mode, TrackMode::empty());
+        assert_eq!(
+            decode_mode(0x05).mode,
+            TrackMode::FUEL | TrackMode::PIT_LANE
+        );
+        assert_eq!(
+            decode_mode(0x03).mode,
+            TrackMode::FUEL | TrackMode::REAL_FUEL
+        );
+
+        let status = decode_mode(0x0F);
+        assert_eq!(status.mode, TrackMode::all());
+        assert!(status.is_fuel_enabled());
+        assert!(status.is_real_fuel_enabled());
+        assert!(status.is_pit_lane_connected());
+        assert!(status.is_lap_counter_connected());
+    }
 
-pub fn make_set_lap_low_request(value: u8) -> [u8; 6] {
-    const WORD_ADDRESS: u8 = 0xF2;
-    const WORD_REPETITIONS: u8 = 0x01;
-    make_set_word_request(WORD_ADDRESS, value, WORD_REPETITIONS)
-}
+    #[test]
+    fn decodes_every_mode_nibble_to_its_bits() {
+        for mode in 0..=0x0F {
+            assert_eq!(decode_mode(mode).mode.bits(), mode);
+        }
+    }
 
-pub fn make_set_lap_high_request(value: u8) -> [u8; 6] {
-    const WORD_ADDRESS: u8 = 0xF1;
-    const WORD_REPETITIONS: u8 = 0x01;
-    make_set_word_request(WORD_ADDRESS, value, WORD_REPETITIONS)
+    #[test]
+    fn derives_the_fuel_mode_from_the_mode_bits() {
+        assert_eq!(decode_mode(0x00).fuel_mode(), FuelMode::Off);
+        assert_eq!(decode_mode(0x04).fuel_mode(), FuelMode::Off);
+        assert_eq!(decode_mode(0x01).fuel_mode(), FuelMode::On);
+        assert_eq!(decode_mode(0x03).fuel_mode(), FuelMode::Real);
+        assert_eq!(decode_mode(0x02).fuel_mode(), FuelMode::Real);
+    }
 }
@@ -0,0 +1,114 @@
+//! Module which implements a publisher pushing race events to an MQTT broker.
+
+use crate::{Lap, LiveTimingMessage, RaceSession, TrackStatus};
+use rumqttc::{AsyncClient, ClientError, EventLoop, MqttOptions, QoS};
+use tokio::task::JoinHandle;
+
+/// Number of requests which are buffered before publishing waits for the broker.
+const REQUEST_BUFFER_SIZE: usize = 64;
+
+/// Topics the [`MqttPublisher`] publishes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttTopics {
+    /// Topic of the completed laps.
+    pub laps: String,
+
+    /// Topic of the standings, which are published as retained message.
+    pub standings: String,
+
+    /// Topic of the track statuses.
+    pub track_status: String,
+}
+
+impl Default for MqttTopics {
+    fn default() -> Self {
+        Self::with_prefix("carrlink")
+    }
+}
+
+impl MqttTopics {
+    /// Creates the topics `<prefix>/laps`, `<prefix>/standings` and `<prefix>/track_status`.
+    pub fn with_prefix(prefix: &str) -> MqttTopics {
+        MqttTopics {
+            laps: format!("{}/laps", prefix),
+            standings: format!("{}/standings", prefix),
+            track_status: format!("{}/track_status", prefix),
+        }
+    }
+}
+
+/// Publisher which pushes laps, standings and track statuses as JSON to an MQTT broker.
+/// Laps and standings use the [`LiveTimingMessage`] format.
+pub struct MqttPublisher {
+    client: AsyncClient,
+    topics: MqttTopics,
+    event_loop_task: JoinHandle<()>,
+}
+
+impl MqttPublisher {
+    /// Creates a publisher for the broker configured by the options.
+    /// The connection is established and kept alive in the background, so this must be called
+    /// within a tokio runtime.
+    pub fn connect(options: MqttOptions, topics: MqttTopics) -> MqttPublisher {
+        let (client, event_loop) = AsyncClient::new(options, REQUEST_BUFFER_SIZE);
+        let event_loop_task = tokio::spawn(drive_event_loop(event_loop));
+
+        MqttPublisher {
+            client,
+            topics,
+            event_loop_task,
+        }
+    }
+
+    /// Returns the topics the publisher publishes to.
+    pub fn topics(&self) -> &MqttTopics {
+        &self.topics
+    }
+
+    /// Publishes the completed lap.
+    pub async fn publish_lap(&self, lap: &Lap) -> Result<(), ClientError> {
+        let payload = LiveTimingMessage::lap(lap).to_json();
+        self.client
+            .publish(&self.topics.laps, QoS::AtLeastOnce, false, payload)
+            .await
+    }
+
+    /// Publishes the current standings of the session as retained message,
+    /// so clients which subscribe later immediately receive them.
+    pub async fn publish_standings(&self, session: &RaceSession) -> Result<(), ClientError> {
+        let payload = LiveTimingMessage::standings(session).to_json();
+        self.client
+            .publish(&self.topics.standings, QoS::AtLeastOnce, true, payload)
+            .await
+    }
+
+    /// Publishes the track status.
+    pub async fn publish_track_status(&self, status: &TrackStatus) -> Result<(), ClientError> {
+        // the status consists of plain data only, which always serializes
+        let payload = serde_json::to_string(status).expect("track statuses are serializable");
+        self.client
+            .publish(&self.topics.track_status, QoS::AtMostOnce, false, payload)
+            .await
+    }
+
+    /// Disconnects from the broker after all pending messages were sent.
+    pub async fn disconnect(&self) -> Result<(), ClientError> {
+        self.client.disconnect().await
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        self.event_loop_task.abort();
+    }
+}
+
+async fn drive_event_loop(mut event_loop: EventLoop) {
+    loop {
+        if let Err(error) = event_loop.poll().await {
+            log::warn!("MQTT connection failed: {}", error);
+            // the event loop reconnects on the next poll
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Module which adapts the rate of status requests to the activity on the track.
+
+use crate::Status;
+use std::time::{Duration, Instant};
+
+/// Activity on the track which determines how often statuses are polled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PollPhase {
+    /// No controller is in use and no countdown is running.
+    Idle,
+
+    /// Controllers are in use or cars recently crossed a sensor.
+    Racing,
+
+    /// The start lights are showing the countdown or the go signal.
+    StartSequence,
+}
+
+/// Determines the interval between two status requests from the polled statuses.
+///
+/// Polling slows down while the track is idle, which reduces the congestion of the bluetooth
+/// connection, and speeds up as soon as controllers are in use or the start sequence begins,
+/// which keeps the latency of laps and the start low. The poller stays fast for a linger
+/// duration after the last activity, so short gaps in the statuses do not slow it down.
+///
+/// Feed every polled status into [`update_status`](AdaptivePollRate::update_status) and wait for
+/// the returned interval before the next request.
+#[derive(Debug, Clone)]
+pub struct AdaptivePollRate {
+    idle_interval: Duration,
+    racing_interval: Duration,
+    start_interval: Duration,
+    linger: Duration,
+    phase: PollPhase,
+    last_activity: Option<Instant>,
+}
+
+impl Default for AdaptivePollRate {
+    fn default() -> Self {
+        AdaptivePollRate::new()
+    }
+}
+
+impl AdaptivePollRate {
+    /// Creates a poll rate which polls every 500ms while idle, every 50ms while racing and
+    /// every 20ms during the start sequence, with a linger duration of five seconds.
+    pub fn new() -> AdaptivePollRate {
+        AdaptivePollRate {
+            idle_interval: Duration::from_millis(500),
+            racing_interval: Duration::from_millis(50),
+            start_interval: Duration::from_millis(20),
+            linger: Duration::from_secs(5),
+            phase: PollPhase::Idle,
+            last_activity: None,
+        }
+    }
+
+    /// Sets the interval between two polls while the track is idle.
+    pub fn with_idle_interval(mut self, interval: Duration) -> Self {
+        self.idle_interval = interval;
+        self
+    }
+
+    /// Sets the interval between two polls while racing.
+    pub fn with_racing_interval(mut self, interval: Duration) -> Self {
+        self.racing_interval = interval;
+        self
+    }
+
+    /// Sets the interval between two polls during the start sequence.
+    pub fn with_start_interval(mut self, interval: Duration) -> Self {
+        self.start_interval = interval;
+        self
+    }
+
+    /// Sets how long polling stays fast after the last activity on the track.
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// Consumes the status which was polled at the given point in time and returns the interval
+    /// until the next poll.
+    pub fn update_status(&mut self, status: &Status, now: Instant) -> Duration {
+        let activity = match status {
+            Status::Track(track) if track.start_signal.seconds_remaining().is_some() => {
+                Some(PollPhase::StartSequence)
+            }
+            Status::Track(track) if track.controller_count > 0 => Some(PollPhase::Racing),
+            // cars crossing the line during the go signal do not end the start sequence
+            Status::Lap(_) if self.phase == PollPhase::StartSequence => {
+                Some(PollPhase::StartSequence)
+            }
+            Status::Lap(_) => Some(PollPhase::Racing),
+            Status::Track(_) | Status::NoData => None,
+        };
+
+        match activity {
+            Some(phase) => {
+                self.phase = phase;
+                self.last_activity = Some(now);
+            }
+            None => self.check(now),
+        }
+
+        self.interval()
+    }
+
+    /// Falls back to idle polling once the linger duration elapsed without activity.
+    fn check(&mut self, now: Instant) {
+        let is_lingering = self.last_activity.is_some_and(|last_activity| {
+            now.saturating_duration_since(last_activity) < self.linger
+        });
+        if !is_lingering {
+            self.phase = PollPhase::Idle;
+        }
+    }
+
+    /// Returns the current phase of the track.
+    pub fn phase(&self) -> PollPhase {
+        self.phase
+    }
+
+    /// Returns the interval until the next poll in the current phase.
+    pub fn interval(&self) -> Duration {
+        match self.phase {
+            PollPhase::Idle => self.idle_interval,
+            PollPhase::Racing => self.racing_interval,
+            PollPhase::StartSequence => self.start_interval,
+        }
+    }
+
+    /// Resets the poll rate to idle, e.g. after reconnecting to the control unit.
+    pub fn reset(&mut self) {
+        self.phase = PollPhase::Idle;
+        self.last_activity = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, LapStatus, StartSignal, TestClock, TrackStatus};
+
+    fn idle_track() -> Status {
+        Status::Track(TrackStatus::default())
+    }
+
+    fn racing_track() -> Status {
+        Status::Track(TrackStatus::default().with_controller_count(2))
+    }
+
+    fn countdown() -> Status {
+        Status::Track(TrackStatus::default().with_start_signal(StartSignal::Three))
+    }
+
+    #[test]
+    fn starts_with_the_idle_interval() {
+        let poll_rate = AdaptivePollRate::new();
+
+        assert_eq!(poll_rate.phase(), PollPhase::Idle);
+        assert_eq!(poll_rate.interval(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn speeds_up_with_activity_on_the_track() {
+        let clock = TestClock::new();
+        let mut poll_rate = AdaptivePollRate::new();
+
+        assert_eq!(
+            poll_rate.update_status(&idle_track(), clock.now()),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            poll_rate.update_status(&racing_track(), clock.now()),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            poll_rate.update_status(&countdown(), clock.now()),
+            Duration::from_millis(20)
+        );
+
+        // laps during the start sequence keep the fastest rate
+        let lap = Status::Lap(LapStatus::default());
+        assert_eq!(
+            poll_rate.update_status(&lap, clock.now()),
+            Duration::from_millis(20)
+        );
+        assert_eq!(poll_rate.phase(), PollPhase::StartSequence);
+    }
+
+    #[test]
+    fn slows_down_after_the_linger_duration() {
+        let clock = TestClock::new();
+        let mut poll_rate = AdaptivePollRate::new().with_linger(Duration::from_secs(1));
+
+        poll_rate.update_status(&racing_track(), clock.now());
+        clock.advance(Duration::from_millis(999));
+        assert_eq!(
+            poll_rate.update_status(&idle_track(), clock.now()),
+            Duration::from_millis(50)
+        );
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(
+            poll_rate.update_status(&Status::NoData, clock.now()),
+            Duration::from_millis(500)
+        );
+        assert_eq!(poll_rate.phase(), PollPhase::Idle);
+    }
+
+    #[test]
+    fn stays_within_the_configured_intervals() {
+        let clock = TestClock::new();
+        let fastest = Duration::from_millis(10);
+        let slowest = Duration::from_secs(2);
+        let mut poll_rate = AdaptivePollRate::new()
+            .with_start_interval(fastest)
+            .with_racing_interval(Duration::from_millis(100))
+            .with_idle_interval(slowest)
+            .with_linger(Duration::from_millis(300));
+
+        let statuses = [countdown(), racing_track(), idle_track(), Status::NoData];
+        for status in statuses.iter().cycle().take(40) {
+            let interval = poll_rate.update_status(status, clock.now());
+            assert!(fastest <= interval && interval <= slowest);
+            clock.advance(Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn returns_to_idle_on_reset() {
+        let clock = TestClock::new();
+        let mut poll_rate = AdaptivePollRate::new();
+        poll_rate.update_status(&countdown(), clock.now());
+
+        poll_rate.reset();
+        assert_eq!(poll_rate.phase(), PollPhase::Idle);
+        assert_eq!(
+            poll_rate.update_status(&idle_track(), clock.now()),
+            Duration::from_millis(500)
+        );
+    }
+}
@@ -0,0 +1,26 @@
+//! Module which re-exports the types most applications need, import it with
+//! `use carrlink::prelude::*;`.
+//!
+//! With the `ble` feature the prelude also contains the few btleplug items which are needed to
+//! discover a control unit, so applications do not have to depend on btleplug themselves.
+
+pub use crate::{
+    BrakeLevel, CarSettings, ControllerId, FuelLevel, FuelMode, LapStatus, LapTime, SpeedLevel,
+    StartDetector, StartEvent, StartSignal, Status, TowerPosition, TrackStatus,
+    MAX_CONTROLLER_COUNT,
+};
+
+#[cfg(feature = "std")]
+pub use crate::{Backend, ControlUnit, CuProfile, Error, Lap, RaceSession, RequestBatch, Standing};
+
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+pub use crate::{
+    connect_any_ble, discover_first_ble, first_adapter, BackendBLE, BleDiscovery, Transport,
+};
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+pub use btleplug::api::{Central as _, Manager as _, Peripheral as _};
+#[cfg(all(feature = "ble", not(target_arch = "wasm32")))]
+pub use btleplug::platform::{Adapter, Manager};
+
+#[cfg(all(target_arch = "wasm32", feature = "web-bluetooth"))]
+pub use crate::WebBluetoothBackend;
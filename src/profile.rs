@@ -0,0 +1,53 @@
+//! Module which describes the firmware of a connected control unit.
+
+/// Protocol profile of a control unit which is determined from its firmware version.
+///
+/// The commands are known for the control units which report their firmware as number, e.g.
+/// "5337", and all of these support the complete command set. Control units which report a
+/// version in another format are not known to speak the same protocol, so the
+/// [`ControlUnit`](crate::ControlUnit) rejects requests which change their state with
+/// [`Error::NotSupported`](crate::Error::NotSupported) instead of risking to corrupt it.
+/// Status and version requests are sent to every control unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolProfile {
+    version: String,
+}
+
+impl ProtocolProfile {
+    /// Creates the profile for the given firmware version as reported by the control unit.
+    pub fn new(version: String) -> ProtocolProfile {
+        ProtocolProfile { version }
+    }
+
+    /// Returns the firmware version as reported by the control unit.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the firmware version as number, if the control unit reported a numeric version.
+    pub fn firmware(&self) -> Option<u32> {
+        self.version.parse::<u32>().ok()
+    }
+
+    /// Determines if the firmware is known to support the commands which change the state of
+    /// the control unit, e.g. button presses and word writes.
+    pub fn supports_commands(&self) -> bool {
+        self.firmware().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supports_commands_only_for_numeric_firmware() {
+        let known = ProtocolProfile::new("5337".to_owned());
+        assert_eq!(known.firmware(), Some(5337));
+        assert!(known.supports_commands());
+
+        let unknown = ProtocolProfile::new("A337".to_owned());
+        assert_eq!(unknown.firmware(), None);
+        assert!(!unknown.supports_commands());
+    }
+}
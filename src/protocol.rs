@@ -0,0 +1,201 @@
+//! Module with the low level encoding primitives of the control unit protocol.
+//! The control unit transmits all values as printable nibbles, i.e. every byte
+//! on the wire carries four bits of information on top of a common base.
+
+// All values accepted by the control unit have to be added on top of this base.
+const VALUE_BASE: u8 = b'0';
+
+/// Number of bytes which are used to transmit a 32-bit value.
+pub const UINT32_SIZE: usize = 8;
+
+/// Capacity of a [`Frame`], which exceeds the longest response of the control unit.
+pub const MAX_FRAME_SIZE: usize = 32;
+
+/// Response of the control unit in a buffer of fixed capacity, so polling does not allocate.
+pub type Frame = arrayvec::ArrayVec<u8, MAX_FRAME_SIZE>;
+
+/// Order in which the nibbles of a 32-bit value are transmitted, given as bit shift of each nibble.
+const UINT32_NIBBLE_SHIFTS: [u32; UINT32_SIZE] = [24, 28, 16, 20, 8, 12, 0, 4];
+
+/// Strictness which is applied when validating the checksums of responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChecksumValidation {
+    /// Responses with invalid checksums are rejected.
+    #[default]
+    Strict,
+
+    /// Responses with invalid checksums are accepted, but a warning is logged.
+    WarnOnly,
+
+    /// Checksums of responses are not validated at all.
+    Off,
+}
+
+/// Address of a word which can be written on the control unit.
+/// Player specific words carry the player index in range [0, 7].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordAddress {
+    /// Speed level of a player.
+    Speed(u8),
+
+    /// Brake level of a player.
+    Brake(u8),
+
+    /// Fuel level of a player.
+    Fuel(u8),
+
+    /// Position of a player displayed on the position tower.
+    Position(u8),
+
+    /// Upper nibble of the lap displayed on the position tower.
+    LapHigh,
+
+    /// Lower nibble of the lap displayed on the position tower.
+    LapLow,
+}
+
+impl WordAddress {
+    /// Player address which is used for words which do not refer to a specific player.
+    const GLOBAL_PLAYER: u8 = 0x07;
+
+    /// Encodes the address as byte, with the player in the upper three bits and the word offset in the lower five bits.
+    pub const fn encode(self) -> u8 {
+        const fn encode_player_address(offset: u8, player: u8) -> u8 {
+            let player_mask: u8 = 0x07;
+            let offset_mask: u8 = 0x1F;
+            ((player & player_mask) << 5) | (offset & offset_mask)
+        }
+
+        match self {
+            WordAddress::Speed(player) => encode_player_address(0x00, player),
+            WordAddress::Brake(player) => encode_player_address(0x01, player),
+            WordAddress::Fuel(player) => encode_player_address(0x02, player),
+            WordAddress::Position(player) => encode_player_address(0x06, player),
+            WordAddress::LapHigh => encode_player_address(0x11, Self::GLOBAL_PLAYER),
+            WordAddress::LapLow => encode_player_address(0x12, Self::GLOBAL_PLAYER),
+        }
+    }
+
+    /// Returns how often the control unit repeats the word write on the track.
+    pub const fn repetitions(self) -> u8 {
+        match self {
+            WordAddress::Speed(_) | WordAddress::Brake(_) | WordAddress::Fuel(_) => 0x02,
+            WordAddress::Position(_) | WordAddress::LapHigh | WordAddress::LapLow => 0x01,
+        }
+    }
+}
+
+/// Encodes the lower nibble of the given value as byte on the wire.
+pub const fn encode_nibble(value: u8) -> u8 {
+    VALUE_BASE + (value & 0x0F)
+}
+
+/// Decodes a nibble from the given byte on the wire.
+pub const fn decode_nibble(data: u8) -> u8 {
+    data & 0x0F
+}
+
+/// Encodes the given 32-bit value as it is transmitted by the control unit.
+pub const fn encode_uint32(value: u32) -> [u8; UINT32_SIZE] {
+    let mut result = [0; UINT32_SIZE];
+    let mut i = 0;
+    while i < UINT32_SIZE {
+        result[i] = encode_nibble((value >> UINT32_NIBBLE_SHIFTS[i]) as u8);
+        i += 1;
+    }
+    result
+}
+
+/// Decodes a 32-bit value as it is transmitted by the control unit.
+pub const fn decode_uint32(data: &[u8; UINT32_SIZE]) -> u32 {
+    let mut result = 0;
+    let mut i = 0;
+    while i < UINT32_SIZE {
+        result |= (decode_nibble(data[i]) as u32) << UINT32_NIBBLE_SHIFTS[i];
+        i += 1;
+    }
+    result
+}
+
+/// Computes the checksum of the given slice of data.
+pub const fn compute_checksum(data: &[u8]) -> u8 {
+    let mut sum: u32 = 0;
+    let mut i = 0;
+    while i < data.len() {
+        sum += data[i] as u32;
+        i += 1;
+    }
+    (sum & 0x0F) as u8
+}
+
+/// Creates a request which writes the lower nibble of the given value to the given word address.
+pub const fn make_set_word_request(address: WordAddress, value: u8) -> [u8; 6] {
+    let encoded_address = address.encode();
+    let mut result: [u8; 6] = [
+        b'J',
+        encode_nibble(encoded_address),
+        encode_nibble(encoded_address >> 4),
+        encode_nibble(value),
+        encode_nibble(address.repetitions()),
+        0,
+    ];
+
+    result[5] = compute_checksum(result.split_at(5).0);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn encodes_nibbles_as_printable_characters() {
+        for value in 0..=0x0F {
+            let encoded = encode_nibble(value);
+            assert!(encoded.is_ascii_graphic());
+            assert_eq!(decode_nibble(encoded), value);
+        }
+        assert_eq!(encode_nibble(0x0A), b':');
+        assert_eq!(encode_nibble(0x0F), b'?');
+    }
+
+    #[test]
+    fn encodes_uint32_in_the_nibble_order_of_the_control_unit() {
+        // timer of the lap status `?100102>0417`, i.e. 123456 ms
+        assert_eq!(&encode_uint32(123_456), b"00102>04");
+        assert_eq!(decode_uint32(b"00102>04"), 123_456);
+
+        assert_eq!(&encode_uint32(0x1234_5678), b"21436587");
+        assert_eq!(&encode_uint32(u32::MAX), b"????????");
+    }
+
+    #[test]
+    fn encodes_set_word_requests() {
+        assert_eq!(
+            make_set_word_request(WordAddress::Speed(1), 8),
+            *b"J0282\x06"
+        );
+        assert_eq!(
+            make_set_word_request(WordAddress::Position(0), 9),
+            *b"J6091\x0A"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn uint32_round_trips(value: u32) {
+            prop_assert_eq!(decode_uint32(&encode_uint32(value)), value);
+        }
+
+        #[test]
+        fn uint32_decoding_ignores_the_value_base(value: u32) {
+            // only the lower nibble of every byte carries information
+            let mut data = encode_uint32(value);
+            for byte in data.iter_mut() {
+                *byte &= 0x0F;
+            }
+            prop_assert_eq!(decode_uint32(&data), value);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Module which shares a locally connected backend with remote applications over TCP.
+//!
+//! Every frame consists of its type, the length of its payload as big endian `u16` and the payload.
+//!
+//! | Frame        | Type | Direction        | Payload                                  |
+//! |--------------|------|------------------|------------------------------------------|
+//! | Request      | 1    | client to server | timeout in ms `u32`, request of the CU   |
+//! | Response     | 2    | server to client | response of the CU                       |
+//! | Error        | 3    | server to client | [`Error::code`] `u32`, message as UTF-8  |
+//! | IsConnected  | 4    | client to server |                                          |
+//! | Connected    | 5    | server to client | `1` if the backend is connected, else `0`|
+//!
+//! Clients may pipeline requests, the server answers the frames of a connection in their order.
+
+use crate::backend::{batch_timeout, to_frame};
+use crate::{Backend, DecodeError, Error, Frame, Operation};
+use async_trait::async_trait;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+
+const REQUEST_FRAME: u8 = 1;
+const RESPONSE_FRAME: u8 = 2;
+const ERROR_FRAME: u8 = 3;
+const IS_CONNECTED_FRAME: u8 = 4;
+const CONNECTED_FRAME: u8 = 5;
+
+/// Byte stream between client and server, which is a TCP connection outside of tests.
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Sync> Connection for T {}
+
+async fn write_frame<S: AsyncWrite + Unpin + ?Sized>(
+    stream: &mut S,
+    frame_type: u8,
+    payload: &[u8],
+) -> io::Result<()> {
+    let length = u16::try_from(payload.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "payload exceeds the frame size",
+        )
+    })?;
+
+    let mut frame = Vec::with_capacity(payload.len() + 3);
+    frame.push(frame_type);
+    frame.extend_from_slice(&length.to_be_bytes());
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame).await
+}
+
+async fn read_frame<S: AsyncRead + Unpin + ?Sized>(stream: &mut S) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 3];
+    stream.read_exact(&mut header).await?;
+
+    let mut payload = vec![0u8; u16::from_be_bytes([header[1], header[2]]) as usize];
+    stream.read_exact(&mut payload).await?;
+    Ok((header[0], payload))
+}
+
+/// Serves the backend to remote applications connecting to the listener.
+/// The backend has to be connected already, requests of all clients are forwarded one after another.
+pub async fn serve_backend<T: Backend + Sync + 'static>(
+    backend: Arc<Mutex<T>>,
+    listener: TcpListener,
+) -> io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        log::debug!("remote client {} connected", peer);
+        let _ = stream.set_nodelay(true);
+        tokio::spawn(serve_client(Arc::clone(&backend), stream));
+    }
+}
+
+async fn serve_client<T: Backend + Sync, S: AsyncRead + AsyncWrite + Unpin>(
+    backend: Arc<Mutex<T>>,
+    mut stream: S,
+) {
+    loop {
+        let (frame_type, payload) = match read_frame(&mut stream).await {
+            Ok(frame) => frame,
+            // the client disconnected
+            Err(_) => return,
+        };
+
+        let result = match frame_type {
+            REQUEST_FRAME if payload.len() >= 4 => {
+                let timeout = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                let timeout = Duration::from_millis(timeout as u64);
+                backend
+                    .lock()
+                    .await
+                    .request(&payload[4..], timeout)
+                    .await
+                    .map(|response| (RESPONSE_FRAME, response))
+            }
+            // the timeout is missing, report the length the client sent
+            REQUEST_FRAME => Err(Error::InvalidResponse {
+                raw: payload,
+                reason: DecodeError::WrongLength,
+            }),
+            IS_CONNECTED_FRAME => backend
+                .lock()
+                .await
+                .is_connected()
+                .await
+                .map(|is_connected| (CONNECTED_FRAME, Frame::from_iter([is_connected as u8]))),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown frame type {}",
+                frame_type
+            ))),
+        };
+
+        let written = match result {
+            Ok((frame_type, payload)) => write_frame(&mut stream, frame_type, &payload).await,
+            Err(error) => {
+                let mut payload = error.code().to_be_bytes().to_vec();
+                payload.extend_from_slice(error.detail().as_bytes());
+                write_frame(&mut stream, ERROR_FRAME, &payload).await
+            }
+        };
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+/// Backend which uses a backend shared by [`serve_backend`] on another machine.
+pub struct RemoteBackend {
+    address: String,
+    stream: Option<Box<dyn Connection>>,
+}
+
+impl RemoteBackend {
+    /// Creates a backend which connects to the server at the given address, e.g. "192.168.0.10:7448".
+    pub fn new(address: &str) -> RemoteBackend {
+        RemoteBackend {
+            address: address.to_owned(),
+            stream: None,
+        }
+    }
+
+    async fn exchange(&mut self, frame_type: u8, payload: &[u8]) -> crate::Result<(u8, Vec<u8>)> {
+        let stream = self.stream.as_mut().ok_or(Error::NotConnected)?;
+        let result = async {
+            write_frame(stream, frame_type, payload).await?;
+            read_frame(stream).await
+        }
+        .await;
+
+        match result {
+            Ok((ERROR_FRAME, payload)) => Err(remote_error(&payload)),
+            Ok(frame) => Ok(frame),
+            Err(error) => {
+                self.stream = None;
+                Err(Error::Other(Box::new(error)))
+            }
+        }
+    }
+}
+
+/// Restores the error which was reported by the server.
+fn remote_error(payload: &[u8]) -> Error {
+    if payload.len() < 4 {
+        return Error::RuntimeError("malformed error of the remote backend".to_owned());
+    }
+
+    let code = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+    let message = String::from_utf8_lossy(&payload[4..]).into_owned();
+    Error::from_code(code, message)
+}
+
+/// Creates the payload of a request frame.
+fn request_payload(data: &[u8], timeout: Duration) -> Vec<u8> {
+    let mut payload = (timeout.as_millis().min(u32::MAX as u128) as u32)
+        .to_be_bytes()
+        .to_vec();
+    payload.extend_from_slice(data);
+    payload
+}
+
+/// Extracts the response of the control unit from a frame which was received for a request.
+fn response_frame(frame_type: u8, payload: &[u8]) -> crate::Result<Frame> {
+    match frame_type {
+        RESPONSE_FRAME => to_frame(payload),
+        ERROR_FRAME => Err(remote_error(payload)),
+        frame_type => Err(Error::RuntimeError(format!(
+            "unexpected frame type {} from the remote backend",
+            frame_type
+        ))),
+    }
+}
+
+fn request_timed_out(timeout: Duration) -> Error {
+    Error::TimedOut {
+        operation: Operation::Request,
+        timeout,
+    }
+}
+
+async fn connect_stream<A: ToSocketAddrs>(address: A) -> crate::Result<TcpStream> {
+    let stream = TcpStream::connect(address)
+        .await
+        .map_err(|error| Error::Other(Box::new(error)))?;
+    let _ = stream.set_nodelay(true);
+    Ok(stream)
+}
+
+#[async_trait]
+impl Backend for RemoteBackend {
+    async fn connect(&mut self) -> crate::Result<()> {
+        self.stream = Some(Box::new(connect_stream(self.address.as_str()).await?));
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        self.stream = None;
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        Ok(self.stream.is_some())
+    }
+
+    async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+        let payload = request_payload(data, timeout);
+
+        // the server applies the timeout to the control unit, allow for the network on top
+        let exchange = self.exchange(REQUEST_FRAME, &payload);
+        match tokio::time::timeout(timeout * 2, exchange).await {
+            Ok(Ok((frame_type, response))) => response_frame(frame_type, &response),
+            Ok(Err(error)) => Err(error),
+            Err(_) => {
+                self.stream = None;
+                Err(request_timed_out(timeout))
+            }
+        }
+    }
+
+    /// Writes all requests back-to-back before reading the responses, which the server
+    /// returns in the order of the requests.
+    async fn request_batch(
+        &mut self,
+        requests: &[&[u8]],
+        timeout: Duration,
+    ) -> crate::Result<Vec<Frame>> {
+        let stream = self.stream.as_mut().ok_or(Error::NotConnected)?;
+        let exchange = async {
+            for request in requests {
+                write_frame(stream, REQUEST_FRAME, &request_payload(request, timeout)).await?;
+            }
+
+            let mut responses = Vec::with_capacity(requests.len());
+            for _ in requests {
+                responses.push(read_frame(stream).await?);
+            }
+            Ok::<_, io::Error>(responses)
+        };
+
+        // the server forwards the requests one after another
+        match tokio::time::timeout(batch_timeout(timeout, requests.len()), exchange).await {
+            Ok(Ok(responses)) => responses
+                .iter()
+                .map(|(frame_type, response)| response_frame(*frame_type, response))
+                .collect(),
+            Ok(Err(error)) => {
+                self.stream = None;
+                Err(Error::Other(Box::new(error)))
+            }
+            Err(_) => {
+                self.stream = None;
+                Err(request_timed_out(timeout))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::DuplexStream;
+
+    /// Backend which answers the version request and fails all other requests.
+    struct VersionBackend;
+
+    #[async_trait]
+    impl Backend for VersionBackend {
+        async fn connect(&mut self) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> crate::Result<()> {
+            Ok(())
+        }
+
+        async fn is_connected(&self) -> crate::Result<bool> {
+            Ok(true)
+        }
+
+        async fn request(&mut self, data: &[u8], timeout: Duration) -> crate::Result<Frame> {
+            match data {
+                b"0" => to_frame(b"053372"),
+                _ => Err(request_timed_out(timeout)),
+            }
+        }
+    }
+
+    /// Serves the backend on one end of an in-memory stream and returns the other end.
+    fn serve_in_memory() -> DuplexStream {
+        let (client, server) = tokio::io::duplex(256);
+        tokio::spawn(serve_client(Arc::new(Mutex::new(VersionBackend)), server));
+        client
+    }
+
+    fn remote_backend(stream: DuplexStream) -> RemoteBackend {
+        RemoteBackend {
+            address: String::new(),
+            stream: Some(Box::new(stream)),
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_requests_and_their_errors() {
+        let mut backend = remote_backend(serve_in_memory());
+        let timeout = Duration::from_millis(250);
+
+        let response = backend.request(b"0", timeout).await.unwrap();
+        assert_eq!(response.as_slice(), b"053372");
+        assert!(backend.is_connected().await.unwrap());
+
+        let error = backend.request(b"?", timeout).await.unwrap_err();
+        assert!(matches!(
+            error,
+            Error::TimedOut {
+                operation: Operation::Request,
+                timeout: restored,
+            } if restored == timeout
+        ));
+    }
+
+    #[tokio::test]
+    async fn answers_pipelined_requests_in_order() {
+        let mut backend = remote_backend(serve_in_memory());
+        let requests: [&[u8]; 3] = [b"0", b"0", b"0"];
+
+        let responses = backend
+            .request_batch(&requests, Duration::from_millis(250))
+            .await
+            .unwrap();
+        assert_eq!(responses.len(), 3);
+        assert!(responses
+            .iter()
+            .all(|response| response.as_slice() == b"053372"));
+    }
+
+    #[tokio::test]
+    async fn reports_the_connection_state_of_the_backend() {
+        let mut stream = serve_in_memory();
+
+        write_frame(&mut stream, IS_CONNECTED_FRAME, &[])
+            .await
+            .unwrap();
+        let (frame_type, payload) = read_frame(&mut stream).await.unwrap();
+        assert_eq!(frame_type, CONNECTED_FRAME);
+        assert_eq!(payload, [1]);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_timeout() {
+        let mut stream = serve_in_memory();
+
+        write_frame(&mut stream, REQUEST_FRAME, &[0, 0])
+            .await
+            .unwrap();
+        let (frame_type, payload) = read_frame(&mut stream).await.unwrap();
+        assert_eq!(frame_type, ERROR_FRAME);
+        assert!(matches!(
+            remote_error(&payload),
+            Error::InvalidResponse {
+                raw,
+                reason: DecodeError::WrongLength,
+            } if raw.len() == 2
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_frame_types() {
+        let mut stream = serve_in_memory();
+
+        write_frame(&mut stream, 42, &[]).await.unwrap();
+        let (frame_type, payload) = read_frame(&mut stream).await.unwrap();
+        assert_eq!(frame_type, ERROR_FRAME);
+        assert!(matches!(remote_error(&payload), Error::InvalidArgument(_)));
+    }
+}
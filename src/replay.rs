@@ -0,0 +1,85 @@
+//! Module which replays telemetry logs into a race session.
+
+use crate::{RaceSession, TelemetryEvent, TelemetryRecord};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::time::Duration;
+
+/// Replays a log which was written by the [`TelemetryLogger`](crate::TelemetryLogger).
+///
+/// The logged statuses are fed into a [`RaceSession`] with the original timing between them,
+/// optionally accelerated. Laps are derived again by the session instead of being taken from the
+/// log, so changes to the race logic can be checked against recorded races.
+pub struct SessionReplayer {
+    records: Vec<TelemetryRecord>,
+    speed: f64,
+}
+
+impl SessionReplayer {
+    /// Reads all records of the telemetry log at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SessionReplayer> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                records.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(Self::from_records(records))
+    }
+
+    /// Creates a replayer for the given records, which have to be ordered by their timestamp.
+    pub fn from_records(records: Vec<TelemetryRecord>) -> SessionReplayer {
+        SessionReplayer {
+            records,
+            speed: 1.0,
+        }
+    }
+
+    /// Sets the factor by which the replay is accelerated, e.g. 2.0 replays at double speed.
+    /// An infinite factor replays all records without any delay.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Returns the records of the replay.
+    pub fn records(&self) -> &[TelemetryRecord] {
+        &self.records
+    }
+
+    /// Replays all records into the session.
+    /// The callback receives every replayed status and start event and every lap the session derived.
+    pub async fn replay<F: FnMut(&TelemetryEvent)>(
+        &self,
+        session: &mut RaceSession,
+        mut callback: F,
+    ) {
+        let mut previous_timestamp = None;
+
+        for record in &self.records {
+            if let Some(previous_timestamp) = previous_timestamp {
+                let delay =
+                    Duration::from_millis(record.timestamp_ms.saturating_sub(previous_timestamp));
+                if self.speed.is_finite() && self.speed > 0.0 && !delay.is_zero() {
+                    tokio::time::sleep(delay.div_f64(self.speed)).await;
+                }
+            }
+            previous_timestamp = Some(record.timestamp_ms);
+
+            match &record.event {
+                TelemetryEvent::Status(status) => {
+                    callback(&record.event);
+                    if let Some(lap) = session.update(status) {
+                        callback(&TelemetryEvent::Lap(lap));
+                    }
+                }
+                // laps are derived by the session again
+                TelemetryEvent::Lap(_) => {}
+                event => callback(event),
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Module which collects requests that are sent to the control unit at once.
+
+use crate::messages::{make_ignore_controllers_request, make_reset_positions_request};
+use crate::protocol::{make_set_word_request, WordAddress};
+use crate::{BrakeLevel, CarSettings, ControllerId, Frame, FuelLevel, SpeedLevel, TowerPosition};
+
+/// Requests which are pipelined to the control unit with [`ControlUnit::send_batch`](crate::ControlUnit::send_batch).
+///
+/// Backends which support pipelining write the requests back-to-back and match the responses by
+/// their order, which saves most of the round trips when e.g. configuring all cars before a race.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestBatch {
+    requests: Vec<Frame>,
+}
+
+impl RequestBatch {
+    /// Creates an empty batch.
+    pub fn new() -> RequestBatch {
+        RequestBatch::default()
+    }
+
+    /// Returns the number of requests in the batch.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Determines if the batch contains no requests.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Returns the requests in the order they are sent.
+    pub(crate) fn requests(&self) -> &[Frame] {
+        &self.requests
+    }
+
+    fn push(&mut self, request: &[u8]) -> &mut Self {
+        self.requests.push(request.iter().copied().collect());
+        self
+    }
+
+    /// Adds a request which sets the speed level of the given player to the given value.
+    pub fn set_speed_level(&mut self, player: ControllerId, speed: SpeedLevel) -> &mut Self {
+        let request = make_set_word_request(WordAddress::Speed(player.address()), speed.value());
+        self.push(&request)
+    }
+
+    /// Adds a request which sets the brake level of the given player to the given value.
+    pub fn set_brake_level(&mut self, player: ControllerId, brake: BrakeLevel) -> &mut Self {
+        let request = make_set_word_request(WordAddress::Brake(player.address()), brake.value());
+        self.push(&request)
+    }
+
+    /// Adds a request which sets the fuel level of the given player to the given value.
+    pub fn set_fuel_level(&mut self, player: ControllerId, fuel: FuelLevel) -> &mut Self {
+        let request = make_set_word_request(WordAddress::Fuel(player.address()), fuel.value());
+        self.push(&request)
+    }
+
+    /// Adds the requests which set speed, brake and fuel level of the given player.
+    pub fn set_car_settings(&mut self, player: ControllerId, settings: CarSettings) -> &mut Self {
+        self.set_speed_level(player, settings.speed)
+            .set_brake_level(player, settings.brake)
+            .set_fuel_level(player, settings.fuel)
+    }
+
+    /// Adds a request which sets the position of the given player displayed on the position tower.
+    pub fn set_position(&mut self, player: ControllerId, position: TowerPosition) -> &mut Self {
+        let request =
+            make_set_word_request(WordAddress::Position(player.address()), position.raw());
+        self.push(&request)
+    }
+
+    /// Adds a request which resets the positions displayed on the position tower.
+    pub fn reset_positions(&mut self) -> &mut Self {
+        self.push(&make_reset_positions_request())
+    }
+
+    /// Adds a request which makes the control unit ignore the controllers set in the mask.
+    pub fn ignore_controllers(&mut self, mask: u8) -> &mut Self {
+        self.push(&make_ignore_controllers_request(mask))
+    }
+}
@@ -0,0 +1,162 @@
+//! Module which implements an HTTP API to remotely control a race.
+
+use crate::{
+    Backend, BrakeLevel, ControlUnit, ControllerId, Error, FuelLevel, LiveTimingMessage,
+    RaceSession, SpeedLevel,
+};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use std::sync::{Arc, Mutex};
+
+struct ApiState<T: Backend> {
+    control_unit: Arc<tokio::sync::Mutex<ControlUnit<T>>>,
+    session: Arc<Mutex<RaceSession>>,
+}
+
+impl<T: Backend> Clone for ApiState<T> {
+    fn clone(&self) -> Self {
+        ApiState {
+            control_unit: Arc::clone(&self.control_unit),
+            session: Arc::clone(&self.session),
+        }
+    }
+}
+
+/// Body of the requests which set a level.
+#[derive(serde::Deserialize)]
+struct LevelBody {
+    level: u8,
+}
+
+/// Error of the API, which is returned as JSON body with the stable error code and name.
+struct ApiError(Error);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            Error::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            Error::NotSupported(_) => StatusCode::NOT_IMPLEMENTED,
+            Error::NotConnected | Error::DeviceNotFound => StatusCode::SERVICE_UNAVAILABLE,
+            Error::TimedOut { .. } => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        let body = serde_json::json!({
+            "code": self.0.code(),
+            "error": self.0.name(),
+            "message": self.0.to_string(),
+        });
+
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(value: Error) -> Self {
+        ApiError(value)
+    }
+}
+
+type ApiResult = Result<StatusCode, ApiError>;
+
+/// Creates the router of the control API.
+///
+/// | Method | Path                             | Body             | Action                          |
+/// |--------|----------------------------------|------------------|---------------------------------|
+/// | POST   | `/race/start`                    |                  | starts the race countdown       |
+/// | POST   | `/buttons/{button}`              |                  | presses `enter`, `esc`, `speed`, `brake`, `fuel` or `code` |
+/// | PUT    | `/controllers/{controller}/speed`| `{"level": 0-15}`| sets the speed level            |
+/// | PUT    | `/controllers/{controller}/brake`| `{"level": 0-15}`| sets the brake level            |
+/// | PUT    | `/controllers/{controller}/fuel` | `{"level": 0-15}`| sets the fuel level             |
+/// | GET    | `/standings`                     |                  | returns the standings as [`LiveTimingMessage`] |
+///
+/// Errors are answered with a JSON body containing the [`Error::code`], [`Error::name`] and message.
+/// The session is shared with the application, which keeps feeding it with statuses.
+pub fn control_router<T: Backend + 'static>(
+    control_unit: Arc<tokio::sync::Mutex<ControlUnit<T>>>,
+    session: Arc<Mutex<RaceSession>>,
+) -> Router {
+    Router::new()
+        .route("/race/start", post(start_race::<T>))
+        .route("/buttons/:button", post(press_button::<T>))
+        .route("/controllers/:controller/speed", put(set_speed::<T>))
+        .route("/controllers/:controller/brake", put(set_brake::<T>))
+        .route("/controllers/:controller/fuel", put(set_fuel::<T>))
+        .route("/standings", get(standings::<T>))
+        .with_state(ApiState {
+            control_unit,
+            session,
+        })
+}
+
+/// Serves the router on the given listener until the server fails.
+pub async fn serve_control_api(
+    listener: tokio::net::TcpListener,
+    router: Router,
+) -> std::io::Result<()> {
+    axum::serve(listener, router).await
+}
+
+async fn start_race<T: Backend>(State(state): State<ApiState<T>>) -> ApiResult {
+    state.control_unit.lock().await.start().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn press_button<T: Backend>(
+    State(state): State<ApiState<T>>,
+    Path(button): Path<String>,
+) -> ApiResult {
+    let mut control_unit = state.control_unit.lock().await;
+    match button.as_str() {
+        "enter" => control_unit.press_enter().await?,
+        "esc" => control_unit.press_esc().await?,
+        "speed" => control_unit.press_speed().await?,
+        "brake" => control_unit.press_brake().await?,
+        "fuel" => control_unit.press_fuel().await?,
+        "code" => control_unit.press_code().await?,
+        _ => return Ok(StatusCode::NOT_FOUND),
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_speed<T: Backend>(
+    State(state): State<ApiState<T>>,
+    Path(controller): Path<ControllerId>,
+    Json(body): Json<LevelBody>,
+) -> ApiResult {
+    let level = SpeedLevel::try_from(body.level)?;
+    let mut control_unit = state.control_unit.lock().await;
+    control_unit.set_speed_level(controller, level).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_brake<T: Backend>(
+    State(state): State<ApiState<T>>,
+    Path(controller): Path<ControllerId>,
+    Json(body): Json<LevelBody>,
+) -> ApiResult {
+    let level = BrakeLevel::try_from(body.level)?;
+    let mut control_unit = state.control_unit.lock().await;
+    control_unit.set_brake_level(controller, level).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_fuel<T: Backend>(
+    State(state): State<ApiState<T>>,
+    Path(controller): Path<ControllerId>,
+    Json(body): Json<LevelBody>,
+) -> ApiResult {
+    let level = FuelLevel::try_from(body.level)?;
+    let mut control_unit = state.control_unit.lock().await;
+    control_unit.set_fuel_level(controller, level).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn standings<T: Backend>(State(state): State<ApiState<T>>) -> Response {
+    match state.session.lock() {
+        Ok(session) => Json(LiveTimingMessage::standings(&session)).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
@@ -0,0 +1,361 @@
+//! Module which implements the session layer, which turns the lap statuses of a control unit
+//! into laps of the individual controllers.
+
+use crate::clock::SharedClock;
+use crate::{
+    Clock, ControllerId, Lap, LapStatus, LapTime, Status, TimerGroup, MAX_CONTROLLER_COUNT,
+};
+use std::time::Duration;
+
+/// Timing state of a single controller within a session.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ControllerTiming {
+    /// Timer when the controller crossed the finish line the last time.
+    lap_start: Option<LapTime>,
+
+    /// Timer when the controller crossed any sensor the last time.
+    last_crossing: Option<LapTime>,
+
+    /// Sector times of the lap which is currently driven.
+    splits: Vec<Duration>,
+
+    /// Number of laps which were completed.
+    lap_count: usize,
+}
+
+/// Position of a controller in the standings of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Standing {
+    /// Position in the standings, the leader has position 1.
+    pub position: usize,
+
+    /// Identifier of the controller.
+    pub controller: ControllerId,
+
+    /// Number of completed laps.
+    pub laps: usize,
+
+    /// Sum of the lap times of all completed laps.
+    pub total_time: Duration,
+
+    /// Time of the fastest valid lap.
+    pub best_lap: Option<Duration>,
+
+    /// Time of the last completed lap.
+    pub last_lap: Option<Duration>,
+}
+
+/// Race session which tracks the laps of all controllers.
+///
+/// With the `serde` feature the laps, the timing of all controllers and the lap time limits can
+/// be saved to a file and loaded again, so a race can be resumed after the application was
+/// restarted. The reception times of the laps are saved as wall clock time. The clock is not
+/// saved, loaded sessions use the [`SystemClock`](crate::SystemClock). Drivers and penalties are
+/// not part of the session, applications which assign them have to save them on their own.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RaceSession {
+    controllers: [ControllerTiming; MAX_CONTROLLER_COUNT],
+    laps: Vec<Lap>,
+    min_lap_time: Duration,
+    max_lap_time: Duration,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock: SharedClock,
+}
+
+impl Default for RaceSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RaceSession {
+    /// Creates an empty session without minimum lap time and a maximum lap time of one hour.
+    pub fn new() -> RaceSession {
+        RaceSession {
+            controllers: Default::default(),
+            laps: Vec::new(),
+            min_lap_time: Duration::ZERO,
+            max_lap_time: Duration::from_secs(3600),
+            clock: SharedClock::default(),
+        }
+    }
+
+    /// Sets the minimum lap time, faster laps are recorded as invalid.
+    pub fn with_min_lap_time(mut self, min_lap_time: Duration) -> Self {
+        self.min_lap_time = min_lap_time;
+        self
+    }
+
+    /// Sets the maximum lap time.
+    /// Slower laps are considered as interrupted timing, e.g. by a reset of the clock,
+    /// and restart the timing of the controller instead of producing a lap.
+    pub fn with_max_lap_time(mut self, max_lap_time: Duration) -> Self {
+        self.max_lap_time = max_lap_time;
+        self
+    }
+
+    /// Sets the clock which timestamps laps whose status carries no reception time, e.g. statuses
+    /// which were not received via a [`ControlUnit`](crate::ControlUnit). The default is the
+    /// [`SystemClock`](crate::SystemClock).
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// Returns the minimum lap time of valid laps.
+    pub fn min_lap_time(&self) -> Duration {
+        self.min_lap_time
+    }
+
+    /// Returns the maximum lap time after which the timing of a controller is restarted.
+    pub fn max_lap_time(&self) -> Duration {
+        self.max_lap_time
+    }
+
+    /// Consumes the next status of the control unit.
+    /// Returns the completed lap if the status completed one.
+    pub fn update(&mut self, status: &Status) -> Option<Lap> {
+        match status {
+            Status::Lap(status) => self.update_lap(status),
+            _ => None,
+        }
+    }
+
+    /// Consumes the next lap status of the control unit.
+    /// Returns the completed lap if the status completed one.
+    pub fn update_lap(&mut self, status: &LapStatus) -> Option<Lap> {
+        let timing = self.controllers.get_mut(status.controller.index())?;
+
+        let since_last_crossing = match timing.last_crossing {
+            // the control unit repeats statuses, which must not be counted twice
+            Some(last_crossing) if last_crossing == status.time => return None,
+            Some(last_crossing) => Some(status.time.wrapping_delta(last_crossing)),
+            None => None,
+        };
+        timing.last_crossing = Some(status.time);
+
+        let lap_start = match (status.timer_group(), timing.lap_start) {
+            (TimerGroup::CheckLane(_), Some(_)) => {
+                timing.splits.extend(since_last_crossing);
+                return None;
+            }
+            (TimerGroup::CheckLane(_), None) => return None,
+            (TimerGroup::StartFinish, lap_start) => lap_start,
+        };
+
+        timing.lap_start = Some(status.time);
+        let lap_start = lap_start?;
+
+        let lap_time = status.time.wrapping_delta(lap_start);
+        if lap_time > self.max_lap_time {
+            timing.splits.clear();
+            return None;
+        }
+
+        let mut splits = std::mem::take(&mut timing.splits);
+        if !splits.is_empty() {
+            splits.extend(since_last_crossing);
+        }
+
+        timing.lap_count += 1;
+        let lap = Lap {
+            controller: status.controller,
+            number: timing.lap_count,
+            lap_time,
+            splits,
+            time: status.time,
+            received_at: status.received_at.or_else(|| self.clock.try_now()),
+            is_valid: lap_time >= self.min_lap_time,
+        };
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("carrlink_laps_recorded_total").increment(1);
+
+        self.laps.push(lap.clone());
+        Some(lap)
+    }
+
+    /// Returns all laps of the session in the order they were completed.
+    pub fn laps(&self) -> &[Lap] {
+        &self.laps
+    }
+
+    /// Returns the laps of the given controller in the order they were completed.
+    pub fn laps_of(&self, controller: ControllerId) -> impl Iterator<Item = &Lap> {
+        self.laps
+            .iter()
+            .filter(move |lap| lap.controller == controller)
+    }
+
+    /// Returns the number of laps which were completed by the given controller.
+    pub fn lap_count(&self, controller: ControllerId) -> usize {
+        self.controllers[controller.index()].lap_count
+    }
+
+    /// Returns the fastest valid lap of the given controller.
+    pub fn best_lap(&self, controller: ControllerId) -> Option<&Lap> {
+        self.laps_of(controller)
+            .filter(|lap| lap.is_valid)
+            .min_by_key(|lap| lap.lap_time)
+    }
+
+    /// Returns the standings of all controllers which completed at least one lap.
+    /// Controllers are ranked by the number of completed laps and then by their total time.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = ControllerId::ALL
+            .into_iter()
+            .filter(|controller| self.lap_count(*controller) > 0)
+            .map(|controller| Standing {
+                position: 0,
+                controller,
+                laps: self.lap_count(controller),
+                total_time: self.laps_of(controller).map(|lap| lap.lap_time).sum(),
+                best_lap: self.best_lap(controller).map(|lap| lap.lap_time),
+                last_lap: self.laps_of(controller).last().map(|lap| lap.lap_time),
+            })
+            .collect();
+
+        standings.sort_by(|lhs, rhs| {
+            rhs.laps
+                .cmp(&lhs.laps)
+                .then(lhs.total_time.cmp(&rhs.total_time))
+        });
+        for (index, standing) in standings.iter_mut().enumerate() {
+            standing.position = index + 1;
+        }
+
+        standings
+    }
+
+    /// Restarts the timing of all controllers without discarding the recorded laps.
+    /// Call this after the clock of the control unit was reset.
+    pub fn restart_timing(&mut self) {
+        for timing in self.controllers.iter_mut() {
+            timing.lap_start = None;
+            timing.last_crossing = None;
+            timing.splits.clear();
+        }
+    }
+
+    /// Saves the state of the session as JSON to the given path, see [`RaceSession`] for what is saved.
+    /// The file is replaced atomically, so a crash while saving does not corrupt a previous save.
+    #[cfg(feature = "serde")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.to_path_buf().into_os_string();
+        temporary.push(".tmp");
+
+        std::fs::write(&temporary, serde_json::to_vec(self)?)?;
+        std::fs::rename(&temporary, path)
+    }
+
+    /// Loads a session which was saved with [`save`](RaceSession::save).
+    #[cfg(feature = "serde")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<RaceSession> {
+        let data = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Discards all recorded laps and the timing of all controllers.
+    pub fn reset(&mut self) {
+        self.controllers = Default::default();
+        self.laps.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestClock;
+
+    fn crossing(controller: usize, millis: u32) -> LapStatus {
+        LapStatus::default()
+            .with_controller(ControllerId::ALL[controller])
+            .with_sector(1)
+            .with_time(LapTime::from_millis(millis))
+    }
+
+    #[test]
+    fn timestamps_laps_with_the_session_clock() {
+        let clock = TestClock::new();
+        let mut session = RaceSession::new().with_clock(clock.clone());
+
+        assert_eq!(session.update_lap(&crossing(0, 1_000)), None);
+        clock.advance(Duration::from_millis(12_500));
+        let lap = session.update_lap(&crossing(0, 13_500)).unwrap();
+
+        assert_eq!(lap.lap_time, Duration::from_millis(12_500));
+        assert_eq!(lap.received_at, Some(clock.now()));
+    }
+
+    #[test]
+    fn keeps_the_reception_time_of_statuses() {
+        let clock = TestClock::new();
+        let received_at = clock.now();
+        let mut session = RaceSession::new().with_clock(clock.clone());
+        clock.advance(Duration::from_secs(60));
+
+        session.update_lap(&crossing(0, 1_000).with_received_at(received_at));
+        let lap = session
+            .update_lap(&crossing(0, 9_000).with_received_at(received_at))
+            .unwrap();
+
+        assert_eq!(lap.received_at, Some(received_at));
+    }
+
+    #[test]
+    fn ranks_controllers_by_laps_and_total_time() {
+        let mut session = RaceSession::new().with_min_lap_time(Duration::from_secs(2));
+        for (controller, millis) in [(0, 0), (1, 0), (0, 5_000), (1, 4_000), (1, 5_000)] {
+            session.update_lap(&crossing(controller, millis));
+        }
+
+        let standings = session.standings();
+        assert_eq!(standings[0].controller, ControllerId::ALL[1]);
+        assert_eq!(standings[0].laps, 2);
+        assert_eq!(standings[1].controller, ControllerId::ALL[0]);
+        assert_eq!(
+            session.best_lap(ControllerId::ALL[1]).unwrap().lap_time,
+            Duration::from_secs(4)
+        );
+        assert!(
+            !session
+                .laps_of(ControllerId::ALL[1])
+                .last()
+                .unwrap()
+                .is_valid
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn resumes_a_saved_session() {
+        let clock = TestClock::new();
+        let mut session = RaceSession::new()
+            .with_min_lap_time(Duration::from_secs(2))
+            .with_clock(clock.clone());
+        session.update_lap(&crossing(0, 1_000));
+        clock.advance(Duration::from_millis(9_000));
+        session.update_lap(&crossing(0, 10_000));
+
+        let path =
+            std::env::temp_dir().join(format!("carrlink-session-{}.json", std::process::id()));
+        session.save(&path).unwrap();
+        let mut loaded = RaceSession::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.min_lap_time(), Duration::from_secs(2));
+        let (saved, restored) = (&session.laps()[0], &loaded.laps()[0]);
+        assert_eq!(restored.lap_time, saved.lap_time);
+        let (saved_at, restored_at) = (saved.received_at.unwrap(), restored.received_at.unwrap());
+        assert!(saved_at.max(restored_at) - saved_at.min(restored_at) < Duration::from_millis(100));
+
+        // the timing of the controller continues where it was saved
+        let lap = loaded.update_lap(&crossing(0, 18_000)).unwrap();
+        assert_eq!(lap.number, 2);
+        assert_eq!(lap.lap_time, Duration::from_millis(8_000));
+    }
+}
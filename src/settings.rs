@@ -0,0 +1,17 @@
+//! Module which defines the configurable settings of the cars on the track.
+
+use crate::{BrakeLevel, FuelLevel, SpeedLevel};
+
+/// Settings of a single car which are configured on the control unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CarSettings {
+    /// Speed level of the car.
+    pub speed: SpeedLevel,
+
+    /// Brake level of the car.
+    pub brake: BrakeLevel,
+
+    /// Fuel level of the car.
+    pub fuel: FuelLevel,
+}
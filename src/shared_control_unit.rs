@@ -0,0 +1,184 @@
+//! Module which implements a handle to share a control unit between tasks.
+
+use crate::{
+    Backend, BrakeLevel, CarSettings, ControlUnit, ControllerId, Error, FuelLevel, RequestBatch,
+    SpeedLevel, Status, TowerPosition,
+};
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Cloneable handle of a control unit whose methods take `&self`, e.g. to poll statuses in one
+/// task while another task handles commands of the user.
+///
+/// Every call locks the control unit for the duration of a single exchange. Waiting calls are
+/// served in the order they were issued, so a command waits for at most the exchange which is
+/// currently running, even if a poller issues status requests back to back.
+pub struct SharedControlUnit<T: Backend> {
+    inner: Arc<Mutex<ControlUnit<T>>>,
+}
+
+impl<T: Backend> Clone for SharedControlUnit<T> {
+    fn clone(&self) -> Self {
+        SharedControlUnit {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: Backend> SharedControlUnit<T> {
+    /// Wraps the control unit into a shared handle.
+    pub fn new(control_unit: ControlUnit<T>) -> SharedControlUnit<T> {
+        SharedControlUnit {
+            inner: Arc::new(Mutex::new(control_unit)),
+        }
+    }
+
+    /// Locks the control unit for exclusive access, e.g. to issue several requests without
+    /// other calls in between or to change its configuration.
+    pub async fn lock(&self) -> MutexGuard<'_, ControlUnit<T>> {
+        self.inner.lock().await
+    }
+
+    /// Connects the control unit with the configured backend.
+    pub async fn connect(&self) -> Result<(), Error> {
+        self.lock().await.connect().await
+    }
+
+    /// Disconnects the control unit from the configured backend.
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        self.lock().await.disconnect().await
+    }
+
+    /// Determines if the control unit is connected.
+    pub async fn is_connected(&self) -> Result<bool, Error> {
+        self.lock().await.is_connected().await
+    }
+
+    /// Reads the current status during a race.
+    pub async fn get_status(&self) -> Result<Status, Error> {
+        self.lock().await.get_status().await
+    }
+
+    /// Reads all statuses which are currently queued on the control unit.
+    /// The control unit stays locked until all statuses are read.
+    pub async fn get_pending_statuses(&self) -> Result<Vec<Status>, Error> {
+        self.lock().await.get_pending_statuses().await
+    }
+
+    /// Reads the firmware version of the control unit.
+    pub async fn get_version(&self) -> Result<String, Error> {
+        self.lock().await.get_version().await
+    }
+
+    /// Pings the control unit if it was idle for the configured keepalive duration.
+    pub async fn keep_alive(&self) -> Result<bool, Error> {
+        self.lock().await.keep_alive().await
+    }
+
+    /// Causes a press of the enter button of the control unit.
+    pub async fn press_enter(&self) -> Result<(), Error> {
+        self.lock().await.press_enter().await
+    }
+
+    /// Causes a press of the escape button of the control unit.
+    pub async fn press_esc(&self) -> Result<(), Error> {
+        self.lock().await.press_esc().await
+    }
+
+    /// Causes a press of the speed button of the control unit.
+    pub async fn press_speed(&self) -> Result<(), Error> {
+        self.lock().await.press_speed().await
+    }
+
+    /// Causes a press of the brake button of the control unit.
+    pub async fn press_brake(&self) -> Result<(), Error> {
+        self.lock().await.press_brake().await
+    }
+
+    /// Causes a press of the fuel button of the control unit.
+    pub async fn press_fuel(&self) -> Result<(), Error> {
+        self.lock().await.press_fuel().await
+    }
+
+    /// Causes a press of the code button of the control unit.
+    pub async fn press_code(&self) -> Result<(), Error> {
+        self.lock().await.press_code().await
+    }
+
+    /// Resets the positions of the players displayed on the position tower.
+    pub async fn reset_positions(&self) -> Result<(), Error> {
+        self.lock().await.reset_positions().await
+    }
+
+    /// Sets the position of the given player displayed on the position tower.
+    pub async fn set_position(
+        &self,
+        player: ControllerId,
+        position: TowerPosition,
+    ) -> Result<(), Error> {
+        self.lock().await.set_position(player, position).await
+    }
+
+    /// Makes the control unit ignore the controllers whose bits are set in the given mask.
+    pub async fn ignore_controllers(&self, mask: u8) -> Result<(), Error> {
+        self.lock().await.ignore_controllers(mask).await
+    }
+
+    /// Starts a race, or the countdown of a race, which is the same as pressing the enter button.
+    pub async fn start(&self) -> Result<(), Error> {
+        self.lock().await.start().await
+    }
+
+    /// Resets the clock for all players.
+    pub async fn reset_clock(&self) -> Result<(), Error> {
+        self.lock().await.reset_clock().await
+    }
+
+    /// Sets the speed level of the given player to the given value.
+    pub async fn set_speed_level(
+        &self,
+        player: ControllerId,
+        speed: SpeedLevel,
+    ) -> Result<(), Error> {
+        self.lock().await.set_speed_level(player, speed).await
+    }
+
+    /// Sets the brake level of the given player to the given value.
+    pub async fn set_brake_level(
+        &self,
+        player: ControllerId,
+        brake: BrakeLevel,
+    ) -> Result<(), Error> {
+        self.lock().await.set_brake_level(player, brake).await
+    }
+
+    /// Sets the fuel level of the given player to the given value.
+    pub async fn set_fuel_level(&self, player: ControllerId, fuel: FuelLevel) -> Result<(), Error> {
+        self.lock().await.set_fuel_level(player, fuel).await
+    }
+
+    /// Sets speed, brake and fuel level of the given player at once.
+    pub async fn set_car_settings(
+        &self,
+        player: ControllerId,
+        settings: CarSettings,
+    ) -> Result<(), Error> {
+        self.lock().await.set_car_settings(player, settings).await
+    }
+
+    /// Sends all requests of the batch, the control unit stays locked until the batch is done.
+    pub async fn send_batch(&self, batch: &RequestBatch) -> Result<(), Error> {
+        self.lock().await.send_batch(batch).await
+    }
+
+    /// Sets the lap currently displayed by the position tower.
+    pub async fn set_lap(&self, lap: usize) -> Result<(), Error> {
+        self.lock().await.set_lap(lap).await
+    }
+}
+
+impl<T: Backend> From<ControlUnit<T>> for SharedControlUnit<T> {
+    fn from(control_unit: ControlUnit<T>) -> Self {
+        Self::new(control_unit)
+    }
+}
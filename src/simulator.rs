@@ -0,0 +1,481 @@
+//! Module which implements a simulated control unit for tests and demos without hardware.
+//!
+//! The [`SimulatedBackend`] answers requests in the common format of all backends, just like a
+//! real control unit would. Cars lap once the countdown started by the enter button finished,
+//! their lap times depend on their speed level and they burn one fuel step per lap unless the fuel
+//! button switched fuel off. Cars which run low on fuel take a pit stop and leave it with a full
+//! tank.
+
+use crate::backend::to_frame;
+use crate::messages::{STATUS_REQUEST, VERSION_REQUEST};
+use crate::protocol::{compute_checksum, encode_nibble, encode_uint32};
+use crate::{
+    Backend, Clock, Error, Frame, FuelLevel, FuelMode, LapTime, StartSignal, SystemClock,
+    TrackMode, MAX_CONTROLLER_COUNT,
+};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Firmware version which is reported by the simulator.
+const FIRMWARE_VERSION: &[u8; 4] = b"5337";
+
+/// Code of the enter button, which starts the countdown.
+const BUTTON_ENTER: u8 = 2;
+
+/// Code of the fuel button, which switches the fuel mode.
+const BUTTON_FUEL: u8 = 7;
+
+/// Word offsets of the player specific levels.
+const SPEED_OFFSET: u8 = 0x00;
+const BRAKE_OFFSET: u8 = 0x01;
+const FUEL_OFFSET: u8 = 0x02;
+
+/// Sector which is reported for the start and finish line.
+const START_FINISH_SECTOR: u8 = 1;
+
+/// Duration of each light of the countdown.
+const COUNTDOWN_STEP: Duration = Duration::from_secs(1);
+
+/// Duration the go signal is shown before the lights go off.
+const GO_DURATION: Duration = Duration::from_secs(1);
+
+/// Duration of a pit stop which refuels the car.
+const PIT_STOP_DURATION: Duration = Duration::from_secs(3);
+
+/// Fuel level at which a car takes a pit stop.
+const PIT_STOP_FUEL_LEVEL: u8 = 3;
+
+/// Appends the checksum of everything after the command character to the response.
+fn with_checksum(mut response: Vec<u8>) -> Vec<u8> {
+    response.push(encode_nibble(compute_checksum(&response[1..])));
+    response
+}
+
+#[derive(Debug, Clone)]
+struct SimulatedCar {
+    speed: u8,
+    brake: u8,
+    fuel: FuelLevel,
+    next_crossing: Option<Duration>,
+    refuel_until: Option<Duration>,
+}
+
+impl SimulatedCar {
+    fn new() -> SimulatedCar {
+        SimulatedCar {
+            speed: 15,
+            brake: 15,
+            fuel: FuelLevel::FULL,
+            next_crossing: None,
+            refuel_until: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RaceState {
+    Idle,
+    Countdown(Duration),
+    Running(Duration),
+}
+
+/// Backend which simulates a control unit with a number of cars.
+pub struct SimulatedBackend {
+    is_connected: bool,
+    clock: Arc<dyn Clock>,
+    clock_start: Instant,
+    cars: Vec<SimulatedCar>,
+    ignored_mask: u8,
+    base_lap_time: Duration,
+    race: RaceState,
+    fuel_mode: FuelMode,
+    pending_laps: VecDeque<(usize, Duration)>,
+    random_state: u64,
+}
+
+impl SimulatedBackend {
+    /// Creates a simulator with the given number of cars, which is clamped to [1, 8].
+    pub fn new(car_count: usize) -> SimulatedBackend {
+        SimulatedBackend {
+            is_connected: false,
+            clock: Arc::new(SystemClock),
+            clock_start: Instant::now(),
+            cars: vec![SimulatedCar::new(); car_count.clamp(1, MAX_CONTROLLER_COUNT)],
+            ignored_mask: 0,
+            base_lap_time: Duration::from_secs(8),
+            race: RaceState::Idle,
+            fuel_mode: FuelMode::On,
+            pending_laps: VecDeque::new(),
+            random_state: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+
+    /// Marks the simulator as connected, e.g. before it is served with [`serve_backend`](crate::serve_backend),
+    /// which expects a connected backend.
+    pub fn connected(mut self) -> Self {
+        self.is_connected = true;
+        self
+    }
+
+    /// Sets the clock which drives the simulation, the default is the [`SystemClock`].
+    /// With a [`TestClock`](crate::TestClock) races can be simulated without waiting.
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock_start = clock.now();
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the lap time of a car with full speed level, the default is eight seconds.
+    pub fn with_base_lap_time(mut self, base_lap_time: Duration) -> Self {
+        self.base_lap_time = base_lap_time;
+        self
+    }
+
+    /// Sets the seed of the variation of the lap times, so runs can be reproduced.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        // the generator must never be seeded with zero
+        self.random_state = seed | 1;
+        self
+    }
+
+    /// Returns a pseudo random factor in range [0.97, 1.03].
+    fn next_variation(&mut self) -> f64 {
+        // xorshift64
+        self.random_state ^= self.random_state << 13;
+        self.random_state ^= self.random_state >> 7;
+        self.random_state ^= self.random_state << 17;
+        0.97 + (self.random_state % 601) as f64 / 10_000.0
+    }
+
+    fn lap_time_of(&mut self, car: usize) -> Duration {
+        // every speed level below the maximum costs four percent and every brake level one percent,
+        // every car is a bit slower than the previous one
+        let SimulatedCar { speed, brake, .. } = self.cars[car];
+        let slowdown =
+            1.0 + (15 - speed) as f64 * 0.04 + (15 - brake) as f64 * 0.01 + car as f64 * 0.01;
+        self.base_lap_time.mul_f64(slowdown * self.next_variation())
+    }
+
+    /// Advances the simulation to the given time of the control unit clock.
+    fn advance(&mut self, now: Duration) {
+        if let RaceState::Countdown(started_at) = self.race {
+            let go_at = started_at + COUNTDOWN_STEP * 5;
+            if now >= go_at {
+                self.race = RaceState::Running(go_at);
+                for car in 0..self.cars.len() {
+                    // the cars start behind the line, their first crossing starts the timing
+                    let first_crossing = go_at + Duration::from_millis(300 + car as u64 * 150);
+                    self.cars[car].next_crossing = Some(first_crossing);
+                }
+            }
+        }
+
+        if !matches!(self.race, RaceState::Running(_)) {
+            return;
+        }
+
+        for index in 0..self.cars.len() {
+            if let Some(refuel_until) = self.cars[index].refuel_until {
+                if now >= refuel_until {
+                    self.cars[index].refuel_until = None;
+                    self.cars[index].fuel = FuelLevel::FULL;
+                }
+            }
+
+            while let Some(crossing) = self.cars[index].next_crossing.filter(|c| *c <= now) {
+                if self.ignored_mask & (1 << index) == 0 {
+                    self.pending_laps.push_back((index, crossing));
+                }
+
+                let mut lap_time = self.lap_time_of(index);
+                let burns_fuel = self.fuel_mode != FuelMode::Off;
+                let car = &mut self.cars[index];
+                if burns_fuel {
+                    car.fuel = car.fuel.saturating_sub(1);
+                }
+                if car.fuel.value() <= PIT_STOP_FUEL_LEVEL && car.refuel_until.is_none() {
+                    car.refuel_until = Some(crossing + lap_time / 2 + PIT_STOP_DURATION);
+                    lap_time += PIT_STOP_DURATION;
+                }
+                car.next_crossing = Some(crossing + lap_time);
+            }
+        }
+    }
+
+    fn start_signal(&self, now: Duration) -> StartSignal {
+        match self.race {
+            RaceState::Idle => StartSignal::None,
+            RaceState::Countdown(started_at) => {
+                match (now.saturating_sub(started_at).as_millis() / COUNTDOWN_STEP.as_millis())
+                    as u8
+                {
+                    0 => StartSignal::Five,
+                    1 => StartSignal::Four,
+                    2 => StartSignal::Three,
+                    3 => StartSignal::Two,
+                    _ => StartSignal::One,
+                }
+            }
+            RaceState::Running(go_at) if now < go_at + GO_DURATION => StartSignal::Go,
+            RaceState::Running(_) => StartSignal::None,
+        }
+    }
+
+    fn encode_lap_status(controller: usize, crossing: Duration) -> Vec<u8> {
+        let time = LapTime::try_from(crossing).unwrap_or(LapTime::from_millis(u32::MAX));
+
+        let mut response = vec![b'?', encode_nibble(controller as u8 + 1)];
+        response.extend_from_slice(&encode_uint32(time.as_millis()));
+        response.push(encode_nibble(START_FINISH_SECTOR));
+        with_checksum(response)
+    }
+
+    fn encode_track_status(&self, now: Duration) -> Vec<u8> {
+        let mut fuel_levels = [FuelLevel::FULL.value(); MAX_CONTROLLER_COUNT];
+        let mut refuel_mask = 0u8;
+        for (index, car) in self.cars.iter().enumerate() {
+            fuel_levels[index] = car.fuel.value();
+            if car.refuel_until.is_some_and(|until| now < until) {
+                refuel_mask |= 1 << index;
+            }
+        }
+
+        let mut response = vec![b'?', b':'];
+        response.extend(fuel_levels.iter().map(|level| encode_nibble(*level)));
+        response.push(encode_nibble(self.start_signal(now) as u8));
+        let fuel_mode = match self.fuel_mode {
+            FuelMode::Off => TrackMode::empty(),
+            FuelMode::On => TrackMode::FUEL,
+            FuelMode::Real => TrackMode::FUEL | TrackMode::REAL_FUEL,
+        };
+        response.push(encode_nibble((fuel_mode | TrackMode::PIT_LANE).bits()));
+        response.push(encode_nibble(refuel_mask));
+        response.push(encode_nibble(refuel_mask >> 4));
+        response.push(encode_nibble(self.cars.len() as u8));
+        with_checksum(response)
+    }
+
+    fn write_word(&mut self, address: u8, value: u8) {
+        let player = (address >> 5) as usize;
+        let Some(car) = self.cars.get_mut(player) else {
+            return;
+        };
+
+        match address & 0x1F {
+            SPEED_OFFSET => car.speed = value,
+            BRAKE_OFFSET => car.brake = value,
+            FUEL_OFFSET => car.fuel = FuelLevel::new(value).unwrap_or(FuelLevel::FULL),
+            // the position tower and lap display have no effect on the simulation
+            _ => (),
+        }
+    }
+
+    /// Computes the response of the simulated control unit to the request.
+    /// Returns none for requests a control unit does not answer.
+    fn respond(&mut self, request: &[u8], now: Duration) -> Option<Vec<u8>> {
+        self.advance(now);
+
+        let nibble = |index: usize| request.get(index).map(|value| value & 0x0F);
+        match request.first()? {
+            _ if request == STATUS_REQUEST => Some(match self.pending_laps.pop_front() {
+                Some((controller, crossing)) => Self::encode_lap_status(controller, crossing),
+                None => self.encode_track_status(now),
+            }),
+            _ if request == VERSION_REQUEST => {
+                let mut response = vec![b'0'];
+                response.extend_from_slice(FIRMWARE_VERSION);
+                Some(with_checksum(response))
+            }
+            b'T' => {
+                match nibble(1)? {
+                    BUTTON_ENTER if self.race == RaceState::Idle => {
+                        self.race = RaceState::Countdown(now)
+                    }
+                    BUTTON_FUEL if self.race == RaceState::Idle => {
+                        self.fuel_mode = self.fuel_mode.next()
+                    }
+                    _ => (),
+                }
+                Some(vec![b'T'])
+            }
+            b'J' => {
+                self.write_word(nibble(1)? | (nibble(2)? << 4), nibble(3)?);
+                Some(vec![b'J'])
+            }
+            b':' => {
+                self.ignored_mask = nibble(1)? | (nibble(2)? << 4);
+                Some(vec![b':'])
+            }
+            b'=' => {
+                self.clock_start = self.clock.now();
+                self.race = RaceState::Idle;
+                self.pending_laps.clear();
+                for car in &mut self.cars {
+                    car.next_crossing = None;
+                    car.refuel_until = None;
+                }
+                Some(vec![b'='])
+            }
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for SimulatedBackend {
+    async fn connect(&mut self) -> crate::Result<()> {
+        self.is_connected = true;
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> crate::Result<()> {
+        self.is_connected = false;
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> crate::Result<bool> {
+        Ok(self.is_connected)
+    }
+
+    async fn request(&mut self, data: &[u8], _timeout: Duration) -> crate::Result<Frame> {
+        if !self.is_connected {
+            return Err(Error::NotConnected);
+        }
+
+        let now = self.clock.now().saturating_duration_since(self.clock_start);
+        let response = self.respond(data, now).ok_or(Error::NoResponse)?;
+        to_frame(&response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ControlUnit, ControllerId, RaceSession, Status, TestClock, TrackStatus};
+
+    async fn simulated_control_unit(clock: &TestClock) -> ControlUnit<SimulatedBackend> {
+        let backend = SimulatedBackend::new(2)
+            .with_clock(clock.clone())
+            .with_base_lap_time(Duration::from_secs(4))
+            .with_seed(7);
+        let mut control_unit = ControlUnit::new(backend);
+        control_unit.connect().await.unwrap();
+        control_unit
+    }
+
+    /// Drains the queued statuses and returns the lap statuses followed by the track status.
+    async fn poll(control_unit: &mut ControlUnit<SimulatedBackend>) -> (Vec<Status>, TrackStatus) {
+        let mut statuses = control_unit.get_pending_statuses().await.unwrap();
+        match statuses.pop() {
+            Some(Status::Track(track)) => (statuses, track),
+            status => panic!("expected a track status, got {:?}", status),
+        }
+    }
+
+    /// Presses enter and advances the clock past the countdown.
+    async fn start_race(control_unit: &mut ControlUnit<SimulatedBackend>, clock: &TestClock) {
+        control_unit.start().await.unwrap();
+        clock.advance(COUNTDOWN_STEP * 5);
+    }
+
+    #[tokio::test]
+    async fn reports_the_firmware_version() {
+        let clock = TestClock::new();
+        let mut control_unit = simulated_control_unit(&clock).await;
+
+        assert_eq!(control_unit.get_version().await.unwrap(), "5337");
+    }
+
+    #[tokio::test]
+    async fn runs_the_start_sequence() {
+        let clock = TestClock::new();
+        let mut control_unit = simulated_control_unit(&clock).await;
+        assert_eq!(
+            poll(&mut control_unit).await.1.start_signal,
+            StartSignal::None
+        );
+
+        control_unit.start().await.unwrap();
+        let mut signals = Vec::new();
+        for _ in 0..7 {
+            signals.push(poll(&mut control_unit).await.1.start_signal);
+            clock.advance(COUNTDOWN_STEP);
+        }
+
+        assert_eq!(
+            signals,
+            [
+                StartSignal::Five,
+                StartSignal::Four,
+                StartSignal::Three,
+                StartSignal::Two,
+                StartSignal::One,
+                StartSignal::Go,
+                StartSignal::None,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn generates_laps_of_all_cars() {
+        let clock = TestClock::new();
+        let mut control_unit = simulated_control_unit(&clock).await;
+        let mut session = RaceSession::new();
+        start_race(&mut control_unit, &clock).await;
+
+        clock.advance(Duration::from_secs(20));
+        let (laps, _) = poll(&mut control_unit).await;
+        for status in &laps {
+            session.update(status);
+        }
+
+        for controller in &ControllerId::ALL[..2] {
+            let laps: Vec<_> = session.laps_of(*controller).collect();
+            assert!(laps.len() >= 3);
+            assert!(laps.iter().all(|lap| {
+                lap.lap_time > Duration::from_millis(3_800)
+                    && lap.lap_time < Duration::from_millis(4_300)
+            }));
+        }
+        assert_eq!(session.laps_of(ControllerId::ALL[2]).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn burns_one_fuel_step_per_lap() {
+        let clock = TestClock::new();
+        let mut control_unit = simulated_control_unit(&clock).await;
+        start_race(&mut control_unit, &clock).await;
+
+        clock.advance(Duration::from_secs(10));
+        let (laps, track) = poll(&mut control_unit).await;
+        let crossings = laps
+            .iter()
+            .filter(|status| matches!(status, Status::Lap(lap) if lap.controller == ControllerId::ALL[0]))
+            .count() as u8;
+
+        assert!(crossings > 0);
+        assert_eq!(
+            track.fuel_level(ControllerId::ALL[0]).value(),
+            FuelLevel::FULL.value() - crossings
+        );
+        assert!(track.is_fuel_enabled());
+    }
+
+    #[tokio::test]
+    async fn keeps_the_tank_full_with_fuel_off() {
+        let clock = TestClock::new();
+        let mut control_unit = simulated_control_unit(&clock).await;
+        control_unit.press_fuel().await.unwrap();
+        control_unit.press_fuel().await.unwrap();
+        start_race(&mut control_unit, &clock).await;
+
+        clock.advance(Duration::from_secs(10));
+        let (laps, track) = poll(&mut control_unit).await;
+
+        assert!(!laps.is_empty());
+        assert_eq!(track.fuel_mode(), FuelMode::Off);
+        assert_eq!(track.fuel_level(ControllerId::ALL[0]), FuelLevel::FULL);
+    }
+}
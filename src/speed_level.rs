@@ -0,0 +1,42 @@
+//! Module which defines the speed level of a car.
+
+use crate::bounded::bounded_type;
+
+bounded_type! {
+    /// Maximum speed of a car as it is configured on the control unit.
+    /// The level is always in range [0, 15], higher levels make the car faster.
+    pub struct SpeedLevel(u8) in [0, 15], "speed level";
+}
+
+impl SpeedLevel {
+    /// Lowest speed level.
+    pub const MIN: SpeedLevel = SpeedLevel(0);
+
+    /// Highest speed level, which is the default of the control unit.
+    pub const MAX: SpeedLevel = SpeedLevel(15);
+
+    /// Returns the raw speed level in range [0, 15].
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for SpeedLevel {
+    /// Creates the highest speed level.
+    fn default() -> Self {
+        Self::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_levels_up_to_the_maximum() {
+        assert_eq!(SpeedLevel::new(0), Some(SpeedLevel::MIN));
+        assert_eq!(SpeedLevel::new(15), Some(SpeedLevel::MAX));
+        assert_eq!(SpeedLevel::new(16), None);
+        assert_eq!(u8::from(SpeedLevel::MAX), 15);
+    }
+}
@@ -0,0 +1,167 @@
+//! Module which implements the detection of race starts from consecutive start signals.
+
+use crate::{StartSignal, Status};
+
+/// Events which are emitted by the [`StartDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StartEvent {
+    /// The countdown finished and the race started.
+    RaceStarted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StartState {
+    Idle,
+    Countdown,
+    Started,
+}
+
+/// Detects the start of a race from the start signals of consecutive track statuses.
+/// Emits exactly one [`StartEvent::RaceStarted`] per countdown, even if single signals of the
+/// countdown were missed. A start signal of go without a preceding countdown does not emit an event.
+#[derive(Debug, Clone)]
+pub struct StartDetector {
+    state: StartState,
+}
+
+impl Default for StartDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StartDetector {
+    /// Creates a detector which waits for the next countdown.
+    pub fn new() -> StartDetector {
+        StartDetector {
+            state: StartState::Idle,
+        }
+    }
+
+    /// Consumes the next start signal and returns an event if the race started.
+    pub fn update(&mut self, signal: StartSignal) -> Option<StartEvent> {
+        let (state, event) = match (self.state, signal) {
+            (StartState::Countdown, StartSignal::Go) => {
+                (StartState::Started, Some(StartEvent::RaceStarted))
+            }
+            // the countdown was aborted before the race started
+            (StartState::Countdown, StartSignal::None) => (StartState::Idle, None),
+            (_, signal) if signal.is_countdown_active() => (StartState::Countdown, None),
+            (state, _) => (state, None),
+        };
+
+        self.state = state;
+        event
+    }
+
+    /// Consumes the start signal of the given status, if it is a track status.
+    pub fn update_status(&mut self, status: &Status) -> Option<StartEvent> {
+        match status {
+            Status::Track(status) => self.update(status.start_signal),
+            _ => None,
+        }
+    }
+
+    /// Determines if the race started since the last countdown.
+    pub fn is_started(&self) -> bool {
+        self.state == StartState::Started
+    }
+
+    /// Resets the detector, so it waits for the next countdown.
+    pub fn reset(&mut self) {
+        self.state = StartState::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_SEQUENCE: [StartSignal; 8] = [
+        StartSignal::None,
+        StartSignal::Five,
+        StartSignal::Four,
+        StartSignal::Three,
+        StartSignal::Two,
+        StartSignal::One,
+        StartSignal::Go,
+        StartSignal::Go,
+    ];
+
+    /// Feeds the signals to the detector and returns the number of race starts it emitted.
+    fn count_starts(detector: &mut StartDetector, signals: &[StartSignal]) -> usize {
+        signals
+            .iter()
+            .filter_map(|signal| detector.update(*signal))
+            .filter(|event| *event == StartEvent::RaceStarted)
+            .count()
+    }
+
+    #[test]
+    fn emits_one_event_per_countdown() {
+        let mut detector = StartDetector::new();
+
+        assert_eq!(count_starts(&mut detector, &START_SEQUENCE[..7]), 1);
+        assert!(detector.is_started());
+    }
+
+    #[test]
+    fn starts_even_if_countdown_signals_were_missed() {
+        let mut detector = StartDetector::new();
+        let signals = [
+            StartSignal::Five,
+            StartSignal::Three,
+            StartSignal::One,
+            StartSignal::Go,
+        ];
+
+        assert_eq!(count_starts(&mut detector, &signals), 1);
+    }
+
+    #[test]
+    fn ignores_repeated_go_signals() {
+        let mut detector = StartDetector::new();
+
+        assert_eq!(count_starts(&mut detector, &START_SEQUENCE), 1);
+        assert_eq!(count_starts(&mut detector, &[StartSignal::Go; 3]), 0);
+        assert!(detector.is_started());
+    }
+
+    #[test]
+    fn ignores_go_without_countdown() {
+        let mut detector = StartDetector::new();
+
+        assert_eq!(
+            count_starts(&mut detector, &[StartSignal::None, StartSignal::Go]),
+            0
+        );
+        assert!(!detector.is_started());
+    }
+
+    #[test]
+    fn ignores_an_aborted_countdown() {
+        let mut detector = StartDetector::new();
+        let signals = [StartSignal::Five, StartSignal::None, StartSignal::Go];
+
+        assert_eq!(count_starts(&mut detector, &signals), 0);
+    }
+
+    #[test]
+    fn detects_the_next_start_after_a_reset() {
+        let mut detector = StartDetector::new();
+        count_starts(&mut detector, &START_SEQUENCE);
+
+        detector.reset();
+        assert!(!detector.is_started());
+        assert_eq!(count_starts(&mut detector, &[StartSignal::Go]), 0);
+        assert_eq!(count_starts(&mut detector, &START_SEQUENCE), 1);
+    }
+
+    #[test]
+    fn ignores_statuses_without_start_signal() {
+        let mut detector = StartDetector::new();
+
+        assert_eq!(detector.update_status(&Status::NoData), None);
+    }
+}
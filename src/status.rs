@@ -1,27 +1,100 @@
-use crate::LapTime;
+use crate::{ControllerId, FuelLevel, LapTime};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 /// Status of the lap of a specific controller.
 /// Contains the sector and time of the controller.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct LapStatus {
     /// Identifier of the controller.
-    /// Range is typically [0, 8].
-    pub controller: usize,
+    pub controller: ControllerId,
 
     /// Sector of the track where the time was taken.
     pub sector: usize,
 
-    /// Timestamp when the corresponding controller crossed the finish line.
+    /// Timestamp of the control unit timer when the corresponding controller crossed the sensor.
+    /// Use [`LapTime::wrapping_delta`] to compute the time between two statuses.
     pub time: LapTime,
+
+    /// Point in time when the status was received from the control unit.
+    /// Is none if the status was not received via a [`ControlUnit`](crate::ControlUnit).
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub received_at: Option<Instant>,
 }
 
-impl LapStatus {
+/// Timing group of the sensor which produced a lap status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimerGroup {
+    /// The sensor at the start and finish line, i.e. a completed lap.
+    StartFinish,
+
+    /// An additional check lane sensor on the track, identified by the group reported by the control unit.
+    CheckLane(usize),
+}
+
+impl Default for LapStatus {
     /// Creates a default initialized status.
-    pub fn new() -> LapStatus {
+    fn default() -> Self {
         LapStatus {
-            controller: 0,
+            controller: ControllerId::default(),
             sector: 0,
             time: LapTime::from_millis(0),
+            #[cfg(feature = "std")]
+            received_at: None,
+        }
+    }
+}
+
+impl LapStatus {
+    /// Timer group which is reported for the start and finish line.
+    const START_FINISH_GROUP: usize = 1;
+
+    /// Sets the identifier of the controller.
+    pub fn with_controller(mut self, controller: ControllerId) -> Self {
+        self.controller = controller;
+        self
+    }
+
+    /// Sets the sector of the track where the time was taken.
+    pub fn with_sector(mut self, sector: usize) -> Self {
+        self.sector = sector;
+        self
+    }
+
+    /// Sets the timestamp when the controller crossed the sensor.
+    pub fn with_time(mut self, time: LapTime) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Sets the point in time when the status was received from the control unit.
+    #[cfg(feature = "std")]
+    pub fn with_received_at(mut self, received_at: Instant) -> Self {
+        self.received_at = Some(received_at);
+        self
+    }
+
+    /// Returns the raw 32-bit timer value of the control unit when the status was taken.
+    /// The raw value is needed to handle timer wraparounds or to deduplicate statuses.
+    pub fn raw_timer(&self) -> u32 {
+        self.time.raw()
+    }
+
+    /// Returns the timestamp as duration since the start of the control unit timer.
+    pub fn duration(&self) -> Duration {
+        self.time.as_duration()
+    }
+
+    /// Determines the timing group of the sensor which produced the status from its sector.
+    pub fn timer_group(&self) -> TimerGroup {
+        match self.sector {
+            Self::START_FINISH_GROUP => TimerGroup::StartFinish,
+            group => TimerGroup::CheckLane(group),
         }
     }
 }
@@ -29,8 +102,10 @@ impl LapStatus {
 /// Maximum number of controllers which can be supported.
 pub const MAX_CONTROLLER_COUNT: usize = 8;
 
-/// Start signal which is emitted by the track
-#[derive(Debug)]
+/// Start signal which is emitted by the track.
+/// Signals are ordered by the progression of the countdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StartSignal {
     None = 0,
     Five = 2,
@@ -41,6 +116,32 @@ pub enum StartSignal {
     Go = 7,
 }
 
+impl StartSignal {
+    /// Returns the number of seconds until the race starts.
+    /// Returns zero once the race started and none if no countdown is running.
+    pub fn seconds_remaining(&self) -> Option<u8> {
+        match self {
+            StartSignal::None => None,
+            StartSignal::Five => Some(5),
+            StartSignal::Four => Some(4),
+            StartSignal::Three => Some(3),
+            StartSignal::Two => Some(2),
+            StartSignal::One => Some(1),
+            StartSignal::Go => Some(0),
+        }
+    }
+
+    /// Determines if the countdown for the start of a race is currently running.
+    pub fn is_countdown_active(&self) -> bool {
+        matches!(self.seconds_remaining(), Some(seconds) if seconds > 0)
+    }
+
+    /// Determines if the race was started.
+    pub fn is_go(&self) -> bool {
+        *self == StartSignal::Go
+    }
+}
+
 impl TryFrom<u8> for StartSignal {
     type Error = ();
 
@@ -58,11 +159,59 @@ impl TryFrom<u8> for StartSignal {
     }
 }
 
-#[derive(Debug)]
+bitflags::bitflags! {
+    /// Mode of the track as reported by the control unit.
+    /// Bits which are not known are preserved, so the mode can be converted back to its wire value.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct TrackMode: u8 {
+        /// Fuel consumption is enabled.
+        const FUEL = 0x01;
+
+        /// Real fuel mode is enabled.
+        const REAL_FUEL = 0x02;
+
+        /// A pit lane adapter is connected.
+        const PIT_LANE = 0x04;
+
+        /// A lap counter adapter is connected.
+        const LAP_COUNTER = 0x08;
+    }
+}
+
+/// Fuel mode of the track, which is switched with the fuel button of the control unit.
+/// Each press of the button switches to the next mode, after real mode fuel is off again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FuelMode {
+    /// Cars do not consume fuel.
+    Off,
+
+    /// Cars consume fuel.
+    On,
+
+    /// Cars consume fuel depending on the throttle, fuel levels are only displayed when refueling.
+    Real,
+}
+
+impl FuelMode {
+    /// Returns the mode which follows this one when the fuel button is pressed.
+    pub fn next(&self) -> FuelMode {
+        match self {
+            FuelMode::Off => FuelMode::On,
+            FuelMode::On => FuelMode::Real,
+            FuelMode::Real => FuelMode::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct TrackStatus {
     /// The fuel level of each controller.
-    /// Values are in range [0,15].
-    pub fuel_levels: [usize; MAX_CONTROLLER_COUNT],
+    pub fuel_levels: [FuelLevel; MAX_CONTROLLER_COUNT],
 
     /// Determines which controller is refueling at the pit lane.
     pub is_refueling: [bool; MAX_CONTROLLER_COUNT],
@@ -70,40 +219,149 @@ pub struct TrackStatus {
     /// Countdown indicator for the start of a race.
     pub start_signal: StartSignal,
 
-    pub is_fuel_enabled: bool,
-
-    /// Determines if real fuel mode is enabled on the track.
-    pub is_real_fuel_enabled: bool,
-
-    /// Determines if a pit lane adapter is connected.
-    pub is_pit_lane_connected: bool,
-
-    /// Determines oif a lap counter adapter is connected.
-    pub is_lap_counter_connected: bool,
+    /// Mode of the track, i.e. the fuel mode and the connected adapters.
+    pub mode: TrackMode,
 
     /// Number of controllers which are currently in use.
     pub controller_count: usize,
+
+    /// Point in time when the status was received from the control unit.
+    /// Is none if the status was not received via a [`ControlUnit`](crate::ControlUnit).
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub received_at: Option<Instant>,
 }
 
-impl TrackStatus {
-    pub fn new() -> TrackStatus {
+impl Default for TrackStatus {
+    /// Creates a default initialized status.
+    fn default() -> Self {
         TrackStatus {
-            fuel_levels: [0; MAX_CONTROLLER_COUNT],
+            fuel_levels: [FuelLevel::EMPTY; MAX_CONTROLLER_COUNT],
             is_refueling: [false; MAX_CONTROLLER_COUNT],
             start_signal: StartSignal::None,
-            is_fuel_enabled: false,
-            is_real_fuel_enabled: false,
-            is_pit_lane_connected: false,
-            is_lap_counter_connected: false,
+            mode: TrackMode::empty(),
             controller_count: 0,
+            #[cfg(feature = "std")]
+            received_at: None,
+        }
+    }
+}
+
+impl TrackStatus {
+    /// Sets the fuel level of each controller.
+    pub fn with_fuel_levels(mut self, fuel_levels: [FuelLevel; MAX_CONTROLLER_COUNT]) -> Self {
+        self.fuel_levels = fuel_levels;
+        self
+    }
+
+    /// Sets which controllers are refueling at the pit lane.
+    pub fn with_refueling(mut self, is_refueling: [bool; MAX_CONTROLLER_COUNT]) -> Self {
+        self.is_refueling = is_refueling;
+        self
+    }
+
+    /// Sets the countdown indicator for the start of a race.
+    pub fn with_start_signal(mut self, start_signal: StartSignal) -> Self {
+        self.start_signal = start_signal;
+        self
+    }
+
+    /// Sets the mode of the track.
+    pub fn with_mode(mut self, mode: TrackMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the number of controllers which are currently in use.
+    pub fn with_controller_count(mut self, controller_count: usize) -> Self {
+        self.controller_count = controller_count;
+        self
+    }
+
+    /// Sets the point in time when the status was received from the control unit.
+    #[cfg(feature = "std")]
+    pub fn with_received_at(mut self, received_at: Instant) -> Self {
+        self.received_at = Some(received_at);
+        self
+    }
+
+    /// Returns the controllers which are currently in use.
+    pub fn active_controllers(&self) -> impl Iterator<Item = ControllerId> {
+        ControllerId::ALL.into_iter().take(self.controller_count)
+    }
+
+    /// Returns the controllers which are currently refueling at the pit lane.
+    pub fn refueling_controllers(&self) -> impl Iterator<Item = ControllerId> + '_ {
+        ControllerId::ALL
+            .into_iter()
+            .filter(|controller| self.is_refueling[controller.index()])
+    }
+
+    /// Returns the fuel level of the given controller.
+    pub fn fuel_level(&self, controller: ControllerId) -> FuelLevel {
+        self.fuel_levels[controller.index()]
+    }
+
+    /// Determines if fuel consumption is enabled on the track.
+    pub fn is_fuel_enabled(&self) -> bool {
+        self.mode.contains(TrackMode::FUEL)
+    }
+
+    /// Determines if real fuel mode is enabled on the track.
+    /// The control unit reports the same fields in real fuel mode, the consumption it applies is
+    /// not transmitted.
+    pub fn is_real_fuel_enabled(&self) -> bool {
+        self.mode.contains(TrackMode::REAL_FUEL)
+    }
+
+    /// Returns the fuel mode of the track.
+    pub fn fuel_mode(&self) -> FuelMode {
+        match (self.is_fuel_enabled(), self.is_real_fuel_enabled()) {
+            (_, true) => FuelMode::Real,
+            (true, false) => FuelMode::On,
+            (false, false) => FuelMode::Off,
         }
     }
+
+    /// Determines if a pit lane adapter is connected.
+    pub fn is_pit_lane_connected(&self) -> bool {
+        self.mode.contains(TrackMode::PIT_LANE)
+    }
+
+    /// Determines if a lap counter adapter is connected.
+    pub fn is_lap_counter_connected(&self) -> bool {
+        self.mode.contains(TrackMode::LAP_COUNTER)
+    }
 }
 
 /// Status message that can be returned by the control unit.
 /// Either contains a lap status or a track status.
-#[derive(Debug)]
+/// If the control unit has nothing new to report, it is signaled as no data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Status {
     Lap(LapStatus),
     Track(TrackStatus),
+    NoData,
+}
+
+#[cfg(feature = "std")]
+impl Status {
+    /// Returns the point in time when the status was received from the control unit.
+    pub fn received_at(&self) -> Option<Instant> {
+        match self {
+            Status::Lap(status) => status.received_at,
+            Status::Track(status) => status.received_at,
+            Status::NoData => None,
+        }
+    }
+
+    /// Sets the point in time when the status was received from the control unit.
+    pub(crate) fn set_received_at(&mut self, received_at: Instant) {
+        match self {
+            Status::Lap(status) => status.received_at = Some(received_at),
+            Status::Track(status) => status.received_at = Some(received_at),
+            Status::NoData => (),
+        }
+    }
 }
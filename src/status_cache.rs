@@ -0,0 +1,62 @@
+//! Module which shares the latest state of a polled control unit with many readers.
+
+use crate::{Lap, RaceSession, Standing, Status, TrackStatus};
+use arc_swap::{ArcSwap, ArcSwapOption};
+use std::sync::Arc;
+
+/// Cache of the latest track status, lap and standings of a polled control unit.
+///
+/// The poller updates the cache with every status and lap, while any number of tasks, e.g. the
+/// handlers of a web server, read it concurrently. Reads never lock and hand out shared
+/// references to the cached values instead of cloning them. Share the cache with an [`Arc`].
+#[derive(Debug, Default)]
+pub struct StatusCache {
+    track_status: ArcSwapOption<TrackStatus>,
+    last_lap: ArcSwapOption<Lap>,
+    standings: ArcSwap<Vec<Standing>>,
+}
+
+impl StatusCache {
+    /// Creates an empty cache.
+    pub fn new() -> StatusCache {
+        StatusCache::default()
+    }
+
+    /// Consumes the next status of the control unit, track statuses replace the cached one.
+    pub fn update_status(&self, status: &Status) {
+        if let Status::Track(track_status) = status {
+            self.track_status.store(Some(Arc::new(*track_status)));
+        }
+    }
+
+    /// Consumes the state of the session after it completed the given lap, if any.
+    /// The standings are only recomputed when a lap was completed.
+    pub fn update_session(&self, session: &RaceSession, lap: Option<&Lap>) {
+        if let Some(lap) = lap {
+            self.last_lap.store(Some(Arc::new(lap.clone())));
+            self.standings.store(Arc::new(session.standings()));
+        }
+    }
+
+    /// Returns the latest track status, if any was reported yet.
+    pub fn track_status(&self) -> Option<Arc<TrackStatus>> {
+        self.track_status.load_full()
+    }
+
+    /// Returns the lap which was completed last, if any.
+    pub fn last_lap(&self) -> Option<Arc<Lap>> {
+        self.last_lap.load_full()
+    }
+
+    /// Returns the standings as of the last completed lap.
+    pub fn standings(&self) -> Arc<Vec<Standing>> {
+        self.standings.load_full()
+    }
+
+    /// Clears the cache, e.g. when a new session starts.
+    pub fn clear(&self) {
+        self.track_status.store(None);
+        self.last_lap.store(None);
+        self.standings.store(Arc::default());
+    }
+}
@@ -0,0 +1,320 @@
+//! Module which implements a SQLite database storing the laps of race sessions for long-term statistics.
+
+use crate::{ControllerId, RaceSession};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS drivers (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS sessions (
+        id INTEGER PRIMARY KEY,
+        track TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS laps (
+        id INTEGER PRIMARY KEY,
+        session_id INTEGER NOT NULL REFERENCES sessions(id),
+        driver_id INTEGER REFERENCES drivers(id),
+        controller INTEGER NOT NULL,
+        number INTEGER NOT NULL,
+        lap_time_ms INTEGER NOT NULL,
+        splits_ms TEXT NOT NULL,
+        timer_ms INTEGER NOT NULL,
+        is_valid INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS laps_by_session ON laps(session_id);
+    CREATE INDEX IF NOT EXISTS laps_by_driver ON laps(driver_id);
+";
+
+/// Session which was recorded in a [`LapStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredSession {
+    /// Identifier of the session within the store.
+    pub id: i64,
+
+    /// Name of the track the session was driven on.
+    pub track: String,
+
+    /// Wall clock time when the session was recorded in seconds since the unix epoch.
+    pub recorded_at: u64,
+
+    /// Number of laps which were recorded for the session.
+    pub lap_count: usize,
+}
+
+/// Fastest lap of a driver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BestLap {
+    /// Name of the driver.
+    pub driver: String,
+
+    /// Time of the lap.
+    pub lap_time: Duration,
+
+    /// Identifier of the session in which the lap was driven.
+    pub session_id: i64,
+
+    /// Name of the track the lap was driven on.
+    pub track: String,
+}
+
+/// Store which records sessions, laps and drivers into a SQLite database.
+pub struct LapStore {
+    connection: Connection,
+}
+
+impl LapStore {
+    /// Opens the database at the given path, creating it if it does not exist yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<LapStore> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a database which only lives in memory.
+    pub fn open_in_memory() -> rusqlite::Result<LapStore> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> rusqlite::Result<LapStore> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(LapStore { connection })
+    }
+
+    /// Returns the identifier of the driver with the given name, adding the driver if it is unknown.
+    pub fn driver_id(&self, name: &str) -> rusqlite::Result<i64> {
+        let existing = self
+            .connection
+            .query_row(
+                "SELECT id FROM drivers WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match existing {
+            Some(id) => Ok(id),
+            None => {
+                self.connection
+                    .execute("INSERT INTO drivers (name) VALUES (?1)", params![name])?;
+                Ok(self.connection.last_insert_rowid())
+            }
+        }
+    }
+
+    /// Records all laps of the session which was driven on the given track.
+    /// The drivers map controllers to driver names, laps of other controllers are stored without a driver.
+    /// Returns the identifier of the recorded session.
+    pub fn record_session(
+        &mut self,
+        track: &str,
+        session: &RaceSession,
+        drivers: &[(ControllerId, &str)],
+    ) -> rusqlite::Result<i64> {
+        let mut driver_ids = Vec::with_capacity(drivers.len());
+        for (controller, name) in drivers {
+            driver_ids.push((*controller, self.driver_id(name)?));
+        }
+
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO sessions (track, recorded_at) VALUES (?1, ?2)",
+            params![track, recorded_at],
+        )?;
+        let session_id = transaction.last_insert_rowid();
+
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO laps (session_id, driver_id, controller, number, lap_time_ms, splits_ms, timer_ms, is_valid)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for lap in session.laps() {
+                let driver_id = driver_ids
+                    .iter()
+                    .find(|(controller, _)| *controller == lap.controller)
+                    .map(|(_, id)| *id);
+                let splits_ms = lap
+                    .splits
+                    .iter()
+                    .map(|split| split.as_millis().to_string())
+                    .collect::<Vec<_>>()
+                    .join(";");
+
+                insert.execute(params![
+                    session_id,
+                    driver_id,
+                    lap.controller.index(),
+                    lap.number,
+                    lap.lap_time.as_millis() as u64,
+                    splits_ms,
+                    lap.time.raw(),
+                    lap.is_valid,
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(session_id)
+    }
+
+    /// Returns the fastest valid lap of every driver, optionally restricted to the given track.
+    /// The laps are ordered from fastest to slowest.
+    pub fn best_laps(&self, track: Option<&str>) -> rusqlite::Result<Vec<BestLap>> {
+        // the session and track are bare columns of the group, which only SQLite fills from the
+        // row holding the MIN() aggregate, other databases reject or fill them arbitrarily
+        let mut statement = self.connection.prepare(
+            "SELECT drivers.name, MIN(laps.lap_time_ms), laps.session_id, sessions.track
+             FROM laps
+             JOIN drivers ON drivers.id = laps.driver_id
+             JOIN sessions ON sessions.id = laps.session_id
+             WHERE laps.is_valid AND (?1 IS NULL OR sessions.track = ?1)
+             GROUP BY drivers.id
+             ORDER BY MIN(laps.lap_time_ms)",
+        )?;
+
+        let rows = statement.query_map(params![track], |row| {
+            Ok(BestLap {
+                driver: row.get(0)?,
+                lap_time: Duration::from_millis(row.get(1)?),
+                session_id: row.get(2)?,
+                track: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Returns all recorded sessions, optionally restricted to the given track, newest first.
+    pub fn sessions(&self, track: Option<&str>) -> rusqlite::Result<Vec<StoredSession>> {
+        let mut statement = self.connection.prepare(
+            "SELECT sessions.id, sessions.track, sessions.recorded_at, COUNT(laps.id)
+             FROM sessions
+             LEFT JOIN laps ON laps.session_id = sessions.id
+             WHERE ?1 IS NULL OR sessions.track = ?1
+             GROUP BY sessions.id
+             ORDER BY sessions.recorded_at DESC, sessions.id DESC",
+        )?;
+
+        let rows = statement.query_map(params![track], |row| {
+            Ok(StoredSession {
+                id: row.get(0)?,
+                track: row.get(1)?,
+                recorded_at: row.get(2)?,
+                lap_count: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LapStatus, LapTime};
+
+    /// Creates a session in which every controller completed laps of the given durations.
+    fn session(laps: &[(usize, &[u32])]) -> RaceSession {
+        let mut session = RaceSession::new().with_min_lap_time(Duration::from_secs(2));
+        for (controller, lap_times) in laps {
+            let mut time = 0;
+            let mut cross = |time: u32| {
+                let status = LapStatus::default()
+                    .with_controller(ControllerId::ALL[*controller])
+                    .with_sector(1)
+                    .with_time(LapTime::from_millis(time));
+                session.update_lap(&status);
+            };
+            cross(time);
+            for lap_time in *lap_times {
+                time += lap_time;
+                cross(time);
+            }
+        }
+        session
+    }
+
+    fn best(laps: &[BestLap]) -> Vec<(&str, u64, &str)> {
+        laps.iter()
+            .map(|lap| {
+                (
+                    lap.driver.as_str(),
+                    lap.lap_time.as_millis() as u64,
+                    lap.track.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reuses_the_identifiers_of_known_drivers() {
+        let store = LapStore::open_in_memory().unwrap();
+
+        let alice = store.driver_id("alice").unwrap();
+        let bob = store.driver_id("bob").unwrap();
+        assert_ne!(alice, bob);
+        assert_eq!(store.driver_id("alice").unwrap(), alice);
+    }
+
+    #[test]
+    fn ranks_the_best_valid_lap_of_every_driver() {
+        let mut store = LapStore::open_in_memory().unwrap();
+        let drivers = [
+            (ControllerId::ALL[0], "alice"),
+            (ControllerId::ALL[1], "bob"),
+        ];
+
+        // the lap below the minimum lap time of bob does not count, controller 3 has no driver
+        let monza = session(&[(0, &[9_000, 8_500]), (1, &[1_000, 8_700]), (2, &[5_000])]);
+        let monza_id = store.record_session("monza", &monza, &drivers).unwrap();
+        let spa = session(&[(0, &[8_200]), (1, &[8_000])]);
+        let spa_id = store.record_session("spa", &spa, &drivers).unwrap();
+
+        let all = store.best_laps(None).unwrap();
+        assert_eq!(best(&all), [("bob", 8_000, "spa"), ("alice", 8_200, "spa")]);
+        assert!(all.iter().all(|lap| lap.session_id == spa_id));
+
+        let monza_laps = store.best_laps(Some("monza")).unwrap();
+        assert_eq!(
+            best(&monza_laps),
+            [("alice", 8_500, "monza"), ("bob", 8_700, "monza")]
+        );
+        assert!(monza_laps.iter().all(|lap| lap.session_id == monza_id));
+        assert!(store.best_laps(Some("imola")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn lists_sessions_by_track() {
+        let mut store = LapStore::open_in_memory().unwrap();
+        let drivers = [(ControllerId::ALL[0], "alice")];
+
+        let first = store
+            .record_session("monza", &session(&[(0, &[9_000, 8_500])]), &drivers)
+            .unwrap();
+        let second = store
+            .record_session("spa", &session(&[(0, &[8_200])]), &drivers)
+            .unwrap();
+        let empty = store
+            .record_session("monza", &RaceSession::new(), &drivers)
+            .unwrap();
+
+        let ids = |sessions: Vec<StoredSession>| -> Vec<(i64, usize)> {
+            sessions
+                .iter()
+                .map(|session| (session.id, session.lap_count))
+                .collect()
+        };
+        assert_eq!(
+            ids(store.sessions(None).unwrap()),
+            [(empty, 0), (second, 1), (first, 2)]
+        );
+        assert_eq!(
+            ids(store.sessions(Some("monza")).unwrap()),
+            [(empty, 0), (first, 2)]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Module which implements a logger writing statuses and derived events as JSON lines.
+
+use crate::{Lap, StartEvent, Status};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Event which is recorded by the [`TelemetryLogger`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    /// A status which was decoded from a response of the control unit.
+    Status(Status),
+
+    /// A lap which was completed within a session.
+    Lap(Lap),
+
+    /// An event of the start of a race.
+    Start(StartEvent),
+}
+
+/// A single line of a telemetry log.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TelemetryRecord {
+    /// Wall clock time when the event was logged in milliseconds since the unix epoch.
+    pub timestamp_ms: u64,
+
+    /// The logged event.
+    #[serde(flatten)]
+    pub event: TelemetryEvent,
+}
+
+/// Logger which appends every event as one JSON object per line to a file.
+///
+/// If a maximum file size is configured, the log is rotated before the size is exceeded:
+/// the current file is renamed with a numeric suffix, e.g. `race.jsonl.1`, and older files
+/// are shifted up to the configured number of retained files.
+pub struct TelemetryLogger {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    written: u64,
+    max_file_size: Option<u64>,
+    max_files: usize,
+}
+
+impl TelemetryLogger {
+    /// Opens the log file at the given path, appending to it if it already exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<TelemetryLogger> {
+        let path = path.as_ref().to_path_buf();
+        let (writer, written) = Self::open(&path)?;
+        Ok(TelemetryLogger {
+            path,
+            writer,
+            written,
+            max_file_size: None,
+            max_files: 0,
+        })
+    }
+
+    /// Enables rotation of the log once it reaches the given size in bytes,
+    /// keeping the given number of rotated files.
+    pub fn with_rotation(mut self, max_file_size: u64, max_files: usize) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self.max_files = max_files;
+        self
+    }
+
+    fn open(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok((BufWriter::new(file), written))
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        (self.writer, self.written) = Self::open(&self.path)?;
+        Ok(())
+    }
+
+    /// Appends the event to the log.
+    pub fn log(&mut self, event: TelemetryEvent) -> io::Result<()> {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let mut line = serde_json::to_vec(&TelemetryRecord {
+            timestamp_ms,
+            event,
+        })?;
+        line.push(b'\n');
+
+        if let Some(max_file_size) = self.max_file_size {
+            if self.written > 0 && self.written + line.len() as u64 > max_file_size {
+                self.rotate()?;
+            }
+        }
+
+        self.writer.write_all(&line)?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Appends the status to the log.
+    pub fn log_status(&mut self, status: &Status) -> io::Result<()> {
+        self.log(TelemetryEvent::Status(*status))
+    }
+
+    /// Appends the lap to the log.
+    pub fn log_lap(&mut self, lap: &Lap) -> io::Result<()> {
+        self.log(TelemetryEvent::Lap(lap.clone()))
+    }
+
+    /// Appends the start event to the log.
+    pub fn log_start(&mut self, event: StartEvent) -> io::Result<()> {
+        self.log(TelemetryEvent::Start(event))
+    }
+
+    /// Writes all buffered lines to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for TelemetryLogger {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
@@ -0,0 +1,348 @@
+//! Reference frames of the control unit protocol together with their expected decoded values.
+//!
+//! The frames are synthetic: they are written by hand from the protocol as it is implemented by
+//! the decoders and are not captured from a control unit. They pin down the behavior of the
+//! decoders, but cannot reveal where the decoders and real hardware disagree. Captures of real
+//! control units belong into the conformance corpus instead.
+//!
+//! The frames are given in the common format every [`Backend`](crate::Backend) has to return
+//! from [`Backend::request`](crate::Backend::request): the command character first, followed by
+//! the payload and the checksum, without any transport specific framing like the trailing `$`.
+//! Backend implementors can feed these frames through their transport to validate it and
+//! application developers can use them to exercise their status handling without hardware.
+//!
+//! The expected statuses carry no receive timestamp, as it is only set by [`ControlUnit`](crate::ControlUnit).
+
+use crate::{
+    ControllerId, DecodeError, FuelLevel, LapStatus, LapTime, StartSignal, Status, TrackMode,
+    TrackStatus, MAX_CONTROLLER_COUNT,
+};
+
+/// A response frame of the control unit with the status it decodes to.
+pub struct StatusVector {
+    /// Short description of the scenario covered by the frame.
+    pub description: &'static str,
+
+    /// Raw response of the control unit to a status request.
+    pub frame: &'static [u8],
+
+    /// Expected outcome of decoding the frame.
+    pub expected: Result<Status, DecodeError>,
+}
+
+/// A response frame of the control unit with the firmware version it decodes to.
+pub struct VersionVector {
+    /// Short description of the scenario covered by the frame.
+    pub description: &'static str,
+
+    /// Raw response of the control unit to a version request.
+    pub frame: &'static [u8],
+
+    /// Expected outcome of decoding the frame.
+    pub expected: Result<&'static str, DecodeError>,
+}
+
+/// A request frame as it is sent to the control unit.
+pub struct RequestVector {
+    /// Short description of the request.
+    pub description: &'static str,
+
+    /// Raw request which is written to the control unit.
+    pub frame: &'static [u8],
+}
+
+/// Converts raw fuel levels as they are transmitted by the control unit.
+fn fuel_levels(levels: [u8; MAX_CONTROLLER_COUNT]) -> [FuelLevel; MAX_CONTROLLER_COUNT] {
+    levels.map(FuelLevel::from_nibble)
+}
+
+/// Returns the synthetic reference responses to status requests.
+pub fn status_vectors() -> Vec<StatusVector> {
+    vec![
+        StatusVector {
+            description: "track status of an idle track with full tanks",
+            frame: b"?:????????000002",
+            expected: Ok(Status::Track(
+                TrackStatus::default().with_fuel_levels([FuelLevel::FULL; MAX_CONTROLLER_COUNT]),
+            )),
+        },
+        StatusVector {
+            description: "track status with fuel mode and pit lane enabled and two controllers",
+            frame: b"?:??<:????050021",
+            expected: Ok(Status::Track(
+                TrackStatus::default()
+                    .with_fuel_levels(fuel_levels([15, 15, 12, 10, 15, 15, 15, 15]))
+                    .with_mode(TrackMode::FUEL | TrackMode::PIT_LANE)
+                    .with_controller_count(2),
+            )),
+        },
+        StatusVector {
+            description: "track status during the countdown with the second controller refueling",
+            frame: b"?:?8??????4?2022",
+            expected: Ok(Status::Track(
+                TrackStatus::default()
+                    .with_fuel_levels(fuel_levels([15, 8, 15, 15, 15, 15, 15, 15]))
+                    .with_refueling([false, true, false, false, false, false, false, false])
+                    .with_start_signal(StartSignal::Three)
+                    .with_mode(TrackMode::all())
+                    .with_controller_count(2),
+            )),
+        },
+        StatusVector {
+            description: "lap status of the first controller",
+            frame: b"?100102>0417",
+            expected: Ok(Status::Lap(
+                LapStatus::default()
+                    .with_controller(ControllerId::ALL[0])
+                    .with_sector(1)
+                    .with_time(LapTime::from_millis(123456)),
+            )),
+        },
+        StatusVector {
+            description: "lap status of the third controller with the maximum timer value",
+            frame: b"?3????????1<",
+            expected: Ok(Status::Lap(
+                LapStatus::default()
+                    .with_controller(ControllerId::ALL[2])
+                    .with_sector(1)
+                    .with_time(LapTime::from_millis(u32::MAX)),
+            )),
+        },
+        StatusVector {
+            description: "no new data available",
+            frame: b"?",
+            expected: Ok(Status::NoData),
+        },
+        StatusVector {
+            description: "track status with a corrupted checksum",
+            frame: b"?:????????000003",
+            expected: Err(DecodeError::BadChecksum),
+        },
+        StatusVector {
+            description: "truncated track status",
+            frame: b"?:????????0000",
+            expected: Err(DecodeError::WrongLength),
+        },
+        StatusVector {
+            description: "lap status with an invalid controller address",
+            frame: b"?000102>0416",
+            expected: Err(DecodeError::BadField),
+        },
+        StatusVector {
+            description: "response to a different request",
+            frame: b"053372",
+            expected: Err(DecodeError::WrongLength),
+        },
+    ]
+}
+
+/// Returns the synthetic reference responses to firmware version requests.
+pub fn version_vectors() -> Vec<VersionVector> {
+    vec![
+        VersionVector {
+            description: "firmware version 5337",
+            frame: b"053372",
+            expected: Ok("5337"),
+        },
+        VersionVector {
+            description: "firmware version with a corrupted checksum",
+            frame: b"053373",
+            expected: Err(DecodeError::BadChecksum),
+        },
+        VersionVector {
+            description: "response to a different request",
+            frame: b"?53372",
+            expected: Err(DecodeError::UnknownCommand),
+        },
+    ]
+}
+
+/// Returns reference requests as they are sent by [`ControlUnit`](crate::ControlUnit).
+pub fn request_vectors() -> Vec<RequestVector> {
+    vec![
+        RequestVector {
+            description: "status request",
+            frame: b"?",
+        },
+        RequestVector {
+            description: "version request",
+            frame: b"0",
+        },
+        RequestVector {
+            description: "press of the enter button",
+            frame: b"T2\x06",
+        },
+        RequestVector {
+            description: "reset of the clock",
+            frame: b"=10\x0E",
+        },
+        RequestVector {
+            description: "reset of the position tower",
+            frame: b"J6091\x0A",
+        },
+        RequestVector {
+            description: "speed level 8 for the second controller",
+            frame: b"J0282\x06",
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{
+        decode_status, decode_version, make_button_press_request, make_reset_clock_request,
+        make_reset_positions_request, STATUS_REQUEST, VERSION_REQUEST,
+    };
+    use crate::protocol::{
+        compute_checksum, encode_nibble, encode_uint32, make_set_word_request, WordAddress,
+    };
+    use crate::ChecksumValidation;
+    use proptest::prelude::*;
+
+    const START_SIGNALS: [StartSignal; 7] = [
+        StartSignal::None,
+        StartSignal::Five,
+        StartSignal::Four,
+        StartSignal::Three,
+        StartSignal::Two,
+        StartSignal::One,
+        StartSignal::Go,
+    ];
+
+    /// Appends the checksum of everything after the command character.
+    fn with_checksum(mut frame: Vec<u8>) -> Vec<u8> {
+        frame.push(encode_nibble(compute_checksum(&frame[1..])));
+        frame
+    }
+
+    /// Encodes a lap status the way the control unit transmits it.
+    fn encode_lap_status(status: &LapStatus) -> Vec<u8> {
+        let mut frame = vec![b'?', encode_nibble(status.controller.index() as u8 + 1)];
+        frame.extend_from_slice(&encode_uint32(status.time.as_millis()));
+        frame.push(encode_nibble(status.sector as u8));
+        with_checksum(frame)
+    }
+
+    /// Encodes a track status the way the control unit transmits it.
+    fn encode_track_status(status: &TrackStatus) -> Vec<u8> {
+        let refuel_mask = status
+            .is_refueling
+            .iter()
+            .enumerate()
+            .fold(0u8, |mask, (i, is_refueling)| {
+                mask | ((*is_refueling as u8) << i)
+            });
+
+        let mut frame = vec![b'?', b':'];
+        frame.extend(
+            status
+                .fuel_levels
+                .iter()
+                .map(|level| encode_nibble(level.value())),
+        );
+        frame.push(encode_nibble(status.start_signal as u8));
+        frame.push(encode_nibble(status.mode.bits()));
+        frame.push(encode_nibble(refuel_mask));
+        frame.push(encode_nibble(refuel_mask >> 4));
+        frame.push(encode_nibble(status.controller_count as u8));
+        with_checksum(frame)
+    }
+
+    #[test]
+    fn status_vectors_decode_to_their_expected_status() {
+        for vector in status_vectors() {
+            assert_eq!(
+                decode_status(vector.frame, ChecksumValidation::Strict),
+                vector.expected,
+                "{}",
+                vector.description
+            );
+        }
+    }
+
+    #[test]
+    fn version_vectors_decode_to_their_expected_version() {
+        for vector in version_vectors() {
+            assert_eq!(
+                decode_version(vector.frame, ChecksumValidation::Strict)
+                    .as_deref()
+                    .map_err(|error| *error),
+                vector.expected,
+                "{}",
+                vector.description
+            );
+        }
+    }
+
+    #[test]
+    fn valid_status_vectors_are_reencoded_to_their_frame() {
+        for vector in status_vectors() {
+            let frame = match vector.expected {
+                Ok(Status::Track(status)) => encode_track_status(&status),
+                Ok(Status::Lap(status)) => encode_lap_status(&status),
+                Ok(Status::NoData) => STATUS_REQUEST.to_vec(),
+                Err(_) => continue,
+            };
+            assert_eq!(frame, vector.frame, "{}", vector.description);
+        }
+    }
+
+    #[test]
+    fn request_vectors_match_the_encoded_requests() {
+        let requests: [&[u8]; 6] = [
+            &STATUS_REQUEST,
+            &VERSION_REQUEST,
+            &make_button_press_request(2),
+            &make_reset_clock_request(),
+            &make_reset_positions_request(),
+            &make_set_word_request(WordAddress::Speed(1), 8),
+        ];
+
+        let vectors = request_vectors();
+        assert_eq!(vectors.len(), requests.len());
+        for (vector, request) in vectors.iter().zip(requests) {
+            assert_eq!(vector.frame, request, "{}", vector.description);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn lap_status_round_trips(
+            controller in 0..MAX_CONTROLLER_COUNT,
+            time in any::<u32>(),
+            sector in 0..16usize,
+        ) {
+            let status = LapStatus::default()
+                .with_controller(ControllerId::ALL[controller])
+                .with_sector(sector)
+                .with_time(LapTime::from_millis(time));
+
+            prop_assert_eq!(
+                decode_status(&encode_lap_status(&status), ChecksumValidation::Strict),
+                Ok(Status::Lap(status))
+            );
+        }
+
+        #[test]
+        fn track_status_round_trips(
+            levels in prop::array::uniform8(0..=15u8),
+            refueling in prop::array::uniform8(any::<bool>()),
+            start_signal in prop::sample::select(START_SIGNALS.as_slice()),
+            mode in 0..=0x0Fu8,
+            controller_count in 0..=MAX_CONTROLLER_COUNT,
+        ) {
+            let status = TrackStatus::default()
+                .with_fuel_levels(fuel_levels(levels))
+                .with_refueling(refueling)
+                .with_start_signal(start_signal)
+                .with_mode(TrackMode::from_bits_retain(mode))
+                .with_controller_count(controller_count);
+
+            prop_assert_eq!(
+                decode_status(&encode_track_status(&status), ChecksumValidation::Strict),
+                Ok(Status::Track(status))
+            );
+        }
+    }
+}
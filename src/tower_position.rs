@@ -0,0 +1,51 @@
+//! Module which defines the position of a player on the position tower.
+
+use crate::bounded::bounded_type;
+use crate::MAX_CONTROLLER_COUNT;
+
+bounded_type! {
+    /// Position of a player as it is displayed by the position tower.
+    /// Positions are one based and always in range [1, 8], the leader has position 1.
+    pub struct TowerPosition(usize) in [1, MAX_CONTROLLER_COUNT], "position";
+}
+
+impl TowerPosition {
+    /// Position of the leader.
+    pub const FIRST: TowerPosition = TowerPosition(1);
+
+    /// Last position which can be displayed.
+    pub const LAST: TowerPosition = TowerPosition(MAX_CONTROLLER_COUNT as u8);
+
+    /// Returns the one based position in range [1, 8].
+    pub fn value(&self) -> usize {
+        self.0 as usize
+    }
+
+    /// Returns the position as it is written to the position tower.
+    #[cfg(feature = "std")]
+    pub(crate) fn raw(&self) -> u8 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_positions_of_the_tower() {
+        assert_eq!(TowerPosition::new(0), None);
+        assert_eq!(TowerPosition::new(1), Some(TowerPosition::FIRST));
+        assert_eq!(TowerPosition::new(8), Some(TowerPosition::LAST));
+        assert_eq!(TowerPosition::new(9), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn rejects_positions_outside_of_the_tower() {
+        assert!(matches!(
+            TowerPosition::try_from(9),
+            Err(crate::Error::InvalidArgument(_))
+        ));
+    }
+}
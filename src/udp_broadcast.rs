@@ -0,0 +1,136 @@
+//! Module which implements the broadcast of compact lap and standings packets on the local network.
+//!
+//! All packets start with the magic bytes `CL`, the format version and the packet type.
+//! Multi-byte values are big endian, absent times are encoded as `0xFFFFFFFF`.
+//!
+//! | Packet    | Type | Payload                                                                         |
+//! |-----------|------|---------------------------------------------------------------------------------|
+//! | Lap       | 1    | controller `u8`, lap `u16`, lap time ms `u32`, valid `u8`                       |
+//! | Standings | 2    | count `u8`, per entry: position `u8`, controller `u8`, laps `u16`, best lap ms `u32`, gap ms `u32` |
+
+use crate::{Lap, RaceSession, Standing};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Magic bytes at the start of every packet.
+pub const UDP_PACKET_MAGIC: [u8; 2] = *b"CL";
+
+/// Version of the packet format.
+pub const UDP_PACKET_VERSION: u8 = 1;
+
+const LAP_PACKET_TYPE: u8 = 1;
+const STANDINGS_PACKET_TYPE: u8 = 2;
+const ABSENT_TIME: u32 = u32::MAX;
+
+fn as_millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(ABSENT_TIME - 1)
+}
+
+fn packet_header(packet_type: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(64);
+    packet.extend_from_slice(&UDP_PACKET_MAGIC);
+    packet.push(UDP_PACKET_VERSION);
+    packet.push(packet_type);
+    packet
+}
+
+/// Encodes the packet of a completed lap.
+pub fn encode_lap_packet(lap: &Lap) -> Vec<u8> {
+    let mut packet = packet_header(LAP_PACKET_TYPE);
+    packet.push(lap.controller.index() as u8);
+    packet.extend_from_slice(&(lap.number.min(u16::MAX as usize) as u16).to_be_bytes());
+    packet.extend_from_slice(&as_millis(lap.lap_time).to_be_bytes());
+    packet.push(lap.is_valid as u8);
+    packet
+}
+
+/// Encodes the packet of the given standings.
+pub fn encode_standings_packet(standings: &[Standing]) -> Vec<u8> {
+    let standings = &standings[..standings.len().min(u8::MAX as usize)];
+    let leader = standings.first();
+
+    let mut packet = packet_header(STANDINGS_PACKET_TYPE);
+    packet.push(standings.len() as u8);
+    for standing in standings {
+        let gap = match leader {
+            Some(leader) if standing.position > 1 && standing.laps == leader.laps => {
+                as_millis(standing.total_time.saturating_sub(leader.total_time))
+            }
+            _ => ABSENT_TIME,
+        };
+
+        packet.push(standing.position as u8);
+        packet.push(standing.controller.index() as u8);
+        packet.extend_from_slice(&(standing.laps.min(u16::MAX as usize) as u16).to_be_bytes());
+        packet.extend_from_slice(
+            &standing
+                .best_lap
+                .map_or(ABSENT_TIME, as_millis)
+                .to_be_bytes(),
+        );
+        packet.extend_from_slice(&gap.to_be_bytes());
+    }
+    packet
+}
+
+/// Broadcaster which sends lap and standings packets via UDP to a broadcast or multicast address.
+/// Laps are sent immediately, the standings are sent at a configurable interval.
+pub struct UdpBroadcaster {
+    socket: UdpSocket,
+    target: SocketAddr,
+    interval: Duration,
+    last_standings: Option<Instant>,
+}
+
+impl UdpBroadcaster {
+    /// Creates a broadcaster which sends packets to the given address,
+    /// e.g. `255.255.255.255:7447` or a multicast group like `239.0.0.1:7447`.
+    /// Standings are sent once per second by default.
+    pub fn new(target: SocketAddr) -> io::Result<UdpBroadcaster> {
+        let socket = match target {
+            SocketAddr::V4(_) => UdpSocket::bind(("0.0.0.0", 0))?,
+            SocketAddr::V6(_) => UdpSocket::bind(("::", 0))?,
+        };
+        socket.set_broadcast(true)?;
+        if let SocketAddr::V4(_) = target {
+            // keep multicast packets on the local network
+            socket.set_multicast_ttl_v4(1)?;
+        }
+
+        Ok(UdpBroadcaster {
+            socket,
+            target,
+            interval: Duration::from_secs(1),
+            last_standings: None,
+        })
+    }
+
+    /// Sets the interval at which the standings are sent.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sends the packet of the completed lap.
+    pub fn send_lap(&self, lap: &Lap) -> io::Result<()> {
+        self.socket.send_to(&encode_lap_packet(lap), self.target)?;
+        Ok(())
+    }
+
+    /// Sends the standings of the session if the interval elapsed since they were sent last.
+    /// Returns whether the standings were sent.
+    pub fn update(&mut self, session: &RaceSession, now: Instant) -> io::Result<bool> {
+        let is_due = self
+            .last_standings
+            .is_none_or(|last| now.saturating_duration_since(last) >= self.interval);
+        if !is_due {
+            return Ok(false);
+        }
+
+        self.socket
+            .send_to(&encode_standings_packet(&session.standings()), self.target)?;
+        self.last_standings = Some(now);
+        Ok(true)
+    }
+}
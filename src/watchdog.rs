@@ -0,0 +1,217 @@
+//! Module which implements the detection of a stalled control unit while polling statuses.
+
+use crate::{LapStatus, Status, TrackStatus};
+use std::time::{Duration, Instant};
+
+/// Events which are emitted by the [`StallWatchdog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StallEvent {
+    /// The control unit did not produce a changing status for the given duration.
+    StallDetected {
+        /// Duration since the last changing status.
+        stalled_for: Duration,
+    },
+
+    /// The control unit produced a changing status again after a stall.
+    Recovered,
+}
+
+/// Watches the statuses of a polling loop and detects when the control unit stops producing
+/// changing statuses or stops answering entirely.
+///
+/// Feed every poll outcome into the watchdog, a status via [`update_status`](StallWatchdog::update_status)
+/// and a failed request via [`update_no_response`](StallWatchdog::update_no_response).
+/// Emits exactly one [`StallEvent::StallDetected`] per stall and one [`StallEvent::Recovered`]
+/// once the control unit produces a changing status again.
+#[derive(Debug, Clone)]
+pub struct StallWatchdog {
+    timeout: Duration,
+    last_status: Option<Status>,
+    last_change: Option<Instant>,
+    is_stalled: bool,
+}
+
+impl StallWatchdog {
+    /// Creates a watchdog which reports a stall after the given duration without a changing status.
+    pub fn new(timeout: Duration) -> StallWatchdog {
+        StallWatchdog {
+            timeout,
+            last_status: None,
+            last_change: None,
+            is_stalled: false,
+        }
+    }
+
+    /// Consumes the status which was polled at the given point in time.
+    pub fn update_status(&mut self, status: &Status, now: Instant) -> Option<StallEvent> {
+        let status = without_received_at(status);
+        let is_change = match status {
+            // every lap status is a new crossing of a sensor
+            Status::Lap(_) => true,
+            Status::NoData => false,
+            status => self.last_status != Some(status),
+        };
+
+        if status != Status::NoData {
+            self.last_status = Some(status);
+        }
+
+        if is_change || self.last_change.is_none() {
+            self.last_change = Some(now);
+            if self.is_stalled {
+                self.is_stalled = false;
+                return Some(StallEvent::Recovered);
+            }
+            return None;
+        }
+
+        self.check(now)
+    }
+
+    /// Consumes a poll at the given point in time which the control unit did not answer.
+    pub fn update_no_response(&mut self, now: Instant) -> Option<StallEvent> {
+        if self.last_change.is_none() {
+            self.last_change = Some(now);
+        }
+        self.check(now)
+    }
+
+    /// Checks for a stall at the given point in time without consuming a poll.
+    pub fn check(&mut self, now: Instant) -> Option<StallEvent> {
+        let stalled_for = now.saturating_duration_since(self.last_change?);
+        if self.is_stalled || stalled_for < self.timeout {
+            return None;
+        }
+
+        self.is_stalled = true;
+        Some(StallEvent::StallDetected { stalled_for })
+    }
+
+    /// Determines if the control unit is currently stalled.
+    pub fn is_stalled(&self) -> bool {
+        self.is_stalled
+    }
+
+    /// Resets the watchdog, e.g. after reconnecting to the control unit.
+    pub fn reset(&mut self) {
+        self.last_status = None;
+        self.last_change = None;
+        self.is_stalled = false;
+    }
+}
+
+/// Strips the receive timestamp, which differs for every poll.
+fn without_received_at(status: &Status) -> Status {
+    match *status {
+        Status::Lap(status) => Status::Lap(LapStatus {
+            received_at: None,
+            ..status
+        }),
+        Status::Track(status) => Status::Track(TrackStatus {
+            received_at: None,
+            ..status
+        }),
+        Status::NoData => Status::NoData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, StartSignal, TestClock};
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn track_status(start_signal: StartSignal, clock: &TestClock) -> Status {
+        Status::Track(
+            TrackStatus::default()
+                .with_start_signal(start_signal)
+                .with_received_at(clock.now()),
+        )
+    }
+
+    #[test]
+    fn detects_a_stall_after_the_timeout() {
+        let clock = TestClock::new();
+        let mut watchdog = StallWatchdog::new(TIMEOUT);
+
+        assert_eq!(
+            watchdog.update_status(&track_status(StartSignal::None, &clock), clock.now()),
+            None
+        );
+        clock.advance(Duration::from_millis(1_500));
+        assert_eq!(
+            watchdog.update_status(&track_status(StartSignal::None, &clock), clock.now()),
+            None
+        );
+        assert!(!watchdog.is_stalled());
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(
+            watchdog.update_status(&track_status(StartSignal::None, &clock), clock.now()),
+            Some(StallEvent::StallDetected {
+                stalled_for: TIMEOUT
+            })
+        );
+        assert!(watchdog.is_stalled());
+    }
+
+    #[test]
+    fn reports_every_stall_once() {
+        let clock = TestClock::new();
+        let mut watchdog = StallWatchdog::new(TIMEOUT);
+
+        watchdog.update_no_response(clock.now());
+        clock.advance(TIMEOUT);
+        assert!(watchdog.update_no_response(clock.now()).is_some());
+
+        for _ in 0..3 {
+            clock.advance(TIMEOUT);
+            assert_eq!(watchdog.update_no_response(clock.now()), None);
+            assert_eq!(watchdog.update_status(&Status::NoData, clock.now()), None);
+            assert_eq!(watchdog.check(clock.now()), None);
+        }
+        assert!(watchdog.is_stalled());
+    }
+
+    #[test]
+    fn recovers_on_the_first_changed_status() {
+        let clock = TestClock::new();
+        let mut watchdog = StallWatchdog::new(TIMEOUT);
+
+        watchdog.update_status(&track_status(StartSignal::None, &clock), clock.now());
+        clock.advance(TIMEOUT);
+        assert!(watchdog.check(clock.now()).is_some());
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(
+            watchdog.update_status(&track_status(StartSignal::None, &clock), clock.now()),
+            None
+        );
+        assert_eq!(
+            watchdog.update_status(&track_status(StartSignal::Five, &clock), clock.now()),
+            Some(StallEvent::Recovered)
+        );
+        assert!(!watchdog.is_stalled());
+
+        clock.advance(Duration::from_millis(100));
+        let lap = Status::Lap(LapStatus::default().with_received_at(clock.now()));
+        assert_eq!(watchdog.update_status(&lap, clock.now()), None);
+    }
+
+    #[test]
+    fn waits_for_the_first_poll_after_a_reset() {
+        let clock = TestClock::new();
+        let mut watchdog = StallWatchdog::new(TIMEOUT);
+
+        watchdog.update_no_response(clock.now());
+        clock.advance(TIMEOUT);
+        assert!(watchdog.check(clock.now()).is_some());
+
+        watchdog.reset();
+        clock.advance(TIMEOUT);
+        assert_eq!(watchdog.check(clock.now()), None);
+        assert!(!watchdog.is_stalled());
+    }
+}
@@ -0,0 +1,157 @@
+//! Module which implements a WebSocket server broadcasting live timing messages.
+
+use crate::{LiveTimingMessage, RaceSession};
+use futures::{SinkExt, StreamExt};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Number of messages which are buffered for a client before it starts missing messages.
+const CLIENT_BUFFER_SIZE: usize = 256;
+
+/// Latest messages which are sent to clients right after they connected.
+#[derive(Default)]
+struct Snapshot {
+    session_info: Option<String>,
+    standings: Option<String>,
+}
+
+/// WebSocket server which broadcasts [`LiveTimingMessage`]s as JSON text messages to all clients.
+///
+/// Clients which connect during a session first receive the latest session information and
+/// standings, followed by all messages which are published afterwards.
+/// Messages sent by clients are ignored.
+pub struct LiveTimingServer {
+    sender: broadcast::Sender<String>,
+    snapshot: Arc<Mutex<Snapshot>>,
+    local_addr: SocketAddr,
+    accept_task: JoinHandle<()>,
+}
+
+impl LiveTimingServer {
+    /// Binds the server to the given address and starts accepting clients.
+    /// Must be called within a tokio runtime.
+    pub async fn bind<A: ToSocketAddrs>(address: A) -> io::Result<LiveTimingServer> {
+        let listener = TcpListener::bind(address).await?;
+        let local_addr = listener.local_addr()?;
+        let (sender, _) = broadcast::channel(CLIENT_BUFFER_SIZE);
+        let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+
+        let accept_task = tokio::spawn(accept_clients(
+            listener,
+            sender.clone(),
+            Arc::clone(&snapshot),
+        ));
+
+        Ok(LiveTimingServer {
+            sender,
+            snapshot,
+            local_addr,
+            accept_task,
+        })
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the number of currently connected clients.
+    pub fn client_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Sends the message to all connected clients.
+    pub fn publish(&self, message: &LiveTimingMessage) {
+        let json = message.to_json();
+
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            match message {
+                LiveTimingMessage::SessionInfo { .. } => snapshot.session_info = Some(json.clone()),
+                LiveTimingMessage::Standings { .. } => snapshot.standings = Some(json.clone()),
+                LiveTimingMessage::Lap { .. } => {}
+            }
+        }
+
+        // sending only fails without any connected client
+        let _ = self.sender.send(json);
+    }
+
+    /// Sends the current session information and standings of the session to all connected clients.
+    pub fn publish_session(&self, session: &RaceSession) {
+        self.publish(&LiveTimingMessage::session_info(session));
+        self.publish(&LiveTimingMessage::standings(session));
+    }
+}
+
+impl Drop for LiveTimingServer {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+async fn accept_clients(
+    listener: TcpListener,
+    sender: broadcast::Sender<String>,
+    snapshot: Arc<Mutex<Snapshot>>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                log::debug!("live timing client {} connected", peer);
+                let receiver = sender.subscribe();
+                let initial = match snapshot.lock() {
+                    Ok(snapshot) => [snapshot.session_info.clone(), snapshot.standings.clone()],
+                    Err(_) => [None, None],
+                };
+                tokio::spawn(serve_client(stream, receiver, initial));
+            }
+            Err(error) => log::warn!("failed to accept live timing client: {}", error),
+        }
+    }
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    mut receiver: broadcast::Receiver<String>,
+    initial: [Option<String>; 2],
+) {
+    let websocket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(websocket) => websocket,
+        Err(error) => {
+            log::warn!("live timing client failed the handshake: {}", error);
+            return;
+        }
+    };
+    let (mut sink, mut stream) = websocket.split();
+
+    for message in initial.into_iter().flatten() {
+        if sink.send(Message::Text(message)).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            message = receiver.recv() => match message {
+                Ok(message) => {
+                    if sink.send(Message::Text(message)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    log::warn!("live timing client missed {} messages", count);
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            incoming = stream.next() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
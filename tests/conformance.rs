@@ -0,0 +1,76 @@
+//! Replays the traces of the conformance corpus in `conformance/` through the decoders.
+
+use carrlink::conformance::Trace;
+use carrlink::ChecksumValidation;
+use std::path::PathBuf;
+
+fn trace_paths() -> Vec<PathBuf> {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("conformance");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "trace")
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn traces_decode_as_expected() {
+    for path in trace_paths() {
+        let trace = Trace::load(&path).unwrap();
+        assert!(
+            trace.firmware.is_some(),
+            "{} does not record its firmware",
+            path.display()
+        );
+        assert!(
+            trace
+                .exchanges
+                .iter()
+                .all(|exchange| exchange.expected.is_some()),
+            "{} has exchanges without an expected outcome",
+            path.display()
+        );
+
+        let mismatches = trace.verify(ChecksumValidation::Strict);
+        assert!(
+            mismatches.is_empty(),
+            "{}: {:?}",
+            path.display(),
+            mismatches
+        );
+    }
+}
+
+#[test]
+fn verify_accepts_matching_outcomes() {
+    let trace = Trace::parse(
+        "# firmware: 5337\n\
+         TX 30 |0|\n\
+         RX 30 35 33 33 37 32 |053372|\n\
+         = version 5337\n",
+    )
+    .unwrap();
+
+    assert_eq!(trace.firmware.as_deref(), Some("5337"));
+    assert!(trace.verify(ChecksumValidation::Strict).is_empty());
+}
+
+#[test]
+fn verify_reports_differing_outcomes() {
+    let trace = Trace::parse(
+        "TX 3F |?|\n\
+         RX 3F |?|\n\
+         = track fuel=0,0,0,0,0,0,0,0 start=0 mode=0 refueling=0 controllers=0\n",
+    )
+    .unwrap();
+
+    let mismatches = trace.verify(ChecksumValidation::Strict);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].line, 2);
+    assert_eq!(mismatches[0].actual, "no_data");
+}
@@ -0,0 +1,116 @@
+//! Cross-checks the encoders and decoders against vectors generated from the Python carreralib.
+//!
+//! The vectors in `tests/differential/vectors.txt` are written by `tests/differential/generate.py`
+//! and cover the nibble order of 32-bit values and the frames, including the checksums, of the
+//! requests. The suite only runs with the `differential` feature, as it needs the generated file.
+
+use carrlink::messages::{
+    make_button_press_request, make_ignore_controllers_request, make_reset_clock_request,
+    make_reset_positions_request,
+};
+use carrlink::protocol::{
+    decode_uint32, encode_uint32, make_set_word_request, WordAddress, UINT32_SIZE,
+};
+use std::path::PathBuf;
+
+/// Returns the fields of every vector line of the given kind.
+fn vectors(kind: &str) -> Vec<Vec<String>> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/differential/vectors.txt");
+    let content = std::fs::read_to_string(&path).unwrap_or_else(|error| {
+        panic!(
+            "{} cannot be read ({}), generate it with tests/differential/generate.py",
+            path.display(),
+            error
+        )
+    });
+
+    let vectors: Vec<Vec<String>> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .filter(|fields| fields[0] == kind)
+        .map(|fields| fields[1..].to_vec())
+        .collect();
+    assert!(
+        !vectors.is_empty(),
+        "no {} vectors in {}",
+        kind,
+        path.display()
+    );
+    vectors
+}
+
+fn parse_u8(field: &str) -> u8 {
+    field.parse().unwrap()
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Creates the request described by the fields of a vector with carrlink.
+fn make_request(fields: &[String]) -> Vec<u8> {
+    let arguments: Vec<u8> = fields[1..].iter().map(|field| parse_u8(field)).collect();
+    match (fields[0].as_str(), arguments.as_slice()) {
+        ("set_speed", &[player, value]) => {
+            make_set_word_request(WordAddress::Speed(player), value).to_vec()
+        }
+        ("set_brake", &[player, value]) => {
+            make_set_word_request(WordAddress::Brake(player), value).to_vec()
+        }
+        ("set_fuel", &[player, value]) => {
+            make_set_word_request(WordAddress::Fuel(player), value).to_vec()
+        }
+        ("set_position", &[player, value]) => {
+            make_set_word_request(WordAddress::Position(player), value).to_vec()
+        }
+        ("set_lap_high", &[value]) => make_set_word_request(WordAddress::LapHigh, value).to_vec(),
+        ("set_lap_low", &[value]) => make_set_word_request(WordAddress::LapLow, value).to_vec(),
+        ("clear_positions", &[]) => make_reset_positions_request().to_vec(),
+        ("press", &[button]) => make_button_press_request(button).to_vec(),
+        ("ignore", &[mask]) => make_ignore_controllers_request(mask).to_vec(),
+        ("reset_clock", &[]) => make_reset_clock_request().to_vec(),
+        _ => panic!("unknown request vector {:?}", fields),
+    }
+}
+
+#[test]
+fn uint32_matches_carreralib() {
+    for fields in vectors("uint32") {
+        let value: u32 = fields[0].parse().unwrap();
+        let encoded: [u8; UINT32_SIZE] = fields[1].as_bytes().try_into().unwrap();
+
+        assert_eq!(
+            encode_uint32(value),
+            encoded,
+            "encoding of {} differs from carreralib",
+            value
+        );
+        assert_eq!(
+            decode_uint32(&encoded),
+            value,
+            "decoding of {} differs from carreralib",
+            fields[1]
+        );
+    }
+}
+
+#[test]
+fn requests_match_carreralib() {
+    for fields in vectors("request") {
+        let (description, frame) = fields.split_at(fields.len() - 1);
+        assert_eq!(
+            make_request(description),
+            decode_hex(&frame[0]),
+            "request {} differs from carreralib",
+            description.join(" ")
+        );
+    }
+}
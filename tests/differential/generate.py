@@ -0,0 +1,87 @@
+#!/usr/bin/env python3
+"""Writes the differential test vectors of carrlink from the Python carreralib.
+
+Run it from the repository root with carreralib installed, e.g.
+
+    pip install carreralib
+    python3 tests/differential/generate.py > tests/differential/vectors.txt
+
+The requests are recorded by handing the carreralib ControlUnit a connection which only keeps
+the frames that are sent, so no control unit is needed.
+"""
+
+import random
+
+import carreralib
+import carreralib.connection
+from carreralib import protocol
+
+
+class RecordingConnection:
+    """Connection which records every frame and answers with the echo of the command."""
+
+    def __init__(self):
+        self.frames = []
+
+    def send(self, buf):
+        self.frames.append(bytes(buf))
+
+    def recv(self, maxlength=None):
+        return self.frames[-1][0:1]
+
+    def close(self):
+        pass
+
+
+def record(connection, request):
+    """Returns the frame which is sent by the given request of the control unit."""
+    del connection.frames[:]
+    try:
+        request()
+    except Exception:
+        # only the sent frame is of interest, not the handling of the echoed response
+        pass
+    return connection.frames[0]
+
+
+def uint32_values():
+    values = [0, 1, 0x0F, 0x10, 0xFF, 0x100, 123456, 0x12345678, 0x87654321, 0xFFFFFFFF]
+    generator = random.Random(1672)
+    values += [generator.getrandbits(32) for _ in range(32)]
+    return values
+
+
+def main():
+    connection = RecordingConnection()
+    carreralib.connection.open = lambda *args, **kwargs: connection
+    cu = carreralib.ControlUnit("recording")
+
+    print("# generated by tests/differential/generate.py from carreralib "
+          + getattr(carreralib, "__version__", "unknown"))
+
+    for value in uint32_values():
+        encoded = protocol.pack("I", value)
+        assert protocol.unpack("I", encoded) == (value,)
+        print("uint32 %d %s" % (value, encoded.decode("ascii")))
+
+    def emit(description, request):
+        print("request %s %s" % (description, record(connection, request).hex()))
+
+    for player in range(8):
+        for value in (0, 7, 15):
+            emit("set_speed %d %d" % (player, value), lambda: cu.setspeed(player, value))
+            emit("set_brake %d %d" % (player, value), lambda: cu.setbrake(player, value))
+            emit("set_fuel %d %d" % (player, value), lambda: cu.setfuel(player, value))
+        emit("set_position %d %d" % (player, player + 1), lambda: cu.setpos(player, player + 1))
+    for value in (0, 9, 15):
+        emit("set_lap_high %d" % value, lambda: cu.setlap_hi(value))
+        emit("set_lap_low %d" % value, lambda: cu.setlap_lo(value))
+    emit("clear_positions", cu.clrpos)
+    for button in (1, 2, 5, 6, 7, 8):
+        emit("press %d" % button, lambda: cu.press(button))
+    for mask in (0x00, 0x01, 0x81, 0xFF):
+        emit("ignore %d" % mask, lambda: cu.ignore(mask))
+    emit("reset_clock", cu.reset)
+
+if __name__ == "__main__":
+    main()